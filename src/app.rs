@@ -1,9 +1,13 @@
 use std::collections::HashMap;
 use winit::event_loop::{ControlFlow, EventLoop, EventLoopBuilder};
 
+mod announce;
 mod apps;
 mod config;
+mod control;
+mod notifications;
 mod osc;
+mod oscquery;
 mod perf;
 mod renderer;
 mod state;
@@ -23,6 +27,9 @@ pub fn init() {
     let mut config = config::init(&mut state);
     let mut apps = apps::init();
     let mut stats = stats::init();
+    let mut control = control::init();
+    let mut oscquery = oscquery::init();
+    let mut announcer = announce::init();
 
     // Create shared event loop for winit + egui + tray-icon events
     // winit::event_loop::EventLoopBuilder::<Event>::with_user_event().build();
@@ -56,6 +63,9 @@ pub fn init() {
             &mut config,
             &mut apps,
             &mut stats,
+            &mut control,
+            &mut oscquery,
+            &mut announcer,
             &mut state,
         )
     });
@@ -72,11 +82,21 @@ fn update(
     tray: &mut tray_icon::TrayIcon,
     renderer: &mut renderer::Renderer,
     ui_draw_call: &mut Box<
-        dyn FnMut(&egui::Context, &mut state::State, &mut config::Config, &winit::window::Window),
+        dyn FnMut(
+            &egui::Context,
+            &mut state::State,
+            &mut config::Config,
+            &apps::Apps,
+            &stats::Stats,
+            &winit::window::Window,
+        ),
     >,
     config: &mut config::Config,
     apps: &mut apps::Apps,
     stats: &mut stats::Stats,
+    control: &mut control::Control,
+    oscquery: &mut oscquery::OscQuery,
+    announcer: &mut announce::Announcer,
     state: &mut state::State,
 ) {
     // Renderer handles a few various winit events outside of redrawing
@@ -95,7 +115,7 @@ fn update(
         perf::start_frame(state);
 
         // Draw Window UI + affect state (immediate mode)
-        renderer::render(window, renderer, ui_draw_call, state, config);
+        renderer::render(window, renderer, ui_draw_call, state, config, apps, stats);
         //
         // Note: timer is finished within the above render call
     }
@@ -113,12 +133,24 @@ fn update(
     }
 
     // Apply any changes to the state
-    apply(control_flow, window, tray, state, config, apps, stats);
+    apply(
+        control_flow,
+        window,
+        tray,
+        state,
+        config,
+        apps,
+        stats,
+        control,
+        oscquery,
+        announcer,
+    );
 }
 
 ///
 /// Step through any changed state flags, apply actions as necessary
 ///
+#[allow(clippy::too_many_arguments)]
 fn apply(
     control_flow: &mut ControlFlow,
     window: &winit::window::Window,
@@ -126,7 +158,10 @@ fn apply(
     state: &mut state::State,
     config: &mut config::Config,
     apps: &mut apps::Apps,
-    _stats: &mut stats::Stats,
+    stats: &mut stats::Stats,
+    control: &mut control::Control,
+    oscquery: &mut oscquery::OscQuery,
+    announcer: &mut announce::Announcer,
     // renderer: &mut renderer::Renderer,
     // tray_menu: &HashMap<String, tray_manager::MenuElement>,
     // ui_draw_call: &mut Box<dyn FnMut(&egui::Context, &mut state::State)>,
@@ -171,19 +206,238 @@ fn apply(
     if state.actions.config_edited {
         state.actions.config_edited = false;
         config.validate_all();
-        config.write(&state.json.filepath);
+
+        config::save_edits(state, config);
+        state.undo.record(config, state.ui.config_watched_app_index);
+    }
+
+    // Reload the config when its file is changed by something else, like a deploy
+    if state.json.watch.poll(&state.json.filepath) {
+        config::file_changed(state, config);
+    }
+
+    // Undo or redo of config edits has been requested
+    if state.actions.config_undo {
+        state.actions.config_undo = false;
+        config::undo(state, config);
+    }
+    if state.actions.config_redo {
+        state.actions.config_redo = false;
+        config::redo(state, config);
     }
 
+    // Config reload has been requested
+    if state.actions.config_reload {
+        state.actions.config_reload = false;
+        reload_config(state, config);
+    }
+
+    // Commands from the remote control port, which may set further action flags below
+    remote_control(control, state, config, apps);
+
     // Build Apps Listeners has been requested
     if state.actions.build_listeners {
         state.actions.build_listeners = false;
-        apps.build_listeners(&config);
+        // The config is locked again each time the apps are watched
+        state.ui.config_unlocked = false;
+        for (name, e) in apps.build_listeners(&config) {
+            notifications::notify(state, &format!("{} is not watched", name), &e.to_string());
+        }
     }
 
     // Destroy Apps Listeners has been requested
     if state.actions.destroy_listeners {
         state.actions.destroy_listeners = false;
-        apps.destroy_listeners();
+        state.ui.config_unlocked = false;
+        let events = apps.destroy_listeners();
+        notify_stop_events(state, events);
+    }
+
+    // Apply config changes to the watched apps, without stopping the untouched ones
+    notify_config_changes(state, apps.update_config(config));
+
+    // Check on the launched watched apps
+    let events = apps.update(stats);
+    notify_stop_events(state, events);
+    for (name, alert) in apps.take_alerts() {
+        notifications::notify(state, &format!("{} alert", name), &alert);
+    }
+
+    // Sample resource usage of the watched apps, restart any that breached a threshold
+    for name in stats.update(apps) {
+        if apps.paused() {
+            continue;
+        }
+        let events = apps.restart(&name, apps::StopReason::ResourceLimit);
+        notify_stop_events(state, events);
+    }
+    for (name, alert) in stats.take_alerts() {
+        notifications::notify(state, &format!("{} telemetry alert", name), &alert);
+    }
+
+    // Serve the latest state to OSCQuery clients
+    if let Some(e) = oscquery.update(&config.oscquery) {
+        notifications::notify(state, "OSCQuery server is not listening", &e.to_string());
+    }
+    oscquery.publish(apps, &config.remote_control);
+
+    // Announce this instance to the multicast group, and hear the others
+    if let Some(e) = announcer.update(&config.announce) {
+        notifications::notify(state, "Not announcing on the network", &e.to_string());
+    }
+    announcer.receive();
+    announcer.publish(apps);
+    state.peers = announcer.peers().to_vec();
+}
+
+///
+/// Read the config file again, keeping the current config if it cannot be parsed
+///
+fn reload_config(state: &mut state::State, config: &mut config::Config) {
+    if !state.json.filepath.exists() {
+        notifications::notify(
+            state,
+            "Config not reloaded",
+            &format!("{:?} does not exist", state.json.filepath),
+        );
+        return;
+    }
+
+    match config::Config::load(&state.json.filepath) {
+        Ok(mut c) => {
+            c.validate_all();
+            *config = c;
+            state.json.parsed = true;
+            state.json.error = None;
+            state.json.changed = None;
+            state.json.dirty = false;
+            state.undo.reset(config, state.ui.config_watched_app_index);
+            config::keep_good_backup(&state.json.filepath);
+            println!("Reloaded config from {:?}", state.json.filepath);
+        }
+        Err(e) => notifications::notify(state, "Config not reloaded", &e.to_string()),
+    }
+}
+
+///
+/// Dispatch the commands received on the remote control port, replying to each
+///
+fn remote_control(
+    control: &mut control::Control,
+    state: &mut state::State,
+    config: &config::Config,
+    apps: &mut apps::Apps,
+) {
+    if let Some(e) = control.update(&config.remote_control) {
+        notifications::notify(state, "Remote control is not listening", &e.to_string());
+    }
+
+    for (command, from) in control.receive() {
+        println!("Remote control command from {}: {:?}", from, command);
+
+        // Commands on apps need them to be watched, and a target to match
+        let target = match &command {
+            control::Command::Start(Some(t))
+            | control::Command::Stop(Some(t))
+            | control::Command::Restart(Some(t)) => Some(t.as_str()),
+            _ => None,
+        };
+        if target.is_some() && !apps.watching() {
+            control.error(
+                from,
+                &command,
+                "not watching, start watching every app first",
+            );
+            continue;
+        }
+        let unmatched = format!("no app or group named {}", target.unwrap_or(""));
+
+        match &command {
+            control::Command::Start(None) if !apps.watching() => {
+                state.actions.build_listeners = true;
+                control.ack(from, &command, "watching every app");
+            }
+            control::Command::Start(_) => match apps.start_apps(target) {
+                0 => control.error(from, &command, &unmatched),
+                n => control.ack(from, &command, &format!("started {} apps", n)),
+            },
+            control::Command::Stop(None) => {
+                if apps.watching() {
+                    state.actions.destroy_listeners = true;
+                    control.ack(from, &command, "stopped watching every app");
+                } else {
+                    control.error(from, &command, "not watching");
+                }
+            }
+            control::Command::Stop(_) => {
+                let (matched, events) = apps.stop_apps(target);
+                notify_stop_events(state, events);
+                match matched {
+                    0 => control.error(from, &command, &unmatched),
+                    n => control.ack(from, &command, &format!("stopped {} apps", n)),
+                }
+            }
+            control::Command::Restart(None) if !apps.watching() => {
+                control.error(from, &command, "not watching");
+            }
+            control::Command::Restart(_) => {
+                let (matched, events) = apps.restart_apps(target);
+                notify_stop_events(state, events);
+                match matched {
+                    0 => control.error(from, &command, &unmatched),
+                    n => control.ack(from, &command, &format!("restarted {} apps", n)),
+                }
+            }
+            control::Command::Pause | control::Command::Resume if !apps.watching() => {
+                control.error(from, &command, "not watching");
+            }
+            control::Command::Pause => {
+                apps.pause();
+                control.ack(from, &command, "supervision paused");
+            }
+            control::Command::Resume => {
+                apps.resume();
+                control.ack(from, &command, "supervision resumed");
+            }
+            control::Command::Reload => {
+                state.actions.config_reload = true;
+                control.ack(from, &command, "reloading config");
+            }
+            control::Command::Query => control.reply_status(from, apps),
+        }
+    }
+}
+
+///
+/// Raises a notification for each watched app that went down
+///
+fn notify_stop_events(state: &mut state::State, events: Vec<(String, apps::StopEvent)>) {
+    for (name, event) in events {
+        notifications::notify(
+            state,
+            &format!("{} went down", name),
+            &event.reason.to_string(),
+        );
+    }
+}
+
+fn notify_config_changes(state: &mut state::State, changes: apps::ConfigChanges) {
+    let lists = [
+        ("Started", &changes.started),
+        ("Stopped", &changes.stopped),
+        ("Restarted", &changes.restarted),
+        ("Updated", &changes.updated),
+    ];
+    let body: Vec<String> = lists
+        .iter()
+        .filter(|(_, names)| !names.is_empty())
+        .map(|(what, names)| format!("{}: {}", what, names.join(", ")))
+        .collect();
+    if !body.is_empty() {
+        notifications::notify(state, "Config applied to watched apps", &body.join("\n"));
+    }
+    for (name, e) in changes.errors {
+        notifications::notify(state, &format!("{} is not watched", name), &e.to_string());
     }
 }
 
@@ -0,0 +1,335 @@
+use super::apps;
+use super::config;
+use super::osc;
+
+use chrono::{DateTime, Local};
+use rosc::{encoder, OscBundle, OscMessage, OscPacket, OscTime, OscType};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+// Other instances are forgotten after missing this many of their announcements
+const MISSED_ANNOUNCEMENTS: u32 = 3;
+
+//
+// Announce
+//
+// Every few seconds, sends a summary of this Watchdog instance to a multicast group, for
+// installs with several PCs, and listens on the same group for the other instances.
+// Each announcement is a bundle, so it arrives whole or not at all:
+//
+// /watchdog/announce host pid interval watching paused apps
+// /watchdog/announce/app name group state restarts, for each app
+//
+// The pid tells instances on the same host apart. The socket is shared with other
+// programs on the port, so several instances (or a dashboard) can join the group on one PC
+//
+
+#[derive(Debug, Clone)]
+pub struct Peer {
+    pub host: String,
+    pub pid: i32,
+    pub from: SocketAddr,
+    pub watching: bool,
+    pub paused: bool,
+    pub apps: Vec<PeerApp>,
+    pub received: DateTime<Local>,
+    // Seconds between the peer's announcements, to tell when it has gone quiet
+    interval: u64,
+    heard: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub struct PeerApp {
+    pub name: String,
+    pub group: String,
+    pub state: String,
+    pub restarts: i32,
+}
+
+pub struct Announcer {
+    socket: Option<UdpSocket>,
+    // Group and interface the config asks for, to rejoin when it changes
+    joined: Option<(SocketAddr, Ipv4Addr)>,
+    // Joining again after it failed
+    retry: osc::Retry,
+    interval: Duration,
+    last_sent: Option<Instant>,
+    host: String,
+    // Other instances heard on the group, in the order they were first heard
+    peers: Vec<Peer>,
+}
+
+impl Announcer {
+    pub fn new() -> Self {
+        Announcer {
+            socket: None,
+            joined: None,
+            retry: osc::Retry::new(),
+            interval: Duration::from_secs(5),
+            last_sent: None,
+            host: host_name(),
+            peers: Vec::new(),
+        }
+    }
+
+    //
+    // Join, rejoin or leave the multicast group to match the config, called every tick
+    // of the event loop. A failed join is retried with a backoff. Returns the error if
+    // joining failed on this tick, the first time only
+    //
+    pub fn update(&mut self, c: &config::Announce) -> Option<osc::BindError> {
+        self.interval = Duration::from_secs(u64::from(&c.interval.val).max(1));
+
+        let wanted = if c.enabled && c.valid {
+            let group = String::from(&c.group.val).parse::<IpAddr>().ok();
+            let interface = String::from(&c.interface.val).parse::<Ipv4Addr>().ok();
+            match (group, interface) {
+                (Some(g), Some(i)) => Some((SocketAddr::new(g, u16::from(&c.port.val)), i)),
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        if wanted != self.joined {
+            self.joined = wanted;
+            self.socket = None;
+            self.last_sent = None;
+            self.peers.clear();
+            self.retry.reset();
+        }
+
+        let (group, interface) = self.joined?;
+        if self.socket.is_some() || !self.retry.due() {
+            return None;
+        }
+        match join(group, interface) {
+            Ok(s) => {
+                println!("Announcing on multicast group {}", group);
+                self.socket = Some(s);
+                self.retry.reset();
+                None
+            }
+            Err(e) => {
+                let e = osc::BindError::from_io(group, e);
+                let (wait, first) = self.retry.failed();
+                println!(
+                    "Announce failed to join multicast group: {}, retrying in {:?}",
+                    e, wait
+                );
+                first.then_some(e)
+            }
+        }
+    }
+
+    // Send the summary of this instance when due
+    pub fn publish(&mut self, apps: &apps::Apps) {
+        let (socket, (group, _)) = match (&self.socket, &self.joined) {
+            (Some(s), Some(j)) => (s, j),
+            _ => return,
+        };
+        if let Some(last) = self.last_sent {
+            if last.elapsed() < self.interval {
+                return;
+            }
+        }
+        self.last_sent = Some(Instant::now());
+
+        let mut content = vec![OscPacket::Message(OscMessage {
+            addr: "/watchdog/announce".to_string(),
+            args: vec![
+                OscType::String(self.host.clone()),
+                OscType::Int(std::process::id() as i32),
+                OscType::Int(self.interval.as_secs() as i32),
+                OscType::Int(apps.watching() as i32),
+                OscType::Int(apps.paused() as i32),
+                OscType::Int(apps.iter().count() as i32),
+            ],
+        })];
+        for a in apps.iter() {
+            content.push(OscPacket::Message(OscMessage {
+                addr: "/watchdog/announce/app".to_string(),
+                args: vec![
+                    OscType::String(a.name().to_string()),
+                    OscType::String(a.group()),
+                    OscType::String(a.state().name().to_string()),
+                    OscType::Int(a.restarts() as i32),
+                ],
+            }));
+        }
+
+        // A time tag of 1 means immediately
+        let packet = OscPacket::Bundle(OscBundle {
+            timetag: OscTime {
+                seconds: 0,
+                fractional: 1,
+            },
+            content,
+        });
+        let msg_buf = match encoder::encode(&packet) {
+            Ok(b) => b,
+            Err(e) => {
+                println!("Announce failed to encode: {:?}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = socket.send_to(&msg_buf, group) {
+            println!("Announce failed to send to {}: {}", group, e);
+        }
+    }
+
+    // Read every announcement waiting on the group, without blocking
+    pub fn receive(&mut self) {
+        let socket = match &self.socket {
+            Some(s) => s,
+            None => return,
+        };
+
+        let mut buffer = [0u8; rosc::decoder::MTU];
+        let mut received = Vec::new();
+        loop {
+            match socket.recv_from(&mut buffer) {
+                Ok((size, from)) => match rosc::decoder::decode_udp(&buffer[..size]) {
+                    Ok((_, packet)) => {
+                        if let Some(p) = Peer::from_packet(packet, from) {
+                            received.push(p);
+                        }
+                    }
+                    Err(e) => println!("Announce dropped invalid OSC packet: {:?}", e),
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                // Windows reports an unreachable sender on the next receive, keep reading
+                Err(e) if e.kind() == ErrorKind::ConnectionReset => continue,
+                Err(e) => {
+                    println!("Announce failed to receive: {}", e);
+                    break;
+                }
+            }
+        }
+
+        for p in received {
+            // Our own announcements come back to the group too
+            if p.host == self.host && p.pid == std::process::id() as i32 {
+                continue;
+            }
+            match self
+                .peers
+                .iter_mut()
+                .find(|q| q.host == p.host && q.pid == p.pid)
+            {
+                Some(q) => *q = p,
+                None => {
+                    println!("Heard Watchdog on {} from {}", p.host, p.from);
+                    self.peers.push(p);
+                }
+            }
+        }
+
+        self.peers.retain(|p| {
+            let heard_lately =
+                p.heard.elapsed() < Duration::from_secs(p.interval) * MISSED_ANNOUNCEMENTS;
+            if !heard_lately {
+                println!("Lost Watchdog on {}", p.host);
+            }
+            heard_lately
+        });
+    }
+
+    // Other instances heard on the group
+    pub fn peers(&self) -> &[Peer] {
+        &self.peers
+    }
+}
+
+impl Peer {
+    // An announcement from another instance, or None if the packet is not one
+    fn from_packet(packet: OscPacket, from: SocketAddr) -> Option<Self> {
+        let bundle = match packet {
+            OscPacket::Bundle(b) => b,
+            OscPacket::Message(_) => return None,
+        };
+        let mut messages = bundle.content.into_iter().filter_map(|p| match p {
+            OscPacket::Message(m) => Some(m),
+            OscPacket::Bundle(_) => None,
+        });
+
+        let msg = messages.next()?;
+        let mut peer = match (msg.addr.as_str(), &msg.args[..]) {
+            (
+                "/watchdog/announce",
+                [OscType::String(host), OscType::Int(pid), OscType::Int(interval), OscType::Int(watching), OscType::Int(paused), ..],
+            ) => Peer {
+                host: host.clone(),
+                pid: *pid,
+                from,
+                watching: *watching != 0,
+                paused: *paused != 0,
+                apps: Vec::new(),
+                received: Local::now(),
+                interval: (*interval).max(1) as u64,
+                heard: Instant::now(),
+            },
+            _ => return None,
+        };
+
+        for msg in messages {
+            if let (
+                "/watchdog/announce/app",
+                [OscType::String(name), OscType::String(group), OscType::String(state), OscType::Int(restarts)],
+            ) = (msg.addr.as_str(), &msg.args[..])
+            {
+                peer.apps.push(PeerApp {
+                    name: name.clone(),
+                    group: group.clone(),
+                    state: state.clone(),
+                    restarts: *restarts,
+                });
+            }
+        }
+
+        Some(peer)
+    }
+}
+
+//
+// A socket bound to the group's port, sharing it with other programs, and joined to the
+// group. Only IPv4 groups are joined on a chosen interface, IPv6 uses the default one
+//
+fn join(group: SocketAddr, interface: Ipv4Addr) -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::for_address(group), Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+
+    match group.ip() {
+        IpAddr::V4(g) => {
+            let any = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), group.port());
+            socket.bind(&any.into())?;
+            socket.join_multicast_v4(&g, &interface)?;
+            socket.set_multicast_if_v4(&interface)?;
+            socket.set_multicast_loop_v4(true)?;
+        }
+        IpAddr::V6(g) => {
+            let any = SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), group.port());
+            socket.bind(&any.into())?;
+            socket.join_multicast_v6(&g, 0)?;
+            socket.set_multicast_loop_v6(true)?;
+        }
+    }
+
+    socket.set_nonblocking(true)?;
+    Ok(socket.into())
+}
+
+// Name of this PC, as shown to the other instances
+fn host_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .or_else(|_| std::fs::read_to_string("/etc/hostname").map(|h| h.trim().to_string()))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+pub fn init() -> Announcer {
+    Announcer::new()
+}
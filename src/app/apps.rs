@@ -1,120 +1,901 @@
 use super::config;
+use super::stats;
 use crate::app::osc;
-use rosc;
+use crate::utils::now;
+use chrono::{DateTime, Local, NaiveDate, NaiveTime};
 
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::VecDeque;
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+mod cgroup;
+mod health;
 
 pub struct Apps {
     watching: bool,
+    // Supervision paused, apps keep running but are not checked or restarted
+    paused: bool,
+    // Shared by every app, while watching
+    reactor: Option<osc::Reactor>,
     valid_listeners: bool,
     watched_apps: Vec<App>,
+    // The watched apps' config as it was last applied, and a changed config waiting to settle
+    applied: String,
+    pending: Option<(String, Instant)>,
 }
 
 pub struct App {
     name: String,
-    interface: Arc<Mutex<osc::Interface>>,
+    // Only bound when one of the app's health checks is an OSC heartbeat,
+    // and handed over to the OSC reactor by start
+    interface: Option<osc::Interface>,
+    osc_interval: Option<Duration>,
+    // Registration with the OSC reactor, while watching
+    osc_token: Option<mio::Token>,
+    // Round trip times of the heartbeats, measured by the interface
+    latency: Option<Arc<Mutex<osc::Latency>>>,
+    // Latest telemetry reported in the heartbeats
+    telemetry: Option<Arc<Mutex<osc::Telemetry>>>,
+    // Messages the app sent over OSC, taken on each update
+    inbox: Option<Arc<Mutex<Vec<osc::AppMessage>>>>,
+    // Every OSC packet sent and received, for the traffic inspector
+    traffic: Option<Arc<Mutex<osc::Traffic>>>,
+    // Whether the app is connected, for the OSC over TCP transport only,
+    // and whether it was on the last update, to raise an alert when it drops
+    connected: Option<Arc<AtomicBool>>,
+    was_connected: bool,
+    // Latest status the app reported about itself
+    reported_status: Option<String>,
+    // Alerts the app raised, until taken for notifications
+    alerts: Vec<String>,
+    // Why the OSC interface could not be bound, the app is not watched when set
+    bind_error: Option<osc::BindError>,
     config: config::WatchedApp,
-    loop_thread: Option<thread::JoinHandle<()>>,
-    listener_thread: Option<thread::JoinHandle<()>>,
+    threads: Vec<thread::JoinHandle<()>>,
     watching: Arc<AtomicBool>,
+    // Health checks, until they are moved onto their threads by start
+    pending_checks: Vec<Box<dyn health::HealthCheck>>,
+    checks: Vec<health::CheckHandle>,
+    // Passed by the OSC reactor when a heartbeat is received
+    osc_status: health::Status,
+    process: Option<Child>,
+    // Process id shared with the health check threads, 0 when not running
+    pid: Arc<AtomicU32>,
+    cgroup: Option<cgroup::Cgroup>,
+    state: AppState,
+    events: VecDeque<StopEvent>,
+    restarts: u32,
+    // Day the daily scheduled restart last happened
+    last_scheduled: Option<NaiveDate>,
+    // Secondary liveness check that is currently failing, and since when
+    hang: Option<(Hang, Duration)>,
+    // Built from a changed config while supervision was paused, and launched when it resumes
+    launch_on_resume: bool,
+}
+
+// How long the config must stay unchanged before it is applied to the watched apps
+const APPLY_DELAY: Duration = Duration::from_secs(2);
+
+// Keep the most recent stop events of each app only
+const MAX_STOP_EVENTS: usize = 50;
+
+pub enum AppState {
+    Stopped,
+    // Launched, waiting for the first heartbeat
+    Starting { since: Duration },
+    Running,
+    // Down, waiting out the restart delay
+    Restarting { at: Duration },
+}
+
+impl AppState {
+    // Short name of the state, as reported to remote control and OSCQuery clients
+    pub fn name(&self) -> &'static str {
+        match self {
+            AppState::Stopped => "stopped",
+            AppState::Starting { .. } => "starting",
+            AppState::Running => "running",
+            AppState::Restarting { .. } => "restarting",
+        }
+    }
+}
+
+// Why a launched app went down
+#[derive(Clone)]
+pub enum StopReason {
+    // Exited on its own, 0 is a clean exit
+    Exited(i32),
+    // Killed by a signal, on unix
+    #[cfg_attr(not(unix), allow(dead_code))]
+    Signaled(i32),
+    // A health check did not pass within its timeout, or passed only while the app looked hung
+    HeartbeatTimeout(Option<Hang>),
+    StartupTimeout,
+    // Killed by the kernel for going over the memory max of its cgroup
+    Oom,
+    // Restarted for staying over a memory or cpu limit
+    ResourceLimit,
+    // The app's OSC connection over TCP dropped, and was not back before the heartbeat timed out
+    ConnectionLost,
+    Operator,
+    Scheduled,
+    // Restarted to apply a change to the fields it is launched with
+    ConfigChanged,
+    // The app asked to be restarted over OSC, with an optional reason
+    Requested(Option<String>),
+}
+
+impl std::fmt::Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopReason::Exited(0) => write!(f, "exited cleanly"),
+            StopReason::Exited(code) => write!(f, "exited with code {}", code),
+            StopReason::Signaled(signal) => write!(f, "killed by signal {}", signal),
+            StopReason::HeartbeatTimeout(None) => write!(f, "heartbeat timed out"),
+            StopReason::HeartbeatTimeout(Some(hang)) => {
+                write!(f, "heartbeat timed out, app looked hung: {}", hang)
+            }
+            StopReason::StartupTimeout => write!(f, "startup timed out"),
+            StopReason::Oom => write!(f, "killed by the kernel, out of memory"),
+            StopReason::ResourceLimit => write!(f, "restarted for exceeding a resource limit"),
+            StopReason::ConnectionLost => write!(f, "heartbeat timed out, OSC connection lost"),
+            StopReason::Operator => write!(f, "killed by operator"),
+            StopReason::Scheduled => write!(f, "scheduled restart"),
+            StopReason::ConfigChanged => write!(f, "restarted for a config change"),
+            StopReason::Requested(None) => write!(f, "restart requested by the app"),
+            StopReason::Requested(Some(reason)) => {
+                write!(f, "restart requested by the app: {}", reason)
+            }
+        }
+    }
+}
+
+impl StopReason {
+    // Classify the exit status of a process that went down on its own
+    fn from_status(status: ExitStatus) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if let Some(signal) = status.signal() {
+                return StopReason::Signaled(signal);
+            }
+        }
+        StopReason::Exited(status.code().unwrap_or(-1))
+    }
+}
+
+// Secondary liveness checks, any of which counts as missed heartbeats while it fails
+#[derive(Clone, Copy, PartialEq)]
+pub enum Hang {
+    CpuIdle,
+    CpuPinned,
+    // Process stuck in uninterruptible sleep (D state)
+    Uninterruptible,
+    LogSilent,
+}
+
+impl std::fmt::Display for Hang {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Hang::CpuIdle => write!(f, "cpu idle"),
+            Hang::CpuPinned => write!(f, "cpu pinned"),
+            Hang::Uninterruptible => write!(f, "uninterruptible sleep"),
+            Hang::LogSilent => write!(f, "log silent"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct StopEvent {
+    pub time: DateTime<Local>,
+    pub reason: StopReason,
 }
 
 impl App {
     pub fn new(c: &config::WatchedApp) -> Self {
-        let interface = Arc::new(Mutex::new(osc::Interface::build(c).unwrap()));
+        let osc_status = health::Status::new();
+        let checks = health::build(c, &osc_status);
+
+        let mut bind_error = None;
+        let interface = if checks.osc_interval.is_some() {
+            match osc::Interface::build(c) {
+                Ok(i) => Some(i),
+                Err(e) => {
+                    println!("App {} cannot be watched: {}", String::from(&c.name.val), e);
+                    bind_error = Some(e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let latency = interface.as_ref().map(|i| i.latency());
+        let telemetry = interface.as_ref().map(|i| i.telemetry());
+        let inbox = interface.as_ref().map(|i| i.inbox());
+        let traffic = interface.as_ref().map(|i| i.traffic());
+        let connected = interface.as_ref().and_then(|i| i.connected());
         let config = c.clone();
         let name = String::from(&c.name.val);
         let watching = Arc::new(AtomicBool::new(false));
+
+        // Do not restart right away if today's scheduled time has already passed
+        let local = Local::now();
+        let last_scheduled = match schedule_time(c) {
+            Some(t) if local.time() >= t => Some(local.date_naive()),
+            _ => None,
+        };
+
         App {
             name,
             interface,
+            osc_interval: checks.osc_interval,
+            osc_token: None,
+            latency,
+            telemetry,
+            inbox,
+            traffic,
+            connected,
+            was_connected: false,
+            reported_status: None,
+            alerts: Vec::new(),
+            bind_error,
             config,
-            loop_thread: None,
-            listener_thread: None,
+            threads: Vec::new(),
             watching,
+            pending_checks: checks.probed,
+            checks: checks.handles,
+            osc_status,
+            process: None,
+            pid: Arc::new(AtomicU32::new(0)),
+            cgroup: None,
+            state: AppState::Stopped,
+            events: VecDeque::new(),
+            restarts: 0,
+            last_scheduled,
+            hang: None,
+            launch_on_resume: false,
         }
     }
 
-    pub fn start(&mut self) {
-        self.watching.store(true, Ordering::Relaxed);
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-        //
-        // Heartbeat Sending thread
-        let interface = self.interface.clone();
-        let interval: u64 = u64::from(&self.config.heartbeat_interval.val);
-        let watching = Arc::clone(&self.watching);
-
-        let t = thread::spawn(move || loop {
-            if watching.load(Ordering::Relaxed) {
-                {
-                    //
-                    // TODO read from the channel and see if we should STOP
-                    let i = interface.lock().unwrap();
-                    i.send_heartbeat();
-                    println!("Sending heartbeat...");
-                }
-                thread::sleep(Duration::from_secs(interval));
-            } else {
-                println!("Stopping heartbeat");
+    pub fn config(&self) -> &config::WatchedApp {
+        &self.config
+    }
+
+    // OS process id of the launched child, if it is running
+    pub fn pid(&self) -> Option<u32> {
+        self.process.as_ref().map(|p| p.id())
+    }
+
+    pub fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    // Stop events, oldest first
+    pub fn events(&self) -> &VecDeque<StopEvent> {
+        &self.events
+    }
+
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+
+    pub fn hang(&self) -> Option<Hang> {
+        self.hang.map(|(h, _)| h)
+    }
+
+    pub fn checks(&self) -> &[health::CheckHandle] {
+        &self.checks
+    }
+
+    pub fn latency(&self) -> Option<std::sync::MutexGuard<'_, osc::Latency>> {
+        self.latency.as_ref().map(|l| l.lock().unwrap())
+    }
+
+    // None for apps without an OSC heartbeat check
+    pub fn traffic(&self) -> Option<std::sync::MutexGuard<'_, osc::Traffic>> {
+        self.traffic.as_ref().map(|t| t.lock().unwrap())
+    }
+
+    // Copy of the latest telemetry, empty for apps that do not report any
+    pub fn telemetry(&self) -> osc::Telemetry {
+        match &self.telemetry {
+            Some(t) => t.lock().unwrap().clone(),
+            None => osc::Telemetry::new(),
+        }
+    }
+
+    pub fn group(&self) -> String {
+        String::from(&self.config.group.val)
+    }
+
+    // An app matches a remote control target by its name (as is, or as in its OSCQuery
+    // address) or group, and every app matches no target
+    pub fn matches(&self, target: Option<&str>) -> bool {
+        match target {
+            Some(t) => self.name == t || osc::address_segment(&self.name) == t || self.group() == t,
+            None => true,
+        }
+    }
+
+    // Resuming supervision, apps have to pass their health checks again, as if just launched
+    pub fn resume(&mut self) {
+        if self.launch_on_resume {
+            self.launch();
+        } else if matches!(self.state, AppState::Running) {
+            self.state = AppState::Starting { since: now() };
+        }
+    }
+
+    pub fn reported_status(&self) -> Option<&str> {
+        self.reported_status.as_deref()
+    }
+
+    pub fn bind_error(&self) -> Option<&osc::BindError> {
+        self.bind_error.as_ref()
+    }
+
+    // Launch the child process from the Run string of the config
+    pub fn launch(&mut self) {
+        self.launch_on_resume = false;
+        if self.process.is_some() {
+            println!("App {} is already running", self.name);
+            return;
+        }
+
+        // Retry after the restart delay, unless launched below
+        self.state = AppState::Restarting {
+            at: now() + self.restart_delay(),
+        };
+
+        let run = String::from(&self.config.run.val);
+        let mut parts = split_command(&run).into_iter();
+        let program = match parts.next() {
+            Some(program) => program,
+            None => {
+                println!("App {} has nothing to run", self.name);
                 return;
             }
-        });
-        self.loop_thread = Some(t);
+        };
+
+        // Limits are applied by a cgroup, when any are configured and cgroup v2 is available
+        let limits = cgroup::Limits::from_config(&self.config);
+        if limits.any() && self.cgroup.is_none() {
+            match cgroup::Cgroup::create(&self.name, &limits) {
+                Ok(c) => self.cgroup = Some(c),
+                Err(e) => println!("Resource limits not applied to app {}: {}", self.name, e),
+            }
+        }
+
+        match Command::new(&program).args(parts).spawn() {
+            Ok(child) => {
+                println!("Launched app {} with pid {}", self.name, child.id());
+                // Note the child runs briefly outside the cgroup, until it is moved in here
+                if let Some(c) = &self.cgroup {
+                    if let Err(e) = c.add(child.id()) {
+                        println!("Resource limits not applied to app {}: {}", self.name, e);
+                    }
+                }
+                self.pid.store(child.id(), Ordering::Relaxed);
+                // Telemetry and status of the previous process no longer apply
+                if let Some(t) = &self.telemetry {
+                    t.lock().unwrap().clear();
+                }
+                self.reported_status = None;
+                self.process = Some(child);
+                self.state = AppState::Starting { since: now() };
+            }
+            Err(e) => {
+                println!("Failed to launch app {}: {}", self.name, e);
+            }
+        }
+    }
+
+    // Kill the child process, if it is running, and any processes it started in its cgroup
+    pub fn kill(&mut self) {
+        self.pid.store(0, Ordering::Relaxed);
+        if let Some(mut child) = self.process.take() {
+            let _ = child.kill();
+            // Reap the process so it does not linger as a zombie
+            let _ = child.wait();
+            println!("Killed app {}", self.name);
+        }
+        if let Some(c) = self.cgroup.take() {
+            c.remove();
+        }
+    }
+
+    //
+    // Step the app state machine, called every tick of the event loop, with the result of the
+    // resource based hang checks. Returns the stop event, if the app went down on this tick
+    //
+    pub fn update(&mut self, hang: Option<Hang>) -> Option<StopEvent> {
+        let n = now();
+
+        // Track when a hang check started failing, heartbeats after that do not count
+        let hang = hang.or_else(|| self.log_silent());
+        self.hang = match (hang, self.hang) {
+            (Some(h), Some((previous, since))) if h == previous => Some((h, since)),
+            (Some(h), _) => {
+                println!("App {} looks hung: {}", self.name, h);
+                Some((h, n))
+            }
+            (None, _) => None,
+        };
+
+        // Messages the app sent, a restart request is handled like an operator restart
+        if let Some(restart) = self.receive_messages() {
+            return restart;
+        }
+
+        self.watch_connection();
+
+        // The process exited on its own
+        if let Some(Ok(Some(status))) = self.process.as_mut().map(|p| p.try_wait()) {
+            self.process = None;
+            self.pid.store(0, Ordering::Relaxed);
+            let oom = self.cgroup.as_mut().is_some_and(|c| c.oom_killed());
+            let reason = if oom {
+                StopReason::Oom
+            } else {
+                StopReason::from_status(status)
+            };
+            return Some(self.went_down(reason));
+        }
+
+        match self.state {
+            AppState::Starting { since } => {
+                if self.checks.iter().all(|c| c.status.last_pass() > since) {
+                    println!("App {} is up", self.name);
+                    self.state = AppState::Running;
+                } else if n.saturating_sub(since) > self.startup_timeout() {
+                    self.kill();
+                    return Some(self.went_down(StopReason::StartupTimeout));
+                }
+            }
+            AppState::Running => {
+                // Passes after a hang check started failing do not count
+                let failing = self.checks.iter().find(|c| {
+                    let last_pass = match self.hang {
+                        Some((_, since)) => c.status.last_pass().min(since),
+                        None => c.status.last_pass(),
+                    };
+                    n.saturating_sub(last_pass) > c.timeout
+                });
+                if let Some(c) = failing {
+                    println!("App {} failed health check: {}", self.name, c.description);
+                    let reason = if self.connection_lost() {
+                        StopReason::ConnectionLost
+                    } else {
+                        StopReason::HeartbeatTimeout(self.hang())
+                    };
+                    self.kill();
+                    return Some(self.went_down(reason));
+                }
+            }
+            AppState::Restarting { at } => {
+                if n >= at {
+                    self.launch();
+                }
+            }
+            AppState::Stopped => {}
+        }
+
+        // Apps stopped by the operator stay stopped
+        if !matches!(self.state, AppState::Stopped) && self.scheduled_restart_due() {
+            return self.restart(StopReason::Scheduled);
+        }
+
+        None
+    }
+
+    //
+    // Handle the messages the app sent since the last update. Returns Some, with the
+    // stop event of the restart, if the app asked to be restarted while running
+    //
+    fn receive_messages(&mut self) -> Option<Option<StopEvent>> {
+        let messages = match &self.inbox {
+            Some(inbox) => std::mem::take(&mut *inbox.lock().unwrap()),
+            None => return None,
+        };
+
+        let mut restart = None;
+        for m in messages {
+            match m {
+                osc::AppMessage::Status(status) => {
+                    println!("App {} status: {}", self.name, status);
+                    self.reported_status = Some(status);
+                }
+                osc::AppMessage::Alert(alert) => {
+                    println!("App {} alert: {}", self.name, alert);
+                    self.alerts.push(alert);
+                }
+                osc::AppMessage::RequestRestart(reason) => {
+                    if self.process.is_some() {
+                        restart = Some(reason);
+                    }
+                }
+            }
+        }
+
+        restart.map(|reason| self.restart(StopReason::Requested(reason)))
+    }
+
+    //
+    // A dropped OSC connection is raised as an alert right away, the app is restarted
+    // if it has not connected again by the time its heartbeat times out
+    //
+    fn watch_connection(&mut self) {
+        let connected = match &self.connected {
+            Some(c) => c.load(Ordering::Relaxed),
+            None => return,
+        };
+
+        if self.was_connected && !connected && matches!(self.state, AppState::Running) {
+            println!("App {} OSC connection lost", self.name);
+            self.alerts.push("OSC connection lost".to_string());
+        }
+        self.was_connected = connected;
+    }
+
+    fn connection_lost(&self) -> bool {
+        self.connected
+            .as_ref()
+            .is_some_and(|c| !c.load(Ordering::Relaxed))
+    }
+
+    // None for apps not using the OSC over TCP transport
+    pub fn connected(&self) -> Option<bool> {
+        self.connected.as_ref().map(|c| c.load(Ordering::Relaxed))
+    }
+
+    pub fn take_alerts(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.alerts)
+    }
+
+    // Record a stop event for an app that is down, and wait out the restart delay
+    fn went_down(&mut self, reason: StopReason) -> StopEvent {
+        println!("App {} went down: {}", self.name, reason);
+        let event = self.record(reason);
+        self.restarts += 1;
+        self.state = AppState::Restarting {
+            at: now() + self.restart_delay(),
+        };
+        event
+    }
+
+    fn record(&mut self, reason: StopReason) -> StopEvent {
+        let event = StopEvent {
+            time: Local::now(),
+            reason,
+        };
+        self.events.push_back(event.clone());
+
+        // Ensure the list is below max length, removing oldest elements
+        while self.events.len() > MAX_STOP_EVENTS {
+            self.events.pop_front();
+        }
+
+        event
+    }
+
+    // Restart right away, returns the stop event if the app was running
+    pub fn restart(&mut self, reason: StopReason) -> Option<StopEvent> {
+        let running = self.process.is_some();
+        self.kill();
+
+        let event = if running {
+            println!("App {} restarting: {}", self.name, reason);
+            self.restarts += 1;
+            Some(self.record(reason))
+        } else {
+            None
+        };
+
+        self.launch();
+        event
+    }
+
+    // Once per day, at the configured time of day
+    fn scheduled_restart_due(&mut self) -> bool {
+        let t = match schedule_time(&self.config) {
+            Some(t) => t,
+            None => return false,
+        };
+
+        let local = Local::now();
+        let today = local.date_naive();
+        if local.time() >= t && self.last_scheduled != Some(today) {
+            self.last_scheduled = Some(today);
+            return true;
+        }
+
+        false
+    }
+
+    // The app's log file has not been written to within the silence timeout
+    fn log_silent(&self) -> Option<Hang> {
+        let timeout = u64::from(&self.config.log_silence_timeout.val);
+        let path = String::from(&self.config.log_file.val);
+        if timeout == 0 || path.is_empty() || !matches!(self.state, AppState::Running) {
+            return None;
+        }
+
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        let silent = modified.elapsed().unwrap_or_default();
+        (silent > Duration::from_secs(timeout)).then_some(Hang::LogSilent)
+    }
+
+    fn startup_timeout(&self) -> Duration {
+        Duration::from_secs(u64::from(&self.config.startup_timeout.val))
+    }
+
+    fn restart_delay(&self) -> Duration {
+        Duration::from_secs(u64::from(&self.config.restart_delay.val))
+    }
+
+    pub fn start(&mut self, reactor: Option<&mut osc::Reactor>) {
+        if self.watch(reactor) {
+            self.launch();
+        }
+    }
+
+    //
+    // Start the health checks and hand the OSC interface to the reactor, without launching.
+    // Returns false if the app cannot be watched
+    //
+    fn watch(&mut self, reactor: Option<&mut osc::Reactor>) -> bool {
+        // Without its OSC interface the app could never be seen as up
+        if self.bind_error.is_some() {
+            return false;
+        }
+
+        self.watching.store(true, Ordering::Relaxed);
 
         //
-        // Listener thread
-        let interface = self.interface.clone();
-        let watching = Arc::clone(&self.watching);
+        // Health check threads, each probing once per its interval
+        for mut check in self.pending_checks.drain(..) {
+            let watching = Arc::clone(&self.watching);
+            let pid = Arc::clone(&self.pid);
 
-        let t = thread::spawn(move || {
-            let mut buffer = [0u8; rosc::decoder::MTU];
-            loop {
+            let t = thread::spawn(move || loop {
                 if watching.load(Ordering::Relaxed) {
-                    {
-                        let i = interface.lock().unwrap();
-                        let received_heartbeat = i.listen(&mut buffer);
-                        if received_heartbeat {
-                            println!("Received heartbeat for app.");
-                            //
-                            // TODO use channel msg to report back..?
-                            //
-                        }
-                    }
-                    thread::sleep(Duration::from_millis(100));
+                    check.probe(pid.load(Ordering::Relaxed));
+                    thread::sleep(check.interval());
                 } else {
-                    println!("Stopping listener");
+                    println!("Stopping health check: {}", check.describe());
                     return;
                 }
+            });
+            self.threads.push(t);
+        }
+
+        //
+        // OSC heartbeats, sent and received by the reactor
+        if let (Some(interface), Some(interval)) = (self.interface.take(), self.osc_interval) {
+            match reactor {
+                Some(reactor) => {
+                    let osc_status = self.osc_status.clone();
+                    let on_heartbeat = Box::new(move || osc_status.pass());
+                    self.osc_token = Some(reactor.register(interface, interval, on_heartbeat));
+                }
+                None => println!("App {} has no OSC reactor to send heartbeats", self.name),
             }
-        });
-        self.listener_thread = Some(t);
+        }
 
-        // println!("Start heartbeat thread from App instance");
+        true
     }
 
-    pub fn stop(&mut self) {
+    // Stop watching, returns the stop event if the app was running
+    pub fn stop(&mut self, reactor: Option<&mut osc::Reactor>) -> Option<StopEvent> {
         self.watching.store(false, Ordering::Relaxed);
+
+        if let (Some(token), Some(reactor)) = (self.osc_token.take(), reactor) {
+            reactor.deregister(token);
+        }
+
+        self.halt()
+    }
+
+    //
+    // Kill the app and leave it stopped, while its health checks keep running so it can be
+    // launched again. Returns the stop event if the app was running
+    //
+    pub fn halt(&mut self) -> Option<StopEvent> {
+        let running = self.process.is_some();
+        self.kill();
+        self.state = AppState::Stopped;
+        self.launch_on_resume = false;
+
+        if running {
+            Some(self.record(StopReason::Operator))
+        } else {
+            None
+        }
+    }
+
+    //
+    // Take a changed config with the same launch fields, the app keeps running. Most timings
+    // are read from the config as they are needed, the heartbeat's are set here
+    //
+    fn reconfigure(&mut self, c: &config::WatchedApp, reactor: Option<&mut osc::Reactor>) {
+        if c.health_checks.is_empty() {
+            if let Some(check) = self.checks.first_mut() {
+                check.timeout = Duration::from_secs(u64::from(&c.heartbeat_timeout.val));
+            }
+            let interval = Duration::from_secs(u64::from(&c.heartbeat_interval.val));
+            if self.osc_interval != Some(interval) {
+                self.osc_interval = Some(interval);
+                if let (Some(token), Some(reactor)) = (self.osc_token, reactor) {
+                    reactor.set_interval(token, interval);
+                }
+            }
+        }
+
+        if schedule_time(c) != schedule_time(&self.config) {
+            let local = Local::now();
+            self.last_scheduled = match schedule_time(c) {
+                Some(t) if local.time() >= t => Some(local.date_naive()),
+                _ => None,
+            };
+        }
+
+        self.config = c.clone();
+    }
+
+    //
+    // Stop the app and watch it again as built from a changed config, keeping its history.
+    // It is launched again only if it was launched before, and not while supervision is
+    // paused, in which case it is launched when supervision resumes
+    //
+    fn rebuild(
+        &mut self,
+        c: &config::WatchedApp,
+        mut reactor: Option<&mut osc::Reactor>,
+        paused: bool,
+    ) -> Self {
+        self.watching.store(false, Ordering::Relaxed);
+        let running = self.process.is_some();
+        let launched = !matches!(self.state, AppState::Stopped) || self.launch_on_resume;
+        self.kill();
+
+        // The old socket is closed before the new one is bound, often on the same ports
+        if let (Some(token), Some(r)) = (self.osc_token.take(), reactor.as_deref_mut()) {
+            r.deregister(token);
+        }
+
+        let mut a = App::new(c);
+        a.events = std::mem::take(&mut self.events);
+        a.restarts = self.restarts;
+        if running {
+            println!(
+                "App {} restarting: {}",
+                self.name,
+                StopReason::ConfigChanged
+            );
+            a.restarts += 1;
+            a.record(StopReason::ConfigChanged);
+        }
+        if a.watch(reactor) && launched {
+            a.launch_later(paused);
+        }
+        a
+    }
+
+    // Launch now, or when supervision resumes if it is paused
+    fn launch_later(&mut self, paused: bool) {
+        if paused {
+            self.launch_on_resume = true;
+        } else {
+            self.launch();
+        }
     }
 }
 
+//
+// Fields only read when an app is launched, or when its health checks and OSC interface are
+// built. Changing any of them restarts the app, the others are applied while it runs
+//
+const LAUNCH_FIELDS: [&str; 10] = [
+    "run",
+    "osc_in_port",
+    "osc_out_port",
+    "osc_bind_address",
+    "osc_client_address",
+    "osc_transport",
+    "heartbeat_channel",
+    "memory_max",
+    "cpu_max",
+    "pids_max",
+];
+
+fn launch_changed(old: &config::WatchedApp, new: &config::WatchedApp) -> bool {
+    let fields = old
+        .fields()
+        .iter()
+        .zip(new.fields().iter())
+        .any(|((name, a), (_, b))| LAUNCH_FIELDS.contains(name) && a.str != b.str);
+    let checks = |c: &config::WatchedApp| -> Vec<String> {
+        c.health_checks
+            .iter()
+            .flat_map(|h| h.fields().map(|(_, d)| d.str.clone()))
+            .collect()
+    };
+    let relays = |c: &config::WatchedApp| -> Vec<String> {
+        c.relay_rules.iter().map(|r| r.str.clone()).collect()
+    };
+
+    fields || checks(old) != checks(new) || relays(old) != relays(new)
+}
+
+// Every value of the watched apps, to tell when any of them changed
+fn fingerprint(apps: &[config::WatchedApp]) -> String {
+    let mut s = String::new();
+    for c in apps.iter() {
+        let values = c
+            .fields()
+            .into_iter()
+            .map(|(_, d)| d)
+            .chain(
+                c.health_checks
+                    .iter()
+                    .flat_map(|h| h.fields().map(|(_, d)| d)),
+            )
+            .chain(c.telemetry_alerts.iter())
+            .chain(c.relay_rules.iter());
+        for d in values {
+            s.push_str(&d.str);
+            s.push('\0');
+        }
+        s.push('\n');
+    }
+    s
+}
+
+// Changes made to the watched apps by applying the config, by app name
+#[derive(Default)]
+pub struct ConfigChanges {
+    pub started: Vec<String>,
+    pub stopped: Vec<String>,
+    pub restarted: Vec<String>,
+    pub updated: Vec<String>,
+    pub errors: Vec<(String, osc::BindError)>,
+}
+
+// The configured time of day for a daily restart, if any
+fn schedule_time(c: &config::WatchedApp) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(&String::from(&c.restart_schedule.val), "%H:%M").ok()
+}
+
 impl Apps {
     pub fn new() -> Self {
         Apps {
             watching: false,
+            paused: false,
+            reactor: None,
             valid_listeners: false,
             watched_apps: Vec::new(),
+            applied: String::new(),
+            pending: None,
         }
     }
 
-    pub fn build_listeners(&mut self, config: &config::Config) {
+    // Build and start watching each app. Returns the apps whose OSC interface
+    // could not be bound, those are left stopped while the others are watched
+    pub fn build_listeners(&mut self, config: &config::Config) -> Vec<(String, osc::BindError)> {
+        let mut errors = Vec::new();
         if self.watching {
             println!("Cannot rebuild already running listeners");
-            return;
+            return errors;
         }
         self.watching = true;
+        self.paused = false;
 
         println!(
             "Building listeners for each watched app, total of: {}",
@@ -123,30 +904,276 @@ impl Apps {
 
         for watched_app in config.watched_apps.iter() {
             let a = App::new(watched_app);
+            if let Some(e) = a.bind_error() {
+                errors.push((a.name().to_string(), e.clone()));
+            }
             self.watched_apps.push(a);
         }
 
         self.valid_listeners = true;
+        self.applied = fingerprint(&config.watched_apps);
+        self.pending = None;
+
+        self.reactor = match osc::Reactor::start() {
+            Ok(r) => Some(r),
+            Err(e) => {
+                println!("Failed to start OSC reactor: {}", e);
+                None
+            }
+        };
 
         println!("Starting apps");
         for a in self.watched_apps.iter_mut() {
-            a.start();
+            a.start(self.reactor.as_mut());
         }
+
+        errors
     }
 
-    pub fn destroy_listeners(&mut self) {
+    // Stop watching all apps, returns the stop events of the apps that were running
+    pub fn destroy_listeners(&mut self) -> Vec<(String, StopEvent)> {
+        let mut events = Vec::new();
         if !self.watching {
             println!("Cannot stop apps when they are not yet running");
-            return;
+            return events;
         }
         self.watching = false;
+        self.paused = false;
         for a in self.watched_apps.iter_mut() {
-            a.stop();
+            if let Some(e) = a.stop(self.reactor.as_mut()) {
+                events.push((a.name().to_string(), e));
+            }
         }
 
+        // Joins the reactor thread
+        self.reactor = None;
+
         // Is this enough to drop them..?
         self.watched_apps.clear();
+
+        events
     }
+
+    //
+    // Bring the watched apps in line with the config while watching, called every tick of the
+    // event loop. The config is applied once it is valid and has not changed for a moment, so
+    // typing a value does not restart an app on every key. Apps are matched by name, new ones
+    // are started, removed ones stopped, and the others restarted only when a field they are
+    // launched with changed. Untouched apps keep running
+    //
+    pub fn update_config(&mut self, config: &config::Config) -> ConfigChanges {
+        let mut changes = ConfigChanges::default();
+        if !self.watching {
+            return changes;
+        }
+
+        let current = fingerprint(&config.watched_apps);
+        if current == self.applied {
+            self.pending = None;
+            return changes;
+        }
+        match &self.pending {
+            Some((pending, _)) if *pending == current => {}
+            _ => {
+                self.pending = Some((current, Instant::now()));
+                return changes;
+            }
+        }
+        let settled = self
+            .pending
+            .as_ref()
+            .is_some_and(|(_, since)| since.elapsed() >= APPLY_DELAY);
+        if !settled || !config.watched_apps.iter().all(|a| a.valid) {
+            return changes;
+        }
+        self.applied = current;
+        self.pending = None;
+
+        println!("Applying config changes to the watched apps");
+        let mut old: Vec<Option<App>> = std::mem::take(&mut self.watched_apps)
+            .into_iter()
+            .map(Some)
+            .collect();
+        for c in config.watched_apps.iter() {
+            let name = String::from(&c.name.val);
+            let found = old
+                .iter_mut()
+                .find(|a| a.as_ref().is_some_and(|a| a.name() == name))
+                .and_then(|a| a.take());
+
+            let a = match found {
+                Some(mut a) if launch_changed(a.config(), c) => {
+                    let a = a.rebuild(c, self.reactor.as_mut(), self.paused);
+                    if let Some(e) = a.bind_error() {
+                        changes.errors.push((name.clone(), e.clone()));
+                    }
+                    changes.restarted.push(name);
+                    a
+                }
+                Some(mut a) => {
+                    if fingerprint(std::slice::from_ref(a.config()))
+                        != fingerprint(std::slice::from_ref(c))
+                    {
+                        a.reconfigure(c, self.reactor.as_mut());
+                        changes.updated.push(name);
+                    }
+                    a
+                }
+                None => {
+                    let mut a = App::new(c);
+                    if a.watch(self.reactor.as_mut()) {
+                        a.launch_later(self.paused);
+                    }
+                    if let Some(e) = a.bind_error() {
+                        changes.errors.push((name.clone(), e.clone()));
+                    }
+                    changes.started.push(name);
+                    a
+                }
+            };
+            self.watched_apps.push(a);
+        }
+
+        for mut a in old.into_iter().flatten() {
+            a.stop(self.reactor.as_mut());
+            changes.stopped.push(a.name().to_string());
+        }
+
+        changes
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, App> {
+        self.watched_apps.iter()
+    }
+
+    // Check on each launched app, called every tick of the event loop.
+    // Returns the stop events of any apps that went down
+    pub fn update(&mut self, stats: &stats::Stats) -> Vec<(String, StopEvent)> {
+        let mut events = Vec::new();
+        if self.paused {
+            return events;
+        }
+        for a in self.watched_apps.iter_mut() {
+            let hang = stats.hang(a);
+            if let Some(e) = a.update(hang) {
+                events.push((a.name().to_string(), e));
+            }
+        }
+        events
+    }
+
+    // Alerts raised by the watched apps since they were last taken, as app name and message
+    pub fn take_alerts(&mut self) -> Vec<(String, String)> {
+        let mut alerts = Vec::new();
+        for a in self.watched_apps.iter_mut() {
+            for alert in a.take_alerts() {
+                alerts.push((a.name().to_string(), alert));
+            }
+        }
+        alerts
+    }
+
+    pub fn watching(&self) -> bool {
+        self.watching
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        if self.paused {
+            self.paused = false;
+            for a in self.watched_apps.iter_mut() {
+                a.resume();
+            }
+        }
+    }
+
+    //
+    // Launch the stopped apps matching a remote control target, an app name or group.
+    // Returns the number of apps matched
+    //
+    pub fn start_apps(&mut self, target: Option<&str>) -> usize {
+        let mut matched = 0;
+        for a in self.watched_apps.iter_mut().filter(|a| a.matches(target)) {
+            matched += 1;
+            // Apps without their OSC interface are not watched, as App::start leaves them
+            if matches!(a.state(), AppState::Stopped) && a.bind_error().is_none() {
+                a.launch();
+            }
+        }
+        matched
+    }
+
+    // Stop the apps matching a target, they stay stopped until started again
+    pub fn stop_apps(&mut self, target: Option<&str>) -> (usize, Vec<(String, StopEvent)>) {
+        let mut matched = 0;
+        let mut events = Vec::new();
+        for a in self.watched_apps.iter_mut().filter(|a| a.matches(target)) {
+            matched += 1;
+            if let Some(e) = a.halt() {
+                events.push((a.name().to_string(), e));
+            }
+        }
+        (matched, events)
+    }
+
+    pub fn restart_apps(&mut self, target: Option<&str>) -> (usize, Vec<(String, StopEvent)>) {
+        let mut matched = 0;
+        let mut events = Vec::new();
+        for a in self.watched_apps.iter_mut().filter(|a| a.matches(target)) {
+            matched += 1;
+            if let Some(e) = a.restart(StopReason::Operator) {
+                events.push((a.name().to_string(), e));
+            }
+        }
+        (matched, events)
+    }
+
+    // Restart a single watched app by name
+    pub fn restart(&mut self, name: &str, reason: StopReason) -> Vec<(String, StopEvent)> {
+        let mut events = Vec::new();
+        for a in self.watched_apps.iter_mut() {
+            if a.name() == name {
+                if let Some(e) = a.restart(reason.clone()) {
+                    events.push((a.name().to_string(), e));
+                }
+            }
+        }
+        events
+    }
+}
+
+//
+// Split a Run string into the program and its arguments, on whitespace,
+// while keeping "double quoted" sections (like paths with spaces) together
+//
+fn split_command(run: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in run.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+
+    parts
 }
 
 pub fn init() -> Apps {
@@ -154,3 +1181,29 @@ pub fn init() -> Apps {
 
     return apps;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clashing_ports_are_not_applied() {
+        let mut config = config::Config::default();
+        let mut other = config::WatchedApp::default();
+        other.name = config::ConfigData::new_text("other");
+        config.watched_apps.push(other);
+        config.validate_all();
+
+        let mut apps = Apps::new();
+        apps.watching = true;
+        // As if the config had already been waiting to settle
+        let since = Instant::now().checked_sub(APPLY_DELAY).unwrap();
+        apps.pending = Some((fingerprint(&config.watched_apps), since));
+
+        let changes = apps.update_config(&config);
+        assert!(changes.started.is_empty());
+        assert!(changes.errors.is_empty());
+        assert!(apps.watched_apps.is_empty());
+        assert!(apps.applied.is_empty());
+    }
+}
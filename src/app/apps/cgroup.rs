@@ -0,0 +1,211 @@
+use crate::app::config;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+//
+// Resource limits for watched apps, enforced by the kernel with a cgroup v2 subtree per app.
+//
+// Watchdog creates the subtree below its own cgroup, so that cgroup must be delegated to the
+// user running Watchdog (eg. a systemd unit with Delegate=yes). Since the kernel does not allow a
+// cgroup with processes to hand controllers down to its children, Watchdog first moves itself into
+// a leaf cgroup named "supervisor" next to the app cgroups.
+//
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+const SUPERVISOR_CGROUP: &str = "supervisor";
+const CONTROLLERS: [&str; 3] = ["memory", "cpu", "pids"];
+
+// Period used for cpu.max, in microseconds
+const CPU_PERIOD: u64 = 100000;
+
+// How long to wait for the killed processes to exit, before removing the cgroup
+const KILL_TIMEOUT: Duration = Duration::from_millis(500);
+
+pub struct Limits {
+    // Bytes, 0 is unlimited
+    pub memory: u64,
+    // Percent of one core, 0 is unlimited
+    pub cpu: u64,
+    // Number of processes and threads, 0 is unlimited
+    pub pids: u64,
+}
+
+impl Limits {
+    pub fn from_config(c: &config::WatchedApp) -> Self {
+        Limits {
+            memory: u64::from(&c.memory_max.val) * 1024 * 1024,
+            cpu: u64::from(&c.cpu_max.val),
+            pids: u64::from(&c.pids_max.val),
+        }
+    }
+
+    pub fn any(&self) -> bool {
+        self.memory > 0 || self.cpu > 0 || self.pids > 0
+    }
+}
+
+pub struct Cgroup {
+    path: PathBuf,
+    // oom_kill count from memory.events, when last checked
+    oom_kills: u64,
+}
+
+impl Cgroup {
+    //
+    // Create a cgroup for one watched app, and apply its limits
+    //
+    pub fn create(name: &str, limits: &Limits) -> Result<Cgroup, String> {
+        let base = base_path()?;
+        enable_controllers(&base)?;
+
+        let path = base.join(format!("app-{}", sanitize(name)));
+        if !path.exists() {
+            fs::create_dir(&path)
+                .map_err(|e| format!("could not create cgroup {:?}: {}", path, e))?;
+        }
+
+        write(&path, "memory.max", &limit_value(limits.memory))?;
+        write(&path, "pids.max", &limit_value(limits.pids))?;
+        let cpu = if limits.cpu > 0 {
+            format!("{} {}", limits.cpu * CPU_PERIOD / 100, CPU_PERIOD)
+        } else {
+            format!("max {}", CPU_PERIOD)
+        };
+        write(&path, "cpu.max", &cpu)?;
+
+        let mut c = Cgroup { path, oom_kills: 0 };
+        c.oom_kills = c.read_oom_kills();
+        Ok(c)
+    }
+
+    // Move a launched process into the cgroup
+    pub fn add(&self, pid: u32) -> Result<(), String> {
+        write(&self.path, "cgroup.procs", &pid.to_string())
+    }
+
+    // Test if the kernel OOM killed a process in the cgroup since the last call
+    pub fn oom_killed(&mut self) -> bool {
+        let kills = self.read_oom_kills();
+        let killed = kills > self.oom_kills;
+        self.oom_kills = kills;
+        killed
+    }
+
+    fn read_oom_kills(&self) -> u64 {
+        fs::read_to_string(self.path.join("memory.events"))
+            .unwrap_or_default()
+            .lines()
+            .find_map(|l| l.strip_prefix("oom_kill "))
+            .and_then(|n| n.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    //
+    // Kill every process left in the cgroup, including those the app started itself, and
+    // remove it. Removing is only possible once they have all exited
+    //
+    pub fn remove(&self) {
+        if self.populated() {
+            if let Err(e) = write(&self.path, "cgroup.kill", "1") {
+                println!(
+                    "Could not kill the processes in cgroup {:?}: {}",
+                    self.path, e
+                );
+            }
+            let deadline = Instant::now() + KILL_TIMEOUT;
+            while self.populated() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+
+        if let Err(e) = fs::remove_dir(&self.path) {
+            println!("Could not remove cgroup {:?}: {}", self.path, e);
+        }
+    }
+
+    // Test if any process is still in the cgroup
+    fn populated(&self) -> bool {
+        fs::read_to_string(self.path.join("cgroup.events"))
+            .unwrap_or_default()
+            .lines()
+            .any(|l| l.trim() == "populated 1")
+    }
+}
+
+// Cgroup v2 is mounted when the unified hierarchy exposes its controllers file
+pub fn available() -> bool {
+    Path::new(CGROUP_ROOT).join("cgroup.controllers").exists()
+}
+
+// The cgroup Watchdog was started in, or the parent of the supervisor cgroup once moved
+fn base_path() -> Result<PathBuf, String> {
+    if !available() {
+        return Err("cgroup v2 is not available on this system".to_string());
+    }
+
+    // On the unified hierarchy /proc/self/cgroup is a single line of "0::/path"
+    let own = fs::read_to_string("/proc/self/cgroup")
+        .map_err(|e| format!("could not read own cgroup: {}", e))?;
+    let own = own
+        .lines()
+        .find_map(|l| l.strip_prefix("0::"))
+        .ok_or("could not find own cgroup v2 path")?;
+
+    let mut path = Path::new(CGROUP_ROOT).join(own.trim().trim_start_matches('/'));
+    if path.ends_with(SUPERVISOR_CGROUP) {
+        path.pop();
+    }
+
+    Ok(path)
+}
+
+// Allow the app cgroups below base to use the memory, cpu and pids controllers
+fn enable_controllers(base: &Path) -> Result<(), String> {
+    let enabled = fs::read_to_string(base.join("cgroup.subtree_control")).unwrap_or_default();
+    if CONTROLLERS
+        .iter()
+        .all(|c| enabled.split_whitespace().any(|e| e == *c))
+    {
+        return Ok(());
+    }
+
+    // Leave base without processes, so it may delegate controllers
+    let supervisor = base.join(SUPERVISOR_CGROUP);
+    if !supervisor.exists() {
+        fs::create_dir(&supervisor)
+            .map_err(|e| format!("could not create cgroup {:?}: {}", supervisor, e))?;
+    }
+    write(&supervisor, "cgroup.procs", &std::process::id().to_string())?;
+
+    let controllers: Vec<String> = CONTROLLERS.iter().map(|c| format!("+{}", c)).collect();
+    write(base, "cgroup.subtree_control", &controllers.join(" "))
+}
+
+fn write(path: &Path, file: &str, value: &str) -> Result<(), String> {
+    fs::write(path.join(file), value)
+        .map_err(|e| format!("could not write {} to {:?}: {}", value, path.join(file), e))
+}
+
+fn limit_value(limit: u64) -> String {
+    if limit > 0 {
+        limit.to_string()
+    } else {
+        "max".to_string()
+    }
+}
+
+// Cgroup names are directory names, keep them to a safe set of characters
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
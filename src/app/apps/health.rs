@@ -0,0 +1,375 @@
+use crate::app::config;
+use crate::utils::now;
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+// Most of an http response to read, enough for the status line and a small body
+const MAX_HTTP_RESPONSE: u64 = 64 * 1024;
+
+//
+// Time of the most recent passing probe of a health check, in ms since the epoch.
+// Shared between the thread running the probes and the app state machine
+//
+#[derive(Clone)]
+pub struct Status(Arc<AtomicU64>);
+
+impl Status {
+    pub fn new() -> Self {
+        Status(Arc::new(AtomicU64::new(0)))
+    }
+
+    pub fn pass(&self) {
+        self.pass_at(now());
+    }
+
+    // Never moves the last pass backwards in time
+    pub fn pass_at(&self, time: Duration) {
+        self.0.fetch_max(time.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub fn last_pass(&self) -> Duration {
+        Duration::from_millis(self.0.load(Ordering::Relaxed))
+    }
+}
+
+//
+// A way of asking a watched app if it is healthy. Each check runs on its own thread,
+// calling probe once per interval, and the app is unhealthy when any check
+// has not passed within its timeout. OSC heartbeats are not probed this way, they are
+// sent and received by the OSC reactor, which passes the app's osc status
+//
+pub trait HealthCheck: Send {
+    // Human readable description, for the Ui
+    fn describe(&self) -> String;
+    fn interval(&self) -> Duration;
+    fn timeout(&self) -> Duration;
+    fn status(&self) -> &Status;
+    // Run one probe against the app's current process id, 0 when not running,
+    // marking the status as passed when healthy
+    fn probe(&mut self, pid: u32);
+}
+
+// What the app state machine keeps of each check, once the check is moved to its thread
+pub struct CheckHandle {
+    pub description: String,
+    pub timeout: Duration,
+    pub status: Status,
+}
+
+impl CheckHandle {
+    pub fn new(check: &dyn HealthCheck) -> Self {
+        CheckHandle {
+            description: check.describe(),
+            timeout: check.timeout(),
+            status: check.status().clone(),
+        }
+    }
+
+    fn osc_heartbeat(timeout: Duration, osc_status: &Status) -> Self {
+        CheckHandle {
+            description: "osc heartbeat".to_string(),
+            timeout,
+            status: osc_status.clone(),
+        }
+    }
+}
+
+// The health checks of a watched app, as built from its config
+pub struct Checks {
+    // Checks probed on threads of their own
+    pub probed: Vec<Box<dyn HealthCheck>>,
+    // Every check, including OSC heartbeats, in config order
+    pub handles: Vec<CheckHandle>,
+    // How often the OSC reactor sends heartbeats, when any check is an OSC heartbeat
+    pub osc_interval: Option<Duration>,
+}
+
+//
+// HTTP GET, passes on the expected status code, or a body containing the expected text
+//
+pub struct Http {
+    url: String,
+    // host:port to connect to
+    authority: String,
+    path: String,
+    expect: String,
+    interval: Duration,
+    timeout: Duration,
+    status: Status,
+}
+
+impl Http {
+    fn get(&self) -> std::io::Result<bool> {
+        let addr = self.authority.to_socket_addrs()?.next().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no address for host")
+        })?;
+
+        let mut stream = TcpStream::connect_timeout(&addr, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+
+        write!(
+            stream,
+            "GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            self.path, self.authority
+        )?;
+
+        let mut buffer = Vec::new();
+        stream.take(MAX_HTTP_RESPONSE).read_to_end(&mut buffer)?;
+        let response = String::from_utf8_lossy(&buffer);
+
+        // Status line is "HTTP/1.1 200 OK"
+        let code: u16 = response
+            .split_whitespace()
+            .nth(1)
+            .and_then(|c| c.parse().ok())
+            .unwrap_or(0);
+        let body = response.split_once("\r\n\r\n").map_or("", |(_, b)| b);
+
+        Ok(if self.expect.is_empty() {
+            (200..300).contains(&code)
+        } else if let Ok(expected) = self.expect.parse::<u16>() {
+            code == expected
+        } else {
+            body.contains(&self.expect)
+        })
+    }
+}
+
+impl HealthCheck for Http {
+    fn describe(&self) -> String {
+        format!("http {}", self.url)
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn status(&self) -> &Status {
+        &self.status
+    }
+
+    fn probe(&mut self, _pid: u32) {
+        if let Ok(true) = self.get() {
+            self.status.pass();
+        }
+    }
+}
+
+//
+// TCP connect, passes when the connection is accepted
+//
+pub struct Tcp {
+    target: String,
+    interval: Duration,
+    timeout: Duration,
+    status: Status,
+}
+
+impl HealthCheck for Tcp {
+    fn describe(&self) -> String {
+        format!("tcp {}", self.target)
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn status(&self) -> &Status {
+        &self.status
+    }
+
+    fn probe(&mut self, _pid: u32) {
+        let addr = self
+            .target
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut a| a.next());
+        if let Some(addr) = addr {
+            if TcpStream::connect_timeout(&addr, self.timeout).is_ok() {
+                self.status.pass();
+            }
+        }
+    }
+}
+
+//
+// Process alive only, passes whenever the launched process is running
+//
+pub struct ProcessAlive {
+    interval: Duration,
+    timeout: Duration,
+    status: Status,
+}
+
+impl HealthCheck for ProcessAlive {
+    fn describe(&self) -> String {
+        "process alive".to_string()
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn status(&self) -> &Status {
+        &self.status
+    }
+
+    fn probe(&mut self, pid: u32) {
+        if pid != 0 {
+            self.status.pass();
+        }
+    }
+}
+
+//
+// File touch, the app updates the modified time of a file, which counts as the last pass
+//
+pub struct FileTouch {
+    path: String,
+    interval: Duration,
+    timeout: Duration,
+    status: Status,
+}
+
+impl HealthCheck for FileTouch {
+    fn describe(&self) -> String {
+        format!("file {}", self.path)
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn status(&self) -> &Status {
+        &self.status
+    }
+
+    fn probe(&mut self, _pid: u32) {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok());
+        if let Some(m) = modified {
+            self.status.pass_at(m);
+        }
+    }
+}
+
+//
+// Build the health checks of a watched app from its config.
+// Every OSC heartbeat check shares the osc status, passed by the OSC reactor
+//
+pub fn build(c: &config::WatchedApp, osc_status: &Status) -> Checks {
+    let mut checks = Checks {
+        probed: Vec::new(),
+        handles: Vec::new(),
+        osc_interval: None,
+    };
+
+    if c.health_checks.is_empty() {
+        let interval = Duration::from_secs(u64::from(&c.heartbeat_interval.val));
+        let timeout = Duration::from_secs(u64::from(&c.heartbeat_timeout.val));
+        checks.osc_interval = Some(interval);
+        checks
+            .handles
+            .push(CheckHandle::osc_heartbeat(timeout, osc_status));
+        return checks;
+    }
+
+    for h in c.health_checks.iter() {
+        let interval = Duration::from_secs(u64::from(&h.interval.val));
+        let timeout = Duration::from_secs(u64::from(&h.timeout.val));
+        let target = String::from(&h.target.val);
+        let status = Status::new();
+
+        let check: Option<Box<dyn HealthCheck>> = match String::from(&h.kind.val).as_str() {
+            "osc" => {
+                // One heartbeat is sent for all OSC checks, as often as the most frequent asks
+                let osc_interval = checks.osc_interval.get_or_insert(interval);
+                *osc_interval = interval.min(*osc_interval);
+                checks
+                    .handles
+                    .push(CheckHandle::osc_heartbeat(timeout, osc_status));
+                continue;
+            }
+            "http" => {
+                // Split http://host:port/path into the authority and path
+                let rest = target.trim_start_matches("http://");
+                let (authority, path) = match rest.find('/') {
+                    Some(i) => (&rest[..i], &rest[i..]),
+                    None => (rest, "/"),
+                };
+                let has_port = authority
+                    .rsplit_once(':')
+                    .is_some_and(|(_, p)| !p.ends_with(']') && p.parse::<u16>().is_ok());
+                let authority = if has_port {
+                    authority.to_string()
+                } else {
+                    format!("{}:80", authority)
+                };
+                Some(Box::new(Http {
+                    url: target.clone(),
+                    authority,
+                    path: path.to_string(),
+                    expect: String::from(&h.expect.val),
+                    interval,
+                    timeout,
+                    status,
+                }))
+            }
+            "tcp" => Some(Box::new(Tcp {
+                target,
+                interval,
+                timeout,
+                status,
+            })),
+            "process" => Some(Box::new(ProcessAlive {
+                interval,
+                timeout,
+                status,
+            })),
+            "file" => Some(Box::new(FileTouch {
+                path: target,
+                interval,
+                timeout,
+                status,
+            })),
+            _ => None,
+        };
+
+        match check {
+            Some(check) => {
+                checks.handles.push(CheckHandle::new(check.as_ref()));
+                checks.probed.push(check);
+            }
+            None => println!(
+                "Skipping invalid health check {} for app {}",
+                String::from(&h.kind.val),
+                String::from(&c.name.val)
+            ),
+        }
+    }
+
+    checks
+}
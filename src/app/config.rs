@@ -1,14 +1,34 @@
+use super::notifications;
 use super::state::State;
+use chrono::NaiveTime;
 // use email_address::EmailAddress;
-use serde::{Deserialize, Serialize};
-use serde_json::Result;
 use std::env;
 use std::fs;
+use std::net::{IpAddr, SocketAddr};
 use std::path::{Path, PathBuf};
 
+mod history;
+mod migrate;
+mod schema;
+mod undo;
+mod watch;
+
+pub use history::{Backup, BackupDiff, Change};
+pub use undo::Undo;
+pub use watch::Watch;
+
 const WATCHDOG_STORE_FILENAME: &str = ".watchdog_store";
 const DEFAULT_CONFIG_FILENAME: &str = "watchdog_config.json";
 pub const MAX_WATCHED_APPS: usize = 5;
+pub const MAX_HEALTH_CHECKS: usize = 4;
+pub const MAX_TELEMETRY_ALERTS: usize = 4;
+pub const MAX_RELAY_RULES: usize = 4;
+
+// Kinds of health check a watched app may use
+pub const HEALTH_CHECK_KINDS: [&str; 5] = ["osc", "http", "tcp", "process", "file"];
+
+// Transports OSC can be sent to a watched app over
+pub const OSC_TRANSPORTS: [&str; 2] = ["udp", "tcp"];
 
 //
 // Store
@@ -79,18 +99,44 @@ impl Store {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Debug, Clone)]
 pub struct WatchedApp {
     pub valid: bool,
     pub name: ConfigData,
     pub run: ConfigData,
+    // Apps sharing a group name can be started, stopped and restarted together
+    pub group: ConfigData,
     pub osc_in_port: ConfigData,
     pub osc_out_port: ConfigData,
+    // Local address the OSC port out is bound on, 0.0.0.0 or :: for every interface
+    pub osc_bind_address: ConfigData,
+    // Address of the machine the watched app runs on, heartbeats are sent to its OSC port in
+    pub osc_client_address: ConfigData,
+    // udp sends to the app's OSC port in, tcp listens on the OSC port out for the app to connect
+    pub osc_transport: ConfigData,
     pub heartbeat_channel: ConfigData,
     pub heartbeat_interval: ConfigData,
     pub heartbeat_timeout: ConfigData,
     pub startup_timeout: ConfigData,
     pub restart_delay: ConfigData,
+    pub memory_limit: ConfigData,
+    pub cpu_limit: ConfigData,
+    pub limit_duration: ConfigData,
+    pub memory_max: ConfigData,
+    pub cpu_max: ConfigData,
+    pub pids_max: ConfigData,
+    pub restart_schedule: ConfigData,
+    pub cpu_idle_timeout: ConfigData,
+    pub cpu_pinned_timeout: ConfigData,
+    pub uninterruptible_timeout: ConfigData,
+    pub log_file: ConfigData,
+    pub log_silence_timeout: ConfigData,
+    // When empty, the app is checked with OSC heartbeats using the heartbeat fields above
+    pub health_checks: Vec<HealthCheckConfig>,
+    // Rules on the telemetry the app reports in its heartbeats, like: fps < 30 for 60s
+    pub telemetry_alerts: Vec<ConfigData>,
+    // Rules forwarding other OSC messages on the app's port, like: /cue/* -> 127.0.0.1:53000
+    pub relay_rules: Vec<ConfigData>,
 }
 
 impl WatchedApp {
@@ -99,18 +145,376 @@ impl WatchedApp {
             valid: false,
             name: ConfigData::new_text("demo"),
             run: ConfigData::new_text("demo.exe"),
+            group: ConfigData::default_group(),
             osc_in_port: ConfigData::new_port(1234),
             osc_out_port: ConfigData::new_port(1235),
+            osc_bind_address: ConfigData::default_osc_address(),
+            osc_client_address: ConfigData::default_osc_address(),
+            osc_transport: ConfigData::default_osc_transport(),
             heartbeat_channel: ConfigData::new_channel("/heart"),
             heartbeat_interval: ConfigData::new_seconds(1),
             heartbeat_timeout: ConfigData::new_seconds(5),
             startup_timeout: ConfigData::new_seconds(30),
             restart_delay: ConfigData::new_seconds(30),
+            memory_limit: ConfigData::default_memory_limit(),
+            cpu_limit: ConfigData::default_cpu_limit(),
+            limit_duration: ConfigData::default_limit_duration(),
+            memory_max: ConfigData::default_memory_max(),
+            cpu_max: ConfigData::default_cpu_max(),
+            pids_max: ConfigData::default_pids_max(),
+            restart_schedule: ConfigData::default_restart_schedule(),
+            cpu_idle_timeout: ConfigData::default_hang_timeout(),
+            cpu_pinned_timeout: ConfigData::default_hang_timeout(),
+            uninterruptible_timeout: ConfigData::default_hang_timeout(),
+            log_file: ConfigData::default_log_file(),
+            log_silence_timeout: ConfigData::default_hang_timeout(),
+            health_checks: Vec::new(),
+            telemetry_alerts: Vec::new(),
+            relay_rules: Vec::new(),
+        }
+    }
+
+    // Each value, by its name in the file, other than the lists
+    pub fn fields(&self) -> [(&'static str, &ConfigData); 25] {
+        [
+            ("name", &self.name),
+            ("run", &self.run),
+            ("group", &self.group),
+            ("osc_in_port", &self.osc_in_port),
+            ("osc_out_port", &self.osc_out_port),
+            ("osc_bind_address", &self.osc_bind_address),
+            ("osc_client_address", &self.osc_client_address),
+            ("osc_transport", &self.osc_transport),
+            ("heartbeat_channel", &self.heartbeat_channel),
+            ("heartbeat_interval", &self.heartbeat_interval),
+            ("heartbeat_timeout", &self.heartbeat_timeout),
+            ("startup_timeout", &self.startup_timeout),
+            ("restart_delay", &self.restart_delay),
+            ("memory_limit", &self.memory_limit),
+            ("cpu_limit", &self.cpu_limit),
+            ("limit_duration", &self.limit_duration),
+            ("memory_max", &self.memory_max),
+            ("cpu_max", &self.cpu_max),
+            ("pids_max", &self.pids_max),
+            ("restart_schedule", &self.restart_schedule),
+            ("cpu_idle_timeout", &self.cpu_idle_timeout),
+            ("cpu_pinned_timeout", &self.cpu_pinned_timeout),
+            ("uninterruptible_timeout", &self.uninterruptible_timeout),
+            ("log_file", &self.log_file),
+            ("log_silence_timeout", &self.log_silence_timeout),
+        ]
+    }
+}
+
+//
+// One health check of a watched app. The target depends on the kind:
+// osc uses the app's OSC ports and channel, http a url like http://127.0.0.1:8080/health,
+// tcp a host:port, process nothing, and file the path of a file the app touches
+//
+#[derive(Debug, Clone)]
+pub struct HealthCheckConfig {
+    pub kind: ConfigData,
+    pub target: ConfigData,
+    // For http only, the expected status code, or text the body must contain. Empty expects any 2xx
+    pub expect: ConfigData,
+    pub interval: ConfigData,
+    pub timeout: ConfigData,
+}
+
+impl HealthCheckConfig {
+    pub fn default() -> Self {
+        Self {
+            kind: ConfigData::new_check_kind("http"),
+            target: ConfigData::new_path("http://127.0.0.1:8080/"),
+            expect: ConfigData::new_path(""),
+            interval: ConfigData::new_seconds(1),
+            timeout: ConfigData::new_seconds(5),
+        }
+    }
+
+    // Each value, by its name in the file
+    pub fn fields(&self) -> [(&'static str, &ConfigData); 5] {
+        [
+            ("kind", &self.kind),
+            ("target", &self.target),
+            ("expect", &self.expect),
+            ("interval", &self.interval),
+            ("timeout", &self.timeout),
+        ]
+    }
+
+    // Validate each field, then the target against the kind of check
+    pub fn validate(&mut self) -> bool {
+        let mut v = true;
+        v = v && self.kind.validate();
+        v = v && self.target.validate();
+        v = v && self.expect.validate();
+        v = v && self.interval.validate();
+        v = v && self.timeout.validate();
+        if !v {
+            return false;
+        }
+
+        let target = String::from(&self.target.val);
+        let port = target
+            .rsplit_once(':')
+            .and_then(|(_, p)| p.parse::<u16>().ok());
+        let error = match String::from(&self.kind.val).as_str() {
+            "http" if !target.starts_with("http://") => {
+                Some("Target must be a url beginning with http://")
+            }
+            "tcp" if port.is_none() => Some("Target must be a host:port to connect to"),
+            "file" if target.is_empty() => Some("Target must be the path of a file"),
+            _ => None,
+        };
+
+        match error {
+            Some(e) => {
+                self.target.invalidate(e.to_string());
+                false
+            }
+            None => true,
+        }
+    }
+}
+
+//
+// A rule on one key of an app's telemetry, written as: <key> <comparison> <limit> for <seconds>s
+// where the comparison is < or >, for example: fps < 30 for 60s
+//
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    pub key: String,
+    // True for <, false for >
+    pub below: bool,
+    pub limit: f64,
+    pub duration: u64,
+}
+
+impl AlertRule {
+    pub fn parse(rule: &str) -> std::result::Result<Self, String> {
+        let format_error = "Alert must be written as: key < limit for 60s, or key > limit for 60s.";
+        let parts: Vec<&str> = rule.split_whitespace().collect();
+        let (key, comparison, limit, duration) = match parts[..] {
+            [key, comparison, limit, "for", duration] => (key, comparison, limit, duration),
+            _ => return Err(format_error.to_string()),
+        };
+
+        let below = match comparison {
+            "<" => true,
+            ">" => false,
+            _ => return Err(format_error.to_string()),
+        };
+
+        let limit: f64 = match limit.parse() {
+            Ok(l) => l,
+            Err(_) => return Err(format!("Limit {} must be a number.", limit)),
+        };
+
+        let duration: u64 = match duration.trim_end_matches('s').parse() {
+            Ok(d) if d <= 86400 => d,
+            _ => return Err("Duration must be in between 0 and 86400 seconds.".to_string()),
+        };
+
+        Ok(AlertRule {
+            key: key.to_string(),
+            below,
+            limit,
+            duration,
+        })
+    }
+
+    pub fn breached_by(&self, value: f64) -> bool {
+        if self.below {
+            value < self.limit
+        } else {
+            value > self.limit
+        }
+    }
+}
+
+impl std::fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let comparison = if self.below { "<" } else { ">" };
+        write!(
+            f,
+            "{} {} {} for {}s",
+            self.key, comparison, self.limit, self.duration
+        )
+    }
+}
+
+//
+// A rule forwarding OSC messages that arrive on an app's port, and are not handled by Watchdog,
+// written as: <pattern> -> <target>, or: <pattern> -> <target> as <address>
+// The target is a host:port, or app for the app's own OSC port in. In the pattern, * matches
+// any characters within a part of the address and ? a single one, neither matches a /. Each *
+// of the address is replaced with what the matching * of the pattern matched, for example:
+// /show/app1/* -> app as /*
+//
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayRule {
+    pub pattern: String,
+    pub target: RelayTarget,
+    // The address forwarded to, the message keeps its own address when None
+    pub rewrite: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelayTarget {
+    // The watched app, over its OSC transport
+    App,
+    Address(SocketAddr),
+}
+
+impl RelayRule {
+    pub fn parse(rule: &str) -> std::result::Result<Self, String> {
+        let format_error =
+            "Relay must be written as: /pattern -> host:port, or /pattern -> app as /address.";
+        let parts: Vec<&str> = rule.split_whitespace().collect();
+        let (pattern, target, rewrite) = match parts[..] {
+            [pattern, "->", target] => (pattern, target, None),
+            [pattern, "->", target, "as", rewrite] => (pattern, target, Some(rewrite)),
+            _ => return Err(format_error.to_string()),
+        };
+
+        if !pattern.starts_with('/') {
+            return Err(format!("Pattern {} must begin with a /.", pattern));
+        }
+
+        let target = match target {
+            "app" => RelayTarget::App,
+            t => match t.parse::<SocketAddr>() {
+                Ok(a) => RelayTarget::Address(a),
+                Err(_) => return Err(format!("Target {} must be app, or an ip:port.", t)),
+            },
+        };
+
+        if let Some(r) = rewrite {
+            let wildcards = pattern.matches('*').count();
+            if !r.starts_with('/') || r.contains('?') {
+                return Err(format!(
+                    "Address {} must begin with a / and have no ? in it.",
+                    r
+                ));
+            }
+            if r.matches('*').count() > wildcards {
+                return Err(format!(
+                    "Address {} has more * than the pattern {}.",
+                    r, pattern
+                ));
+            }
+        }
+
+        Ok(RelayRule {
+            pattern: pattern.to_string(),
+            target,
+            rewrite: rewrite.map(|r| r.to_string()),
+        })
+    }
+
+    // The address to forward a message to, or None if the message does not match
+    pub fn route(&self, address: &str) -> Option<String> {
+        let pattern: Vec<char> = self.pattern.chars().collect();
+        let address: Vec<char> = address.chars().collect();
+        let mut captures = Vec::new();
+        if !glob(&pattern, &address, &mut captures) {
+            return None;
+        }
+
+        let rewrite = match &self.rewrite {
+            Some(r) => r,
+            None => return Some(address.iter().collect()),
+        };
+        let mut captures = captures.into_iter();
+        let mut routed = String::new();
+        for c in rewrite.chars() {
+            match c {
+                '*' => {
+                    if let Some((start, end)) = captures.next() {
+                        routed.extend(&address[start..end]);
+                    }
+                }
+                c => routed.push(c),
+            }
+        }
+        Some(routed)
+    }
+}
+
+//
+// Match an address against a pattern, collecting the range of the address each * matched.
+// As in OSC address patterns, * and ? never match a /, so each part of the address between
+// slashes is matched on its own. Within a part only the last * is ever let match more, as
+// any earlier * could not match more than it does. The address comes from the network, so
+// matching is iterative, without backtracking through every *
+//
+fn glob(pattern: &[char], address: &[char], captures: &mut Vec<(usize, usize)>) -> bool {
+    let (mut p, mut a) = (0, 0);
+    // The last * seen, as its index in the pattern, its capture, and where its match ends
+    let mut star: Option<(usize, usize, usize)> = None;
+
+    while a < address.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                captures.push((a, a));
+                star = Some((p, captures.len() - 1, a));
+                p += 1;
+            }
+            Some('?') if address[a] != '/' => {
+                p += 1;
+                a += 1;
+            }
+            Some(c) if *c == address[a] => {
+                // A * before this / can no longer match more
+                if *c == '/' {
+                    star = None;
+                }
+                p += 1;
+                a += 1;
+            }
+            _ => match star {
+                // Let the last * match one more character, and match the rest from there
+                Some((star_p, capture, end)) if address[end] != '/' => {
+                    captures.truncate(capture + 1);
+                    captures[capture].1 = end + 1;
+                    star = Some((star_p, capture, end + 1));
+                    p = star_p + 1;
+                    a = end + 1;
+                }
+                _ => return false,
+            },
+        }
+    }
+
+    // Stars left at the end of the pattern match nothing
+    while pattern.get(p) == Some(&'*') {
+        captures.push((a, a));
+        p += 1;
+    }
+    p == pattern.len()
+}
+
+impl std::fmt::Display for RelayTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RelayTarget::App => write!(f, "app"),
+            RelayTarget::Address(a) => write!(f, "{}", a),
+        }
+    }
+}
+
+impl std::fmt::Display for RelayRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {}", self.pattern, self.target)?;
+        if let Some(r) = &self.rewrite {
+            write!(f, " as {}", r)?;
         }
+        Ok(())
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Debug, Clone)]
 pub struct ConfigData {
     pub str: String,
     pub val: ConfigDataType,
@@ -163,6 +567,178 @@ impl ConfigData {
         }
     }
 
+    pub fn new_megabytes(val: usize) -> Self {
+        ConfigData {
+            str: val.to_string(),
+            val: ConfigDataType::Megabytes(val),
+            dirty: false,
+            valid: false,
+            error: String::new(),
+        }
+    }
+
+    pub fn new_percent(val: usize) -> Self {
+        ConfigData {
+            str: val.to_string(),
+            val: ConfigDataType::Percent(val),
+            dirty: false,
+            valid: false,
+            error: String::new(),
+        }
+    }
+
+    pub fn new_count(val: usize) -> Self {
+        ConfigData {
+            str: val.to_string(),
+            val: ConfigDataType::Count(val),
+            dirty: false,
+            valid: false,
+            error: String::new(),
+        }
+    }
+
+    pub fn new_time(val: &str) -> Self {
+        ConfigData {
+            str: val.to_string(),
+            val: ConfigDataType::Time(val.to_string()),
+            dirty: false,
+            valid: false,
+            error: String::new(),
+        }
+    }
+
+    pub fn new_optional_seconds(val: usize) -> Self {
+        ConfigData {
+            str: val.to_string(),
+            val: ConfigDataType::OptionalSeconds(val),
+            dirty: false,
+            valid: false,
+            error: String::new(),
+        }
+    }
+
+    pub fn new_path(val: &str) -> Self {
+        ConfigData {
+            str: val.to_string(),
+            val: ConfigDataType::Path(val.to_string()),
+            dirty: false,
+            valid: false,
+            error: String::new(),
+        }
+    }
+
+    pub fn new_address(val: &str) -> Self {
+        ConfigData {
+            str: val.to_string(),
+            val: ConfigDataType::Address(val.to_string()),
+            dirty: false,
+            valid: false,
+            error: String::new(),
+        }
+    }
+
+    pub fn new_alert(val: &str) -> Self {
+        ConfigData {
+            str: val.to_string(),
+            val: ConfigDataType::Alert(val.to_string()),
+            dirty: false,
+            valid: false,
+            error: String::new(),
+        }
+    }
+
+    pub fn new_relay(val: &str) -> Self {
+        ConfigData {
+            str: val.to_string(),
+            val: ConfigDataType::Relay(val.to_string()),
+            dirty: false,
+            valid: false,
+            error: String::new(),
+        }
+    }
+
+    pub fn new_group(val: &str) -> Self {
+        ConfigData {
+            str: val.to_string(),
+            val: ConfigDataType::Group(val.to_string()),
+            dirty: false,
+            valid: false,
+            error: String::new(),
+        }
+    }
+
+    pub fn new_transport(val: &str) -> Self {
+        ConfigData {
+            str: val.to_string(),
+            val: ConfigDataType::Transport(val.to_string()),
+            dirty: false,
+            valid: false,
+            error: String::new(),
+        }
+    }
+
+    pub fn new_check_kind(val: &str) -> Self {
+        ConfigData {
+            str: val.to_string(),
+            val: ConfigDataType::CheckKind(val.to_string()),
+            dirty: false,
+            valid: false,
+            error: String::new(),
+        }
+    }
+
+    //
+    // Defaults for fields added after the original config format,
+    // which older JSON files are missing
+    //
+    fn default_memory_limit() -> Self {
+        ConfigData::new_megabytes(0)
+    }
+
+    fn default_cpu_limit() -> Self {
+        ConfigData::new_percent(0)
+    }
+
+    fn default_limit_duration() -> Self {
+        ConfigData::new_seconds(300)
+    }
+
+    fn default_memory_max() -> Self {
+        ConfigData::new_megabytes(0)
+    }
+
+    fn default_cpu_max() -> Self {
+        ConfigData::new_percent(0)
+    }
+
+    fn default_pids_max() -> Self {
+        ConfigData::new_count(0)
+    }
+
+    fn default_restart_schedule() -> Self {
+        ConfigData::new_time("")
+    }
+
+    fn default_hang_timeout() -> Self {
+        ConfigData::new_optional_seconds(0)
+    }
+
+    fn default_log_file() -> Self {
+        ConfigData::new_path("")
+    }
+
+    fn default_osc_address() -> Self {
+        ConfigData::new_address("127.0.0.1")
+    }
+
+    fn default_group() -> Self {
+        ConfigData::new_group("")
+    }
+
+    fn default_osc_transport() -> Self {
+        ConfigData::new_transport("udp")
+    }
+
     // Validate
     pub fn validate(&mut self) -> bool {
         match self.val {
@@ -306,30 +882,371 @@ impl ConfigData {
 
                 self.valid
             }
-        }
-    }
+            ConfigDataType::Megabytes(ref mut data) => {
+                // move the UI string into the data type
+                let valid_int: bool;
+                let mb: usize = match self.str.trim().parse() {
+                    Ok(num) => {
+                        valid_int = true;
+                        num
+                    }
+                    Err(_) => {
+                        valid_int = false;
+                        0
+                    }
+                };
 
-    // Set validity from external tests
-    pub fn invalidate(&mut self, error: String) {
-        self.valid = false;
-        self.error = error;
-    }
-}
+                // valid range, 0 disables the limit
+                let in_range = mb <= 1048576;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
-pub enum ConfigDataType {
-    Text(String),
-    Channel(String),
-    Port(usize),
-    Seconds(usize),
-}
+                // SET validity
+                self.valid = valid_int && in_range;
 
-impl From<&ConfigDataType> for u16 {
-    fn from(value: &ConfigDataType) -> Self {
-        match value {
-            ConfigDataType::Port(p) => p.clone() as u16,
-            ConfigDataType::Seconds(s) => s.clone() as u16,
-            _ => {
+                // ADD errors for ui
+                if !valid_int {
+                    self.error = "Entry must be a valid positive integer.".to_string();
+                } else if !in_range {
+                    self.error = "Entry must be in between 0 and 1048576.".to_string();
+                } else {
+                    self.error.clear();
+                }
+
+                if self.valid {
+                    // APPLY new type safe value
+                    *data = mb;
+                } else {
+                    // APPLY placeholder data since Ui string is invalid
+                    *data = 0;
+                }
+
+                self.dirty = false;
+
+                self.valid
+            }
+            ConfigDataType::Percent(ref mut data) => {
+                // move the UI string into the data type
+                let valid_int: bool;
+                let pct: usize = match self.str.trim().parse() {
+                    Ok(num) => {
+                        valid_int = true;
+                        num
+                    }
+                    Err(_) => {
+                        valid_int = false;
+                        0
+                    }
+                };
+
+                // valid range, 0 disables the limit, and 100% is one fully used core
+                let in_range = pct <= 25600;
+
+                // SET validity
+                self.valid = valid_int && in_range;
+
+                // ADD errors for ui
+                if !valid_int {
+                    self.error = "Entry must be a valid positive integer.".to_string();
+                } else if !in_range {
+                    self.error = "Entry must be in between 0 and 25600.".to_string();
+                } else {
+                    self.error.clear();
+                }
+
+                if self.valid {
+                    // APPLY new type safe value
+                    *data = pct;
+                } else {
+                    // APPLY placeholder data since Ui string is invalid
+                    *data = 0;
+                }
+
+                self.dirty = false;
+
+                self.valid
+            }
+            ConfigDataType::Time(ref mut data) => {
+                // move the UI string into the data type
+                let s = self.str.trim().to_string();
+
+                // empty disables, otherwise a 24 hour time of day
+                let valid_time = s.is_empty() || NaiveTime::parse_from_str(&s, "%H:%M").is_ok();
+
+                self.valid = valid_time;
+
+                if !valid_time {
+                    self.error = "Time must be empty, or in the 24 hour format HH:MM.".to_string();
+                } else {
+                    self.error.clear();
+                }
+
+                if self.valid {
+                    *data = s;
+                } else {
+                    data.clear();
+                }
+
+                self.dirty = false;
+
+                self.valid
+            }
+            ConfigDataType::OptionalSeconds(ref mut data) => {
+                // move the UI string into the data type
+                let valid_int: bool;
+                let sec: usize = match self.str.trim().parse() {
+                    Ok(num) => {
+                        valid_int = true;
+                        num
+                    }
+                    Err(_) => {
+                        valid_int = false;
+                        0
+                    }
+                };
+
+                // valid range, 0 disables
+                let in_range = sec <= 86400;
+
+                // SET validity
+                self.valid = valid_int && in_range;
+
+                // ADD errors for ui
+                if !valid_int {
+                    self.error = "Entry must be a valid positive integer.".to_string();
+                } else if !in_range {
+                    self.error = "Entry must be in between 0 and 86400.".to_string();
+                } else {
+                    self.error.clear();
+                }
+
+                if self.valid {
+                    // APPLY new type safe value
+                    *data = sec;
+                } else {
+                    // APPLY placeholder data since Ui string is invalid
+                    *data = 0;
+                }
+
+                self.dirty = false;
+
+                self.valid
+            }
+            ConfigDataType::Path(ref mut data) => {
+                // move the UI string into the data type, empty is allowed and disables
+                let s = self.str.trim().to_string();
+
+                self.valid = true;
+                self.error.clear();
+
+                *data = s;
+
+                self.dirty = false;
+
+                self.valid
+            }
+            ConfigDataType::Address(ref mut data) => {
+                // move the UI string into the data type
+                let s = self.str.trim().to_string();
+
+                // IPv4 or IPv6, without a port
+                let valid_ip = s.parse::<IpAddr>().is_ok();
+
+                self.valid = valid_ip;
+
+                if !valid_ip {
+                    self.error =
+                        "Entry must be an IPv4 or IPv6 address, like 127.0.0.1 or ::1.".to_string();
+                } else {
+                    self.error.clear();
+                }
+
+                if self.valid {
+                    *data = s;
+                } else {
+                    data.clear();
+                }
+
+                self.dirty = false;
+
+                self.valid
+            }
+            ConfigDataType::Group(ref mut data) => {
+                // move the UI string into the data type, empty is allowed and means no group
+                let s = self.str.trim().to_string();
+
+                self.valid = true;
+                self.error.clear();
+
+                *data = s;
+
+                self.dirty = false;
+
+                self.valid
+            }
+            ConfigDataType::Alert(ref mut data) => {
+                // move the UI string into the data type
+                let s = self.str.trim().to_string();
+
+                match AlertRule::parse(&s) {
+                    Ok(_) => {
+                        self.valid = true;
+                        self.error.clear();
+                        *data = s;
+                    }
+                    Err(e) => {
+                        self.valid = false;
+                        self.error = e;
+                        data.clear();
+                    }
+                }
+
+                self.dirty = false;
+
+                self.valid
+            }
+            ConfigDataType::Relay(ref mut data) => {
+                // move the UI string into the data type
+                let s = self.str.trim().to_string();
+
+                match RelayRule::parse(&s) {
+                    Ok(_) => {
+                        self.valid = true;
+                        self.error.clear();
+                        *data = s;
+                    }
+                    Err(e) => {
+                        self.valid = false;
+                        self.error = e;
+                        data.clear();
+                    }
+                }
+
+                self.dirty = false;
+
+                self.valid
+            }
+            ConfigDataType::Transport(ref mut data) => {
+                // move the UI string into the data type
+                let s = self.str.trim().to_lowercase();
+
+                self.valid = OSC_TRANSPORTS.contains(&s.as_str());
+
+                if !self.valid {
+                    self.error =
+                        format!("Transport must be one of: {}.", OSC_TRANSPORTS.join(", "));
+                } else {
+                    self.error.clear();
+                }
+
+                if self.valid {
+                    *data = s;
+                } else {
+                    data.clear();
+                }
+
+                self.dirty = false;
+
+                self.valid
+            }
+            ConfigDataType::CheckKind(ref mut data) => {
+                // move the UI string into the data type
+                let s = self.str.trim().to_lowercase();
+
+                self.valid = HEALTH_CHECK_KINDS.contains(&s.as_str());
+
+                if !self.valid {
+                    self.error =
+                        format!("Check must be one of: {}.", HEALTH_CHECK_KINDS.join(", "));
+                } else {
+                    self.error.clear();
+                }
+
+                if self.valid {
+                    *data = s;
+                } else {
+                    data.clear();
+                }
+
+                self.dirty = false;
+
+                self.valid
+            }
+            ConfigDataType::Count(ref mut data) => {
+                // move the UI string into the data type
+                let valid_int: bool;
+                let count: usize = match self.str.trim().parse() {
+                    Ok(num) => {
+                        valid_int = true;
+                        num
+                    }
+                    Err(_) => {
+                        valid_int = false;
+                        0
+                    }
+                };
+
+                // valid range, 0 disables the limit
+                let in_range = count <= 4194304;
+
+                // SET validity
+                self.valid = valid_int && in_range;
+
+                // ADD errors for ui
+                if !valid_int {
+                    self.error = "Entry must be a valid positive integer.".to_string();
+                } else if !in_range {
+                    self.error = "Entry must be in between 0 and 4194304.".to_string();
+                } else {
+                    self.error.clear();
+                }
+
+                if self.valid {
+                    // APPLY new type safe value
+                    *data = count;
+                } else {
+                    // APPLY placeholder data since Ui string is invalid
+                    *data = 0;
+                }
+
+                self.dirty = false;
+
+                self.valid
+            }
+        }
+    }
+
+    // Set validity from external tests
+    pub fn invalidate(&mut self, error: String) {
+        self.valid = false;
+        self.error = error;
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ConfigDataType {
+    Text(String),
+    Channel(String),
+    Port(usize),
+    Seconds(usize),
+    Megabytes(usize),
+    Percent(usize),
+    Count(usize),
+    Time(String),
+    OptionalSeconds(usize),
+    Path(String),
+    Address(String),
+    Group(String),
+    Alert(String),
+    Relay(String),
+    Transport(String),
+    CheckKind(String),
+}
+
+impl From<&ConfigDataType> for u16 {
+    fn from(value: &ConfigDataType) -> Self {
+        match value {
+            ConfigDataType::Port(p) => p.clone() as u16,
+            ConfigDataType::Seconds(s) => s.clone() as u16,
+            _ => {
                 println!(
                     "Warning, casting invalid ConfigDataType into u16, using default value of 0"
                 );
@@ -344,6 +1261,10 @@ impl From<&ConfigDataType> for u64 {
         match value {
             ConfigDataType::Port(p) => p.clone() as u64,
             ConfigDataType::Seconds(s) => s.clone() as u64,
+            ConfigDataType::Megabytes(m) => *m as u64,
+            ConfigDataType::Percent(p) => *p as u64,
+            ConfigDataType::Count(c) => *c as u64,
+            ConfigDataType::OptionalSeconds(s) => *s as u64,
             _ => {
                 println!(
                     "Warning, casting invalid ConfigDataType into u64, using default value of 0"
@@ -359,6 +1280,14 @@ impl From<&ConfigDataType> for String {
         match value {
             ConfigDataType::Text(t) => t.clone(),
             ConfigDataType::Channel(c) => c.clone(),
+            ConfigDataType::Time(t) => t.clone(),
+            ConfigDataType::Path(p) => p.clone(),
+            ConfigDataType::Address(a) => a.clone(),
+            ConfigDataType::Alert(a) => a.clone(),
+            ConfigDataType::Relay(r) => r.clone(),
+            ConfigDataType::Group(g) => g.clone(),
+            ConfigDataType::Transport(t) => t.clone(),
+            ConfigDataType::CheckKind(k) => k.clone(),
             _ => {
                 println!(
                     "Warning, casting invalid ConfigDataType into String, using default value of empty string"
@@ -369,7 +1298,7 @@ impl From<&ConfigDataType> for String {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Debug)]
 pub struct EmailClient {
     pub enabled: bool,
     pub valid: bool,
@@ -380,11 +1309,227 @@ pub struct EmailClient {
     pub limit_per_day: ConfigData,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl EmailClient {
+    // Each value, by its name in the file
+    pub fn fields(&self) -> [(&'static str, &ConfigData); 5] {
+        [
+            ("address", &self.address),
+            ("password", &self.password),
+            ("email_on_startup", &self.email_on_startup),
+            ("email_on_failure", &self.email_on_failure),
+            ("limit_per_day", &self.limit_per_day),
+        ]
+    }
+}
+
+//
+// OSC port Watchdog itself is controlled on, by a show control system
+//
+#[derive(Debug)]
+pub struct RemoteControl {
+    pub enabled: bool,
+    pub valid: bool,
+    pub port: ConfigData,
+    pub bind_address: ConfigData,
+}
+
+impl RemoteControl {
+    pub fn default() -> Self {
+        RemoteControl {
+            enabled: false,
+            valid: false,
+            port: ConfigData::new_port(9000),
+            bind_address: ConfigData::new_address("0.0.0.0"),
+        }
+    }
+
+    // Each value, by its name in the file
+    pub fn fields(&self) -> [(&'static str, &ConfigData); 2] {
+        [("port", &self.port), ("bind_address", &self.bind_address)]
+    }
+}
+
+//
+// HTTP port the OSCQuery namespace is served on, for show control tools to discover Watchdog
+//
+#[derive(Debug)]
+pub struct OscQuery {
+    pub enabled: bool,
+    pub valid: bool,
+    pub port: ConfigData,
+    pub bind_address: ConfigData,
+}
+
+impl OscQuery {
+    pub fn default() -> Self {
+        OscQuery {
+            enabled: false,
+            valid: false,
+            port: ConfigData::new_port(9090),
+            bind_address: ConfigData::new_address("0.0.0.0"),
+        }
+    }
+
+    // Each value, by its name in the file
+    pub fn fields(&self) -> [(&'static str, &ConfigData); 2] {
+        [("port", &self.port), ("bind_address", &self.bind_address)]
+    }
+}
+
+//
+// Multicast group the status of this instance is announced to, every interval, and where the
+// other instances are heard. The interface is the local IPv4 address of the network to use
+//
+#[derive(Debug)]
+pub struct Announce {
+    pub enabled: bool,
+    pub valid: bool,
+    pub group: ConfigData,
+    pub port: ConfigData,
+    pub interface: ConfigData,
+    pub interval: ConfigData,
+}
+
+impl Announce {
+    pub fn default() -> Self {
+        Announce {
+            enabled: false,
+            valid: false,
+            group: ConfigData::new_address("239.255.42.99"),
+            port: ConfigData::new_port(9100),
+            interface: ConfigData::new_address("0.0.0.0"),
+            interval: ConfigData::new_seconds(5),
+        }
+    }
+
+    // Each value, by its name in the file
+    pub fn fields(&self) -> [(&'static str, &ConfigData); 4] {
+        [
+            ("group", &self.group),
+            ("port", &self.port),
+            ("interface", &self.interface),
+            ("interval", &self.interval),
+        ]
+    }
+}
+
+//
+// Why a config file could not be read. The file is left as it is
+//
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(std::io::Error),
+    // Not JSON, or a value of the wrong type. The line is 0 for files that were upgraded,
+    // as the upgrade is parsed instead of the text of the file
+    Parse {
+        line: usize,
+        column: usize,
+        // Path to the value, like: watched_apps[0].health_checks
+        field: String,
+        message: String,
+    },
+    // Saved by a newer version of Watchdog than this one
+    Newer(u64),
+    // The migration from this version failed
+    Migrate(u64, String),
+    Backup(PathBuf, std::io::Error),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Read(e) => write!(f, "could not be read: {}", e),
+            ConfigError::Parse {
+                line,
+                column,
+                field,
+                message,
+            } => {
+                write!(f, "is not a valid config")?;
+                if *line > 0 {
+                    write!(f, ", at line {} column {}", line, column)?;
+                }
+                if !field.is_empty() {
+                    write!(f, ", in {}", field)?;
+                }
+                write!(f, ": {}", message)
+            }
+            ConfigError::Newer(v) => write!(
+                f,
+                "was saved by a newer version of Watchdog, config version {}, this version reads up to {}",
+                v,
+                migrate::CONFIG_VERSION
+            ),
+            ConfigError::Migrate(v, e) => {
+                write!(f, "could not be upgraded from config version {}: {}", v, e)
+            }
+            ConfigError::Backup(p, e) => {
+                write!(f, "could not be backed up to {:?} before upgrading: {}", p, e)
+            }
+        }
+    }
+}
+
+impl ConfigError {
+    fn from_parse(e: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        // The root of the file has no field, and a key that could not be read is a ?
+        let field = match e.path().to_string().trim_end_matches(".?") {
+            "." | "?" => String::new(),
+            f => f.to_string(),
+        };
+        let e = e.into_inner();
+        let (line, column) = (e.line(), e.column());
+
+        // The message without the position, which is kept apart
+        let message = e.to_string();
+        let message = message
+            .strip_suffix(&format!(" at line {} column {}", line, column))
+            .unwrap_or(&message)
+            .to_string();
+
+        ConfigError::Parse {
+            line,
+            column,
+            field,
+            message,
+        }
+    }
+}
+
+// A file next to the config file, named after it, like: watchdog_config.json.v1.bak
+fn sibling_filepath(filepath: &Path, suffix: &str) -> PathBuf {
+    let mut name = filepath.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    filepath.with_file_name(name)
+}
+
+// Where the original of a file is kept when it is upgraded
+fn backup_filepath(filepath: &Path, version: u64) -> PathBuf {
+    sibling_filepath(filepath, &format!(".v{}.bak", version))
+}
+
+// Where a copy of the last file that was read successfully is kept
+fn good_backup_filepath(filepath: &Path) -> PathBuf {
+    sibling_filepath(filepath, ".good.bak")
+}
+
+// Where a file that could not be read is kept, when its backup is restored over it
+fn broken_filepath(filepath: &Path) -> PathBuf {
+    sibling_filepath(filepath, ".broken")
+}
+
+//
+// The config as edited in the Ui, with the text of each field and its validation. It is saved
+// to and loaded from the plain values of the schema
+//
+#[derive(Debug)]
 pub struct Config {
     pub valid: bool,
     pub watched_apps: Vec<WatchedApp>,
     pub email_client: EmailClient,
+    pub remote_control: RemoteControl,
+    pub oscquery: OscQuery,
+    pub announce: Announce,
     // email: Email,
     // network: Network,
 }
@@ -393,7 +1538,7 @@ impl Config {
     //
     // Create default Config instance
     //
-    fn default() -> Self {
+    pub fn default() -> Self {
         Self {
             valid: false,
             watched_apps: vec![WatchedApp::default()],
@@ -406,20 +1551,71 @@ impl Config {
                 email_on_failure: ConfigData::new_text("blake@blakerutledge.com"),
                 limit_per_day: ConfigData::new_text("3"),
             },
+            remote_control: RemoteControl::default(),
+            oscquery: OscQuery::default(),
+            announce: Announce::default(),
         }
     }
 
-    // Create customized Config instance by parsing JSON file
-    fn parse(filepath: &std::path::PathBuf) -> Result<Self> {
-        // Parse & Validate existing JSON Config file
-        let data = std::fs::read_to_string(filepath)
-            .expect(format!("Failed to read json config file {:?}", filepath).as_str());
+    //
+    // Create customized Config instance by parsing JSON file. Files saved by older versions
+    // are upgraded in memory only, along with the version they were upgraded from
+    //
+    pub fn parse(
+        filepath: &std::path::PathBuf,
+    ) -> std::result::Result<(Self, Option<u64>), ConfigError> {
+        let data = fs::read_to_string(filepath).map_err(ConfigError::Read)?;
 
         println!("Read existing json config");
 
-        let c: Result<Self> = serde_json::from_str(data.as_str());
+        let value: serde_json::Value = serde_path_to_error::deserialize(
+            &mut serde_json::Deserializer::from_str(data.as_str()),
+        )
+        .map_err(ConfigError::from_parse)?;
 
-        c
+        let version = migrate::version(&value);
+        if version > migrate::CONFIG_VERSION {
+            return Err(ConfigError::Newer(version));
+        }
+
+        if version == migrate::CONFIG_VERSION {
+            // Parse the text again, for errors to have the line and column
+            let file: schema::ConfigFile = serde_path_to_error::deserialize(
+                &mut serde_json::Deserializer::from_str(data.as_str()),
+            )
+            .map_err(ConfigError::from_parse)?;
+            return Ok((Config::from(file), None));
+        }
+
+        let upgraded =
+            migrate::upgrade(value, version).map_err(|(v, e)| ConfigError::Migrate(v, e))?;
+        let file: schema::ConfigFile =
+            serde_path_to_error::deserialize(upgraded).map_err(ConfigError::from_parse)?;
+        Ok((Config::from(file), Some(version)))
+    }
+
+    //
+    // Parse the main config file, at startup or when it is loaded or reloaded on request.
+    // A file saved by an older version is backed up, and the upgrade is saved over it
+    //
+    pub fn load(filepath: &std::path::PathBuf) -> std::result::Result<Self, ConfigError> {
+        let (c, upgraded_from) = Config::parse(filepath)?;
+        let version = match upgraded_from {
+            Some(v) => v,
+            None => return Ok(c),
+        };
+
+        let backup = backup_filepath(filepath, version);
+        fs::copy(filepath, &backup).map_err(|e| ConfigError::Backup(backup.clone(), e))?;
+        println!(
+            "Upgraded json config from version {}, the original is backed up to {:?}",
+            version, backup
+        );
+
+        if let Err(e) = history::write_atomic(filepath, &c.to_json()) {
+            println!("Failed to save the upgraded json config: {}", e);
+        }
+        Ok(c)
     }
 
     // Compare all values and mark as dirty
@@ -433,13 +1629,38 @@ impl Config {
             let mut v = true;
             v = v && w.name.validate();
             v = v && w.run.validate();
+            v = v && w.group.validate();
             v = v && w.osc_in_port.validate();
             v = v && w.osc_out_port.validate();
+            v = v && w.osc_bind_address.validate();
+            v = v && w.osc_client_address.validate();
+            v = v && w.osc_transport.validate();
             v = v && w.heartbeat_channel.validate();
             v = v && w.heartbeat_interval.validate();
             v = v && w.heartbeat_timeout.validate();
             v = v && w.startup_timeout.validate();
             v = v && w.restart_delay.validate();
+            v = v && w.memory_limit.validate();
+            v = v && w.cpu_limit.validate();
+            v = v && w.limit_duration.validate();
+            v = v && w.memory_max.validate();
+            v = v && w.cpu_max.validate();
+            v = v && w.pids_max.validate();
+            v = v && w.restart_schedule.validate();
+            v = v && w.cpu_idle_timeout.validate();
+            v = v && w.cpu_pinned_timeout.validate();
+            v = v && w.uninterruptible_timeout.validate();
+            v = v && w.log_file.validate();
+            v = v && w.log_silence_timeout.validate();
+            for h in w.health_checks.iter_mut() {
+                v = v && h.validate();
+            }
+            for a in w.telemetry_alerts.iter_mut() {
+                v = v && a.validate();
+            }
+            for r in w.relay_rules.iter_mut() {
+                v = v && r.validate();
+            }
             //
             // Add any props for Watched App validity here
             //
@@ -474,6 +1695,8 @@ impl Config {
                     // Not unique, add the error
                     if used {
                         w.osc_in_port.invalidate(port_clash_error.to_string());
+                        w.valid = false;
+                        valid = false;
                     }
                     // Is unique, add to the list
                     else {
@@ -493,6 +1716,8 @@ impl Config {
                     // Not unique, add the error
                     if used {
                         w.osc_out_port.invalidate(port_clash_error.to_string());
+                        w.valid = false;
+                        valid = false;
                     }
                     // Is unique, add to the list
                     else {
@@ -503,24 +1728,119 @@ impl Config {
         }
 
         //
-        // Sync all components of the email client config
-        if self.email_client.enabled {
+        // Address Families
+        //
+        let family_error = "Must be the same IP version as the bind address";
+        for w in self.watched_apps.iter_mut() {
+            if w.osc_bind_address.valid && w.osc_client_address.valid {
+                let bind = String::from(&w.osc_bind_address.val).parse::<IpAddr>();
+                let client = String::from(&w.osc_client_address.val).parse::<IpAddr>();
+                if let (Ok(bind), Ok(client)) = (bind, client) {
+                    // A socket bound to an IPv4 address cannot send to an IPv6 one, and vice versa
+                    if bind.is_ipv4() != client.is_ipv4() {
+                        w.osc_client_address.invalidate(family_error.to_string());
+                        w.valid = false;
+                        valid = false;
+                    }
+                }
+            }
+        }
+
+        //
+        // Sync all components of the email client config
+        if self.email_client.enabled {
+            let mut v = true;
+            v = v && self.email_client.address.validate();
+            v = v && self.email_client.password.validate();
+            v = v && self.email_client.email_on_startup.validate();
+            v = v && self.email_client.email_on_failure.validate();
+            v = v && self.email_client.limit_per_day.validate();
+            //
+            // Add any props for Email Client validity here
+            //
+            self.email_client.valid = v;
+        } else {
+            self.email_client.valid = true;
+        }
+
+        // Validity for entire config
+        valid = valid && self.email_client.valid;
+
+        //
+        // Sync all components of the remote control config
+        if self.remote_control.enabled {
+            let mut v = true;
+            v = v && self.remote_control.port.validate();
+            v = v && self.remote_control.bind_address.validate();
+
+            // Must not clash with the OSC ports of the watched apps
+            if let ConfigDataType::Port(p) = self.remote_control.port.val {
+                if v && used_ports.contains(&p) {
+                    self.remote_control
+                        .port
+                        .invalidate(port_clash_error.to_string());
+                    v = false;
+                }
+            }
+            self.remote_control.valid = v;
+        } else {
+            self.remote_control.valid = true;
+        }
+
+        valid = valid && self.remote_control.valid;
+
+        //
+        // Sync all components of the OSCQuery config, served over TCP so it cannot clash
+        // with the UDP ports above
+        if self.oscquery.enabled {
+            let mut v = true;
+            v = v && self.oscquery.port.validate();
+            v = v && self.oscquery.bind_address.validate();
+            self.oscquery.valid = v;
+        } else {
+            self.oscquery.valid = true;
+        }
+
+        valid = valid && self.oscquery.valid;
+
+        //
+        // Sync all components of the announce config
+        if self.announce.enabled {
             let mut v = true;
-            v = v && self.email_client.address.validate();
-            v = v && self.email_client.password.validate();
-            v = v && self.email_client.email_on_startup.validate();
-            v = v && self.email_client.email_on_failure.validate();
-            v = v && self.email_client.limit_per_day.validate();
-            //
-            // Add any props for Email Client validity here
-            //
-            self.email_client.valid = v;
+            v = v && self.announce.group.validate();
+            v = v && self.announce.port.validate();
+            v = v && self.announce.interface.validate();
+            v = v && self.announce.interval.validate();
+
+            // Must be a multicast group, and the interface an IPv4 address
+            let group = String::from(&self.announce.group.val).parse::<IpAddr>();
+            if v && !group.map(|g| g.is_multicast()).unwrap_or(false) {
+                self.announce.group.invalidate(
+                    "Must be a multicast address, like 239.255.42.99 or ff02::1234".to_string(),
+                );
+                v = false;
+            }
+            let interface = String::from(&self.announce.interface.val).parse::<IpAddr>();
+            if v && !interface.map(|i| i.is_ipv4()).unwrap_or(false) {
+                self.announce
+                    .interface
+                    .invalidate("Must be an IPv4 address, or 0.0.0.0 for the default".to_string());
+                v = false;
+            }
+
+            // Must not clash with the OSC ports of the watched apps
+            if let ConfigDataType::Port(p) = self.announce.port.val {
+                if v && used_ports.contains(&p) {
+                    self.announce.port.invalidate(port_clash_error.to_string());
+                    v = false;
+                }
+            }
+            self.announce.valid = v;
         } else {
-            self.email_client.valid = true;
+            self.announce.valid = true;
         }
 
-        // Validity for entire config
-        valid = valid && self.email_client.valid;
+        valid = valid && self.announce.valid;
 
         //
         // Add validity for any other sections here
@@ -528,15 +1848,77 @@ impl Config {
         self.valid = valid;
     }
 
+    //
+    // Why each invalid value is invalid, by its path in the file, like
+    // watched_apps[0].osc_in_port: Port must be... Sections that are not enabled are left out
+    //
+    pub fn errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut check = |path: String, d: &ConfigData| {
+            // Values without an error were never validated, as validation stopped before them
+            if !d.valid && !d.error.is_empty() {
+                errors.push(format!("{}: {}", path, d.error.trim_end_matches('.')));
+            }
+        };
+
+        for (i, w) in self.watched_apps.iter().enumerate() {
+            for (name, d) in w.fields() {
+                check(format!("watched_apps[{}].{}", i, name), d);
+            }
+            for (j, h) in w.health_checks.iter().enumerate() {
+                for (name, d) in h.fields() {
+                    check(
+                        format!("watched_apps[{}].health_checks[{}].{}", i, j, name),
+                        d,
+                    );
+                }
+            }
+            for (j, d) in w.telemetry_alerts.iter().enumerate() {
+                check(format!("watched_apps[{}].telemetry_alerts[{}]", i, j), d);
+            }
+            for (j, d) in w.relay_rules.iter().enumerate() {
+                check(format!("watched_apps[{}].relay_rules[{}]", i, j), d);
+            }
+        }
+
+        if self.email_client.enabled {
+            for (name, d) in self.email_client.fields() {
+                check(format!("email_client.{}", name), d);
+            }
+        }
+        if self.remote_control.enabled {
+            for (name, d) in self.remote_control.fields() {
+                check(format!("remote_control.{}", name), d);
+            }
+        }
+        if self.oscquery.enabled {
+            for (name, d) in self.oscquery.fields() {
+                check(format!("oscquery.{}", name), d);
+            }
+        }
+        if self.announce.enabled {
+            for (name, d) in self.announce.fields() {
+                check(format!("announce.{}", name), d);
+            }
+        }
+
+        errors
+    }
+
     // Helper to convert to JSON string
     fn to_json(&self) -> String {
-        serde_json::to_string_pretty(&self).unwrap()
+        serde_json::to_string_pretty(&schema::ConfigFile::from(self)).unwrap()
     }
 
-    // Store any changes to the Config instance to the JSON file
-    pub fn write(&self, filepath: &std::path::PathBuf) {
-        let data = &self.to_json();
-        fs::write(filepath, data).expect("Unable to write Watchdog Config JSON file");
+    //
+    // Store any changes to the Config instance to the JSON file, backing up the version on
+    // disk first when the last backup is not recent
+    //
+    pub fn write(&self, filepath: &Path) -> std::io::Result<()> {
+        if let Err(e) = history::backup(filepath, false) {
+            println!("Failed to back up the json config: {}", e);
+        }
+        history::write_atomic(filepath, &self.to_json())
     }
 }
 
@@ -563,37 +1945,47 @@ pub fn init(state: &mut State) -> Config {
         c.validate_all();
 
         // Write to disk
-        c.write(&state.json.filepath);
+        if let Err(e) = c.write(&state.json.filepath) {
+            println!("Error writing the new config Json file: {}", e);
+            notify_not_saved(state, &e);
+        }
+        state.json.parsed = true;
 
         c
     } else {
         // Initialize from existing file
-        let c = Config::parse(&state.json.filepath);
-
-        // Invalid json file to deserialize, reset the store to defaults
-        if c.is_err() {
-            println!("Error parsing the specified config Json file");
-
-            // Reset store, write
-            state.json.filepath = Store::default_config_filepath();
-            state.json.store.write(&state.json.filepath);
+        match Config::load(&state.json.filepath) {
+            Ok(mut c) => {
+                c.validate_all();
+                state.json.parsed = true;
+                keep_good_backup(&state.json.filepath);
 
-            // Load default config
-            let mut c = Config::default();
-
-            c.validate_all();
+                c
+            }
+            // Start with the defaults, but keep the file and the store as they are, for the
+            // file to be fixed, instead of saving the defaults over it. The Config tab
+            // offers ways to recover
+            Err(e) => {
+                println!("Error parsing the specified config Json file: {}", e);
+                let body = format!(
+                    "{:?} {}. Watchdog started with the default config, and will not save over the file.",
+                    state.json.filepath, e
+                );
+                notifications::notify(state, "Config could not be read", &body);
+                state.json.parsed = false;
+                state.json.error = Some(e.to_string());
 
-            c.write(&state.json.filepath);
+                let mut c = Config::default();
 
-            c
-        } else {
-            let mut c = c.unwrap();
-            c.validate_all();
+                c.validate_all();
 
-            c
+                c
+            }
         }
     };
 
+    state.undo.reset(&c, 0);
+
     c
 }
 
@@ -611,6 +2003,95 @@ pub fn create_watched_app(config: &mut Config, state: &mut State) {
     }
 }
 
+pub fn create_health_check(config: &mut Config, state: &mut State) {
+    let w = &mut config.watched_apps[state.ui.config_watched_app_index];
+    // Guard against creating more than the maximum
+    if w.health_checks.len() < MAX_HEALTH_CHECKS {
+        w.health_checks.push(HealthCheckConfig::default());
+        state.actions.config_edited = true;
+    } else {
+        println!(
+            "Config ERROR cannot create new health check, already using the maximum of {:?} checks",
+            MAX_HEALTH_CHECKS
+        );
+    }
+}
+
+pub fn delete_health_check(config: &mut Config, state: &mut State, index: usize) {
+    let w = &mut config.watched_apps[state.ui.config_watched_app_index];
+    // Ensure index is in bounds
+    if index >= w.health_checks.len() {
+        println!(
+            "Config ERROR cannot remove health check index, out of bounds: {:?}, there are {:?} health checks",
+            index,
+            w.health_checks.len()
+        );
+    } else {
+        w.health_checks.remove(index);
+        state.actions.config_edited = true;
+    }
+}
+
+pub fn create_telemetry_alert(config: &mut Config, state: &mut State) {
+    let w = &mut config.watched_apps[state.ui.config_watched_app_index];
+    // Guard against creating more than the maximum
+    if w.telemetry_alerts.len() < MAX_TELEMETRY_ALERTS {
+        w.telemetry_alerts
+            .push(ConfigData::new_alert("fps < 30 for 60s"));
+        state.actions.config_edited = true;
+    } else {
+        println!(
+            "Config ERROR cannot create new telemetry alert, already using the maximum of {:?} alerts",
+            MAX_TELEMETRY_ALERTS
+        );
+    }
+}
+
+pub fn delete_telemetry_alert(config: &mut Config, state: &mut State, index: usize) {
+    let w = &mut config.watched_apps[state.ui.config_watched_app_index];
+    // Ensure index is in bounds
+    if index >= w.telemetry_alerts.len() {
+        println!(
+            "Config ERROR cannot remove telemetry alert index, out of bounds: {:?}, there are {:?} alerts",
+            index,
+            w.telemetry_alerts.len()
+        );
+    } else {
+        w.telemetry_alerts.remove(index);
+        state.actions.config_edited = true;
+    }
+}
+
+pub fn create_relay_rule(config: &mut Config, state: &mut State) {
+    let w = &mut config.watched_apps[state.ui.config_watched_app_index];
+    // Guard against creating more than the maximum
+    if w.relay_rules.len() < MAX_RELAY_RULES {
+        w.relay_rules
+            .push(ConfigData::new_relay("/cue/* -> 127.0.0.1:53000"));
+        state.actions.config_edited = true;
+    } else {
+        println!(
+            "Config ERROR cannot create new relay rule, already using the maximum of {:?} rules",
+            MAX_RELAY_RULES
+        );
+    }
+}
+
+pub fn delete_relay_rule(config: &mut Config, state: &mut State, index: usize) {
+    let w = &mut config.watched_apps[state.ui.config_watched_app_index];
+    // Ensure index is in bounds
+    if index >= w.relay_rules.len() {
+        println!(
+            "Config ERROR cannot remove relay rule index, out of bounds: {:?}, there are {:?} rules",
+            index,
+            w.relay_rules.len()
+        );
+    } else {
+        w.relay_rules.remove(index);
+        state.actions.config_edited = true;
+    }
+}
+
 pub fn delete_watched_app(config: &mut Config, state: &mut State) {
     // Ensure index is in bounds
     if state.ui.config_watched_app_index >= config.watched_apps.len() {
@@ -641,22 +2122,37 @@ pub fn delete_watched_app(config: &mut Config, state: &mut State) {
 pub fn move_config(file: PathBuf, state: &mut State, config: &mut Config) {
     state.json.filepath = file;
     state.json.store.write(&state.json.filepath);
-    config.write(&state.json.filepath);
+    if let Err(e) = config.write(&state.json.filepath) {
+        notify_not_saved(state, &e);
+    }
+    state.json.parsed = true;
+    state.json.error = None;
+    state.json.changed = None;
+    state.json.dirty = false;
 }
 
 pub fn replace_from_file(file: PathBuf, state: &mut State, config: &mut Config) {
     // Read file from disk
-    let c = Config::parse(&file);
-    if c.is_err() {
-        println!("Error parsing this config Json file");
-    } else {
-        // Update path, update store, write
-        state.json.filepath = file;
-        state.json.store.write(&state.json.filepath);
-
-        // Replace config instance
-        *config = c.unwrap();
-        config.validate_all();
+    match Config::load(&file) {
+        Ok(c) => {
+            // Update path, update store, write
+            state.json.filepath = file;
+            state.json.store.write(&state.json.filepath);
+            state.json.parsed = true;
+            state.json.error = None;
+            state.json.changed = None;
+            state.json.dirty = false;
+            keep_good_backup(&state.json.filepath);
+
+            // Replace config instance
+            *config = c;
+            config.validate_all();
+            state.undo.reset(config, state.ui.config_watched_app_index);
+        }
+        Err(e) => {
+            println!("Error parsing this config Json file: {}", e);
+            notifications::notify(state, "Config not loaded", &format!("{:?} {}", file, e));
+        }
     }
 }
 
@@ -671,8 +2167,21 @@ pub fn reset_config(state: &mut State, config: &mut Config) {
 
 // Reset to default JSON filepath, and reset that to all defaults
 pub fn reinit_config(state: &mut State, config: &mut Config) {
+    // Keep a default file that could not be read, instead of resetting it
+    let default_filepath = Store::default_config_filepath();
+    if !state.json.parsed && default_filepath.exists() {
+        let broken = broken_filepath(&default_filepath);
+        if let Err(e) = fs::rename(&default_filepath, &broken) {
+            println!(
+                "Config ERROR cannot move {:?} to {:?}: {}",
+                default_filepath, broken, e
+            );
+            return;
+        }
+    }
+
     // Update path, update store, write
-    state.json.filepath = Store::default_config_filepath();
+    state.json.filepath = default_filepath;
     state.json.store.write(&state.json.filepath);
 
     // Reset the selected index
@@ -682,5 +2191,479 @@ pub fn reinit_config(state: &mut State, config: &mut Config) {
     let c = Config::default();
     *config = c;
     config.validate_all();
-    config.write(&state.json.filepath);
+    state.undo.reset(config, 0);
+    if let Err(e) = config.write(&state.json.filepath) {
+        notify_not_saved(state, &e);
+    }
+    state.json.parsed = true;
+    state.json.error = None;
+    state.json.changed = None;
+    state.json.dirty = false;
+}
+
+// Tell the operator that edits are not reaching the file
+pub fn notify_not_saved(state: &mut State, e: &std::io::Error) {
+    let body = format!("{:?} could not be written: {}", state.json.filepath, e);
+    notifications::notify(state, "Config not saved", &body);
+}
+
+// Copy a file that was read successfully, to restore if it is broken later
+pub fn keep_good_backup(filepath: &Path) {
+    let backup = good_backup_filepath(filepath);
+    if let Err(e) = fs::copy(filepath, &backup) {
+        println!("Failed to back up the config to {:?}: {}", backup, e);
+    }
+}
+
+// Whether the file that could not be read has a good backup to restore
+pub fn has_good_backup(state: &State) -> bool {
+    good_backup_filepath(&state.json.filepath).exists()
+}
+
+// Open the file that could not be read in a text editor, to be fixed and reloaded
+pub fn open_in_editor(state: &mut State) {
+    let editor = if cfg!(target_os = "windows") {
+        "notepad"
+    } else {
+        "xdg-open"
+    };
+    if let Err(e) = std::process::Command::new(editor)
+        .arg(&state.json.filepath)
+        .spawn()
+    {
+        notifications::notify(
+            state,
+            "Config not opened",
+            &format!("{} could not be started: {}", editor, e),
+        );
+    }
+}
+
+// Keep the defaults in memory, without saving them over the file that could not be read
+pub fn load_defaults(state: &mut State, config: &mut Config) {
+    state.ui.config_watched_app_index = 0;
+    state.json.error = None;
+    state.json.changed = None;
+    state.json.dirty = false;
+
+    *config = Config::default();
+    config.validate_all();
+    state.undo.reset(config, 0);
+}
+
+//
+// Restore the last good backup over the file that could not be read,
+// which is kept next to it as .broken
+//
+pub fn restore_good_backup(state: &mut State, config: &mut Config) {
+    let backup = good_backup_filepath(&state.json.filepath);
+    let c = match Config::parse(&backup) {
+        Ok((c, _)) => c,
+        Err(e) => {
+            notifications::notify(state, "Backup not restored", &format!("{:?} {}", backup, e));
+            return;
+        }
+    };
+
+    let broken = broken_filepath(&state.json.filepath);
+    if state.json.filepath.exists() {
+        if let Err(e) = fs::rename(&state.json.filepath, &broken) {
+            notifications::notify(
+                state,
+                "Backup not restored",
+                &format!(
+                    "{:?} could not be moved to {:?}: {}",
+                    state.json.filepath, broken, e
+                ),
+            );
+            return;
+        }
+    }
+    if let Err(e) = fs::copy(&backup, &state.json.filepath) {
+        notifications::notify(
+            state,
+            "Backup not restored",
+            &format!("{:?} could not be copied: {}", backup, e),
+        );
+        return;
+    }
+
+    state.ui.config_watched_app_index = 0;
+    state.json.parsed = true;
+    state.json.error = None;
+    state.json.changed = None;
+    state.json.dirty = false;
+
+    *config = c;
+    config.validate_all();
+    state.undo.reset(config, 0);
+
+    notifications::notify(
+        state,
+        "Config restored",
+        &format!(
+            "from {:?}, the file that could not be read is kept as {:?}",
+            backup, broken
+        ),
+    );
+}
+
+// Backups of the config file, newest first
+pub fn list_backups(state: &State) -> Vec<Backup> {
+    history::list(&state.json.filepath)
+}
+
+// Show the diff of a backup against the current file, or hide it when it is shown
+pub fn toggle_backup_diff(state: &mut State, backup: &Path) {
+    let shown = matches!(&state.ui.config_backup_diff, Some(d) if d.backup == backup);
+    state.ui.config_backup_diff = if shown {
+        None
+    } else {
+        Some(BackupDiff::new(backup, &state.json.filepath))
+    };
+}
+
+// Make the shown diff again when the file has been saved since
+pub fn refresh_backup_diff(state: &mut State) {
+    if let Some(d) = &state.ui.config_backup_diff {
+        if d.stale(&state.json.filepath) {
+            state.ui.config_backup_diff = Some(BackupDiff::new(&d.backup, &state.json.filepath));
+        }
+    }
+}
+
+//
+// Restore a backup over the config file. The version it replaces is backed up first,
+// so restoring can be undone from the list too
+//
+pub fn restore_backup(state: &mut State, config: &mut Config, backup: &Backup) {
+    let c = match Config::parse(&backup.path) {
+        Ok((c, _)) => c,
+        Err(e) => {
+            let body = format!("{:?} {}", backup.path, e);
+            notifications::notify(state, "Backup not restored", &body);
+            return;
+        }
+    };
+
+    if let Err(e) = history::backup(&state.json.filepath, true) {
+        let body = format!(
+            "{:?} could not be backed up before restoring: {}",
+            state.json.filepath, e
+        );
+        notifications::notify(state, "Backup not restored", &body);
+        return;
+    }
+
+    *config = c;
+    config.validate_all();
+    if let Err(e) = config.write(&state.json.filepath) {
+        notify_not_saved(state, &e);
+        return;
+    }
+
+    state.ui.config_watched_app_index = 0;
+    state.ui.config_backup_diff = None;
+    state.undo.reset(config, 0);
+    state.json.parsed = true;
+    state.json.error = None;
+    state.json.changed = None;
+    state.json.dirty = false;
+    keep_good_backup(&state.json.filepath);
+
+    notifications::notify(
+        state,
+        "Config restored",
+        &format!(
+            "from the backup of {}",
+            backup.time.format("%Y-%m-%d %H:%M:%S")
+        ),
+    );
+}
+
+// Undo the last edit, and save the config as it was before
+pub fn undo(state: &mut State, config: &mut Config) {
+    if let Some((c, app_index)) = state.undo.undo() {
+        replace_edited(state, config, c, app_index);
+    }
+}
+
+// Redo the last edit undone, and save it again
+pub fn redo(state: &mut State, config: &mut Config) {
+    if let Some((c, app_index)) = state.undo.redo() {
+        replace_edited(state, config, c, app_index);
+    }
+}
+
+fn replace_edited(state: &mut State, config: &mut Config, c: Config, app_index: usize) {
+    *config = c;
+    state.ui.config_watched_app_index = app_index;
+    save_edits(state, config);
+}
+
+//
+// Save the config as edited. A file that could not be read, or has a change on disk that
+// was not applied, is never saved over, and the edits are kept in memory only
+//
+pub fn save_edits(state: &mut State, config: &Config) {
+    if !state.json.parsed {
+        state.json.dirty = true;
+        return;
+    }
+    match config.write(&state.json.filepath) {
+        Ok(()) => state.json.dirty = false,
+        Err(e) => {
+            println!("Error writing the config Json file: {}", e);
+            state.json.dirty = true;
+            notify_not_saved(state, &e);
+        }
+    }
+}
+
+//
+// The config file changed on disk. A valid config is applied, as a step that can be
+// undone. Otherwise, or when there are edits here that are not saved to the file, the
+// current config is kept, and the file is not saved over until the operator chooses
+//
+pub fn file_changed(state: &mut State, config: &mut Config) {
+    let text = match fs::read_to_string(&state.json.filepath) {
+        Ok(t) => t,
+        Err(_) => return,
+    };
+    // Watchdog's own save
+    if state.json.parsed && text == config.to_json() {
+        return;
+    }
+    println!("Config file changed on disk {:?}", state.json.filepath);
+
+    let rejected = if state.json.dirty {
+        Some("edits here are not saved to it, and would be lost".to_string())
+    } else {
+        match Config::parse(&state.json.filepath) {
+            Ok((mut c, _)) => {
+                c.validate_all();
+                if c.valid {
+                    *config = c;
+                    state.ui.config_watched_app_index = state
+                        .ui
+                        .config_watched_app_index
+                        .min(config.watched_apps.len() - 1);
+                    state
+                        .undo
+                        .record_step(config, state.ui.config_watched_app_index);
+                    state.json.parsed = true;
+                    state.json.error = None;
+                    state.json.changed = None;
+                    keep_good_backup(&state.json.filepath);
+                    notifications::notify(
+                        state,
+                        "Config reloaded",
+                        "The file changed on disk. Undo in the Config tab to go back.",
+                    );
+                    None
+                } else {
+                    Some(format!("it has invalid values, {}", c.errors().join(", ")))
+                }
+            }
+            Err(e) => Some(format!("it {}", e)),
+        }
+    };
+
+    if let Some(why) = rejected {
+        let body = format!(
+            "{:?} changed on disk, but {}. The current config is kept.",
+            state.json.filepath, why
+        );
+        notifications::notify(state, "Config change not applied", &body);
+        state.json.parsed = false;
+        state.json.changed = Some(why);
+    }
+}
+
+// Keep the current config over a change to the file on disk, which is backed up first
+pub fn keep_current(state: &mut State, config: &Config) {
+    if let Err(e) = history::backup(&state.json.filepath, true) {
+        let body = format!("{:?} could not be backed up: {}", state.json.filepath, e);
+        notifications::notify(state, "Config not saved", &body);
+        return;
+    }
+    state.json.parsed = true;
+    state.json.changed = None;
+    save_edits(state, config);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The default app, and a second one whose OSC in port is the first one's out port
+    fn clashing_config() -> Config {
+        let mut c = Config::default();
+        let mut other = WatchedApp::default();
+        other.name = ConfigData::new_text("other");
+        other.osc_in_port = ConfigData::new_port(1235);
+        other.osc_out_port = ConfigData::new_port(1236);
+        c.watched_apps.push(other);
+        c.validate_all();
+        c
+    }
+
+    // An empty directory of its own for each test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("watchdog-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    const VERSION_1: &str = r#"{"valid": true, "watched_apps": [{"valid": true,
+        "name": {"str": "old", "val": {"Text": "old"}, "dirty": false, "valid": true, "error": ""}}]}"#;
+
+    #[test]
+    fn parse_upgrades_in_memory_only() {
+        let file = temp_dir("parse").join("w.json");
+        fs::write(&file, VERSION_1).unwrap();
+
+        let (c, upgraded_from) = Config::parse(&file).unwrap();
+        assert_eq!(upgraded_from, Some(1));
+        assert_eq!(c.watched_apps[0].name.str, "old");
+        assert_eq!(fs::read_to_string(&file).unwrap(), VERSION_1);
+        assert!(!backup_filepath(&file, 1).exists());
+    }
+
+    #[test]
+    fn load_backs_up_and_saves_the_upgrade() {
+        let file = temp_dir("load").join("w.json");
+        fs::write(&file, VERSION_1).unwrap();
+
+        let c = Config::load(&file).unwrap();
+        assert_eq!(c.watched_apps[0].name.str, "old");
+        assert_eq!(
+            fs::read_to_string(backup_filepath(&file, 1)).unwrap(),
+            VERSION_1
+        );
+        assert_eq!(fs::read_to_string(&file).unwrap(), c.to_json());
+        assert_eq!(Config::parse(&file).unwrap().1, None);
+    }
+
+    #[test]
+    fn parse_refuses_version_0_and_newer_files() {
+        let file = temp_dir("versions").join("w.json");
+        fs::write(&file, r#"{"version": 0, "watched_apps": []}"#).unwrap();
+        assert!(matches!(
+            Config::parse(&file),
+            Err(ConfigError::Migrate(0, _))
+        ));
+
+        let newer = format!(r#"{{"version": {}}}"#, migrate::CONFIG_VERSION + 1);
+        fs::write(&file, &newer).unwrap();
+        assert!(matches!(
+            Config::load(&file),
+            Err(ConfigError::Newer(v)) if v == migrate::CONFIG_VERSION + 1
+        ));
+        assert_eq!(fs::read_to_string(&file).unwrap(), newer);
+    }
+
+    fn route(rule: &str, address: &str) -> Option<String> {
+        RelayRule::parse(rule).unwrap().route(address)
+    }
+
+    #[test]
+    fn parses_relay_rules() {
+        let r = RelayRule::parse("/show/* -> 127.0.0.1:9000").unwrap();
+        assert_eq!(r.pattern, "/show/*");
+        assert_eq!(
+            r.target,
+            RelayTarget::Address("127.0.0.1:9000".parse().unwrap())
+        );
+        assert_eq!(r.rewrite, None);
+
+        let r = RelayRule::parse("/show/app1/*  ->  app as /*").unwrap();
+        assert_eq!(r.target, RelayTarget::App);
+        assert_eq!(r.rewrite.as_deref(), Some("/*"));
+        assert_eq!(r.to_string(), "/show/app1/* -> app as /*");
+    }
+
+    #[test]
+    fn rejects_malformed_relay_rules() {
+        assert!(RelayRule::parse("/show/*").is_err());
+        assert!(RelayRule::parse("/show/* => app").is_err());
+        assert!(RelayRule::parse("show/* -> app").is_err());
+        assert!(RelayRule::parse("/show/* -> localhost").is_err());
+        assert!(RelayRule::parse("/show/* -> app as cue").is_err());
+        assert!(RelayRule::parse("/show/* -> app as /?").is_err());
+        assert!(RelayRule::parse("/show/* -> app as /*/*").is_err());
+    }
+
+    #[test]
+    fn wildcards_match_within_a_part() {
+        assert_eq!(
+            route("/cue/* -> app", "/cue/go").as_deref(),
+            Some("/cue/go")
+        );
+        assert_eq!(route("/cue/* -> app", "/cue/a/b/c"), None);
+        assert_eq!(
+            route("/cue/*/go -> app", "/cue/a/go").as_deref(),
+            Some("/cue/a/go")
+        );
+        assert_eq!(route("/cue/*/go -> app", "/cue/a/b/go"), None);
+        assert_eq!(route("/cue/? -> app", "/cue/1").as_deref(), Some("/cue/1"));
+        assert_eq!(route("/cue/? -> app", "/cue/12"), None);
+        assert_eq!(route("/cue?go -> app", "/cue/go"), None);
+        assert_eq!(
+            route("/cue/*x -> app", "/cue/axbx").as_deref(),
+            Some("/cue/axbx")
+        );
+        assert_eq!(route("/cue/*x -> app", "/cue/axb"), None);
+        assert_eq!(route("/cue -> app", "/cue/go"), None);
+    }
+
+    #[test]
+    fn rewrites_with_what_each_wildcard_matched() {
+        assert_eq!(
+            route("/show/app1/* -> app as /*", "/show/app1/volume").as_deref(),
+            Some("/volume")
+        );
+        assert_eq!(
+            route(
+                "/*/light/*/level -> app as /dmx/*/*",
+                "/stage/light/12/level"
+            )
+            .as_deref(),
+            Some("/dmx/stage/12")
+        );
+        // Fewer * in the address than the pattern drops the last captures
+        assert_eq!(
+            route("/*/cue/* -> app as /go/*", "/a/cue/b").as_deref(),
+            Some("/go/a")
+        );
+        // A * matching nothing, and one at the end of the pattern
+        assert_eq!(
+            route("/cue*/* -> app as /*-*", "/cue/").as_deref(),
+            Some("/-")
+        );
+        assert_eq!(
+            route("/a*b*c -> app as /*/*", "/aXbYbZc").as_deref(),
+            Some("/X/YbZ")
+        );
+    }
+
+    #[test]
+    fn unique_ports_are_valid() {
+        let mut c = clashing_config();
+        c.watched_apps[1].osc_in_port = ConfigData::new_port(1237);
+        c.validate_all();
+        assert!(c.valid);
+        assert!(c.watched_apps.iter().all(|w| w.valid));
+    }
+
+    #[test]
+    fn port_clash_invalidates_the_app_and_the_config() {
+        let c = clashing_config();
+        assert!(!c.valid);
+        assert!(c.watched_apps[0].valid);
+        assert!(!c.watched_apps[1].valid);
+        assert!(!c.watched_apps[1].osc_in_port.valid);
+        assert!(c.watched_apps[1].osc_out_port.valid);
+    }
 }
@@ -0,0 +1,370 @@
+use chrono::{Duration, Local, NaiveDateTime, Timelike};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Backups kept of each config file, the oldest are removed past this
+pub const MAX_BACKUPS: usize = 10;
+
+// The file is saved on every edit, so it is only backed up once in this many seconds
+const BACKUP_INTERVAL: i64 = 60;
+
+// Lines of unchanged text shown around each change in a diff
+const DIFF_CONTEXT: usize = 3;
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+//
+// History
+//
+// Before the config file is saved over, the version on disk is copied next to it, named
+// with the time it was backed up, like watchdog_config.json.20240101-120000.bak.
+// Saving writes a temporary file and renames it over the config, so the config is either
+// the old version or the new one, never half written
+//
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub time: NaiveDateTime,
+}
+
+// A line of a diff, from a backup to the current file
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Same(String),
+    Removed(String),
+    Added(String),
+    // Unchanged lines left out, between the context of two changes
+    Skipped(usize),
+}
+
+// Write the file through a temporary file, renamed over it once it is complete
+pub fn write_atomic(filepath: &Path, data: &str) -> std::io::Result<()> {
+    let mut name = filepath.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let temp = filepath.with_file_name(name);
+
+    let mut file = fs::File::create(&temp)?;
+    file.write_all(data.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&temp, filepath)
+}
+
+//
+// Back up the file before it is saved over. Unless forced, it is skipped when the
+// newest backup is recent, and always when it has the same contents
+//
+pub fn backup(filepath: &Path, force: bool) -> std::io::Result<()> {
+    if !filepath.exists() {
+        return Ok(());
+    }
+
+    let backups = list(filepath);
+    // Backups are named to the second
+    let now = Local::now().naive_local();
+    let now = now.with_nanosecond(0).unwrap_or(now);
+    if let Some(newest) = backups.first() {
+        if !force && now - newest.time < Duration::seconds(BACKUP_INTERVAL) {
+            return Ok(());
+        }
+        if fs::read(&newest.path).ok() == Some(fs::read(filepath)?) {
+            return Ok(());
+        }
+    }
+
+    // A forced backup may follow another within the same second, keep both in order
+    let mut time = now;
+    while backups.iter().any(|b| b.time >= time) {
+        time += Duration::seconds(1);
+    }
+    fs::copy(filepath, backup_filepath(filepath, &time))?;
+
+    // Rotate out the oldest, the new backup is first in the list
+    for old in list(filepath).iter().skip(MAX_BACKUPS) {
+        if let Err(e) = fs::remove_file(&old.path) {
+            println!("Failed to remove old config backup {:?}: {}", old.path, e);
+        }
+    }
+    Ok(())
+}
+
+// Backups of the file, newest first
+pub fn list(filepath: &Path) -> Vec<Backup> {
+    let name = match filepath.file_name().and_then(|n| n.to_str()) {
+        Some(n) => format!("{}.", n),
+        None => return Vec::new(),
+    };
+    let dir = match filepath.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new("."),
+    };
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return Vec::new(),
+    };
+
+    // Other backups, like .v1.bak and .good.bak, have no timestamp and are left out
+    let mut backups: Vec<Backup> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let file_name = e.file_name();
+            let timestamp = file_name
+                .to_str()?
+                .strip_prefix(&name)?
+                .strip_suffix(".bak")?;
+            let time = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+            Some(Backup {
+                path: e.path(),
+                time,
+            })
+        })
+        .collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.time));
+    backups
+}
+
+fn backup_filepath(filepath: &Path, time: &NaiveDateTime) -> PathBuf {
+    let mut name = filepath.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.bak", time.format(TIMESTAMP_FORMAT)));
+    filepath.with_file_name(name)
+}
+
+//
+// The lines changed from the old text to the new, from their longest common subsequence,
+// with a few unchanged lines around each change
+//
+pub fn diff(old: &str, new: &str) -> Vec<Change> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    // Length of the common subsequence of a[i..] and b[j..]
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            changes.push(Change::Same(a[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || common[i][j + 1] >= common[i + 1][j]) {
+            changes.push(Change::Added(b[j].to_string()));
+            j += 1;
+        } else {
+            changes.push(Change::Removed(a[i].to_string()));
+            i += 1;
+        }
+    }
+
+    // Keep only the unchanged lines near a change
+    let near_change = |index: usize| {
+        let start = index.saturating_sub(DIFF_CONTEXT);
+        let end = (index + DIFF_CONTEXT + 1).min(changes.len());
+        changes[start..end]
+            .iter()
+            .any(|c| !matches!(c, Change::Same(_)))
+    };
+    let mut out = Vec::new();
+    let mut skipped = 0;
+    for (index, c) in changes.iter().enumerate() {
+        if matches!(c, Change::Same(_)) && !near_change(index) {
+            skipped += 1;
+            continue;
+        }
+        if skipped > 0 {
+            out.push(Change::Skipped(skipped));
+            skipped = 0;
+        }
+        out.push(c.clone());
+    }
+    if skipped > 0 {
+        out.push(Change::Skipped(skipped));
+    }
+    out
+}
+
+//
+// The diff of a backup against the current file, as shown in the Config tab. It is
+// made again when the file is saved
+//
+#[derive(Debug)]
+pub struct BackupDiff {
+    pub backup: PathBuf,
+    pub changes: Result<Vec<Change>, String>,
+    modified: Option<SystemTime>,
+}
+
+impl BackupDiff {
+    pub fn new(backup: &Path, filepath: &Path) -> Self {
+        let changes = match (fs::read_to_string(backup), fs::read_to_string(filepath)) {
+            (Ok(old), Ok(new)) => Ok(diff(&old, &new)),
+            (Err(e), _) => Err(format!("{:?} could not be read: {}", backup, e)),
+            (_, Err(e)) => Err(format!("{:?} could not be read: {}", filepath, e)),
+        };
+        BackupDiff {
+            backup: backup.to_path_buf(),
+            changes,
+            modified: modified(filepath),
+        }
+    }
+
+    // Whether the file has been saved since the diff was made
+    pub fn stale(&self, filepath: &Path) -> bool {
+        self.modified != modified(filepath)
+    }
+}
+
+fn modified(filepath: &Path) -> Option<SystemTime> {
+    fs::metadata(filepath).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A config file in an empty directory of its own for each test
+    fn temp_file(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("watchdog-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("w.json");
+        fs::write(&file, "current").unwrap();
+        file
+    }
+
+    // A backup as if it had been made long ago
+    fn old_backup(filepath: &Path, minute: u32, contents: &str) -> NaiveDateTime {
+        let time = chrono::NaiveDate::from_ymd_opt(2020, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, minute, 0)
+            .unwrap();
+        fs::write(backup_filepath(filepath, &time), contents).unwrap();
+        time
+    }
+
+    fn lines(text: &[&str]) -> String {
+        text.join("\n")
+    }
+
+    #[test]
+    fn diff_of_the_same_text_skips_every_line() {
+        let text = lines(&["a", "b", "c"]);
+        assert_eq!(diff(&text, &text), vec![Change::Skipped(3)]);
+        assert_eq!(diff("", ""), vec![]);
+    }
+
+    #[test]
+    fn diff_shows_changed_lines() {
+        assert_eq!(
+            diff(&lines(&["a", "b", "c"]), &lines(&["a", "x", "c", "d"])),
+            vec![
+                Change::Same("a".to_string()),
+                Change::Added("x".to_string()),
+                Change::Removed("b".to_string()),
+                Change::Same("c".to_string()),
+                Change::Added("d".to_string()),
+            ]
+        );
+        assert_eq!(diff("", "a"), vec![Change::Added("a".to_string())]);
+        assert_eq!(diff("a", ""), vec![Change::Removed("a".to_string())]);
+    }
+
+    #[test]
+    fn diff_keeps_context_around_each_change() {
+        let old: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+        let mut new = old.clone();
+        new[5] = "five".to_string();
+        new[15] = "fifteen".to_string();
+        let old = old.join("\n");
+        let new = new.join("\n");
+
+        let same = |i: usize| Change::Same(i.to_string());
+        let mut expected = vec![Change::Skipped(2), same(2), same(3), same(4)];
+        expected.push(Change::Added("five".to_string()));
+        expected.push(Change::Removed("5".to_string()));
+        expected.extend([same(6), same(7), same(8), Change::Skipped(3)]);
+        expected.extend([same(12), same(13), same(14)]);
+        expected.push(Change::Added("fifteen".to_string()));
+        expected.push(Change::Removed("15".to_string()));
+        expected.extend([same(16), same(17), same(18), Change::Skipped(1)]);
+        assert_eq!(diff(&old, &new), expected);
+    }
+
+    #[test]
+    fn backups_are_listed_newest_first() {
+        let file = temp_file("list");
+        let first = old_backup(&file, 1, "one");
+        let second = old_backup(&file, 2, "two");
+        // Not a timestamped backup
+        fs::write(file.with_file_name("w.json.v1.bak"), "v1").unwrap();
+
+        let times: Vec<NaiveDateTime> = list(&file).iter().map(|b| b.time).collect();
+        assert_eq!(times, vec![second, first]);
+    }
+
+    #[test]
+    fn backup_is_skipped_within_the_interval_unless_forced() {
+        let file = temp_file("interval");
+        backup(&file, false).unwrap();
+        assert_eq!(list(&file).len(), 1);
+
+        fs::write(&file, "edited").unwrap();
+        backup(&file, false).unwrap();
+        assert_eq!(list(&file).len(), 1);
+
+        backup(&file, true).unwrap();
+        let backups = list(&file);
+        assert_eq!(backups.len(), 2);
+        assert_eq!(fs::read_to_string(&backups[0].path).unwrap(), "edited");
+        assert!(backups[0].time > backups[1].time);
+    }
+
+    #[test]
+    fn backup_after_the_interval_and_not_of_the_same_contents() {
+        let file = temp_file("same");
+        old_backup(&file, 1, "older");
+        backup(&file, false).unwrap();
+        assert_eq!(list(&file).len(), 2);
+
+        // The newest backup already has these contents, even when forced
+        backup(&file, true).unwrap();
+        assert_eq!(list(&file).len(), 2);
+    }
+
+    #[test]
+    fn backups_past_the_max_are_removed_oldest_first() {
+        let file = temp_file("prune");
+        for minute in 0..MAX_BACKUPS as u32 + 2 {
+            old_backup(&file, minute, &minute.to_string());
+        }
+        backup(&file, false).unwrap();
+
+        let backups = list(&file);
+        assert_eq!(backups.len(), MAX_BACKUPS);
+        assert_eq!(fs::read_to_string(&backups[0].path).unwrap(), "current");
+        // The three oldest were removed to make room
+        let oldest = backups.last().unwrap();
+        assert_eq!(fs::read_to_string(&oldest.path).unwrap(), "3");
+    }
+
+    #[test]
+    fn write_atomic_replaces_the_file() {
+        let file = temp_file("atomic");
+        write_atomic(&file, "new").unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "new");
+        assert!(!file.with_file_name("w.json.tmp").exists());
+    }
+}
@@ -0,0 +1,180 @@
+use serde_json::{Map, Value};
+
+// Version of the config file written by this version of Watchdog
+pub const CONFIG_VERSION: u64 = 2;
+
+//
+// Migrations
+//
+// Each upgrades a config file from one version to the next, working on the JSON itself
+// so that it does not depend on the structs of any version. The migration at index i
+// upgrades version i + 1 to i + 2, so a release that changes the schema bumps the
+// version above and adds its migration at the end:
+//
+// 1    the Ui state of every value, as { str, val, dirty, valid, error }
+// 2    plain values, with a version field
+//
+const MIGRATIONS: [fn(Value) -> Result<Value, String>; 1] = [plain_values];
+
+//
+// The version a file was saved with. Files before version 2 have no version field,
+// and plain files without one were saved just before the field was added
+//
+pub fn version(value: &Value) -> u64 {
+    if let Some(v) = value.get("version").and_then(|v| v.as_u64()) {
+        return v;
+    }
+    if value.get("valid").is_some() {
+        1
+    } else {
+        2
+    }
+}
+
+// Run each migration from the file's version up to the current one
+pub fn upgrade(mut value: Value, from: u64) -> Result<Value, (u64, String)> {
+    // Versions start at 1, a 0 can only have been written by hand
+    if from == 0 {
+        return Err((from, "there is no version 0 of the config file".to_string()));
+    }
+    for version in from..CONFIG_VERSION {
+        let migration = match MIGRATIONS.get(version as usize - 1) {
+            Some(m) => m,
+            None => return Err((version, "no migration from this version".to_string())),
+        };
+        value = migration(value).map_err(|e| (version, e))?;
+
+        if let Value::Object(o) = &mut value {
+            o.insert("version".to_string(), Value::from(version + 1));
+        }
+    }
+    Ok(value)
+}
+
+//
+// 1 to 2, replace each value's Ui state with the text of the value, as a number for
+// numeric values, and drop the validity of each section
+//
+fn plain_values(value: Value) -> Result<Value, String> {
+    if !value.is_object() {
+        return Err("the config is not a JSON object".to_string());
+    }
+    Ok(plain(value))
+}
+
+fn plain(value: Value) -> Value {
+    match value {
+        Value::Object(o) if o.contains_key("str") && o.contains_key("val") => {
+            let text = o.get("str").and_then(|s| s.as_str()).unwrap_or_default();
+            let numeric = match o.get("val") {
+                Some(Value::Object(val)) => val.keys().any(|k| {
+                    matches!(
+                        k.as_str(),
+                        "Port" | "Seconds" | "Megabytes" | "Percent" | "Count" | "OptionalSeconds"
+                    )
+                }),
+                _ => false,
+            };
+            match text.trim().parse::<u64>() {
+                Ok(n) if numeric => Value::from(n),
+                _ => Value::from(text),
+            }
+        }
+        Value::Object(o) => {
+            let mut out = Map::new();
+            for (k, v) in o {
+                if k != "valid" {
+                    out.insert(k, plain(v));
+                }
+            }
+            Value::Object(out)
+        }
+        Value::Array(a) => Value::Array(a.into_iter().map(plain).collect()),
+        v => v,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // A file as version 1 saved it, each value with its Ui state
+    fn version_1() -> Value {
+        json!({
+            "valid": true,
+            "watched_apps": [{
+                "valid": true,
+                "name": { "str": "demo", "val": { "Text": "demo" }, "dirty": false, "valid": true, "error": "" },
+                "osc_in_port": { "str": "1234", "val": { "Port": 1234 }, "dirty": false, "valid": true, "error": "" },
+                "osc_out_port": { "str": "12ab", "val": { "Port": 0 }, "dirty": true, "valid": false, "error": "Must be a number" },
+                "heartbeat_timeout": { "str": " 5 ", "val": { "Seconds": 5 }, "dirty": false, "valid": true, "error": "" },
+                "health_checks": [{
+                    "kind": { "str": "tcp", "val": { "Text": "tcp" }, "dirty": false, "valid": true, "error": "" },
+                    "interval": { "str": "10", "val": { "OptionalSeconds": 10 }, "dirty": false, "valid": true, "error": "" }
+                }]
+            }],
+            "email_client": {
+                "enabled": false,
+                "valid": true,
+                "limit_per_day": { "str": "3", "val": { "Count": 3 }, "dirty": false, "valid": true, "error": "" }
+            }
+        })
+    }
+
+    #[test]
+    fn version_of_each_kind_of_file() {
+        assert_eq!(version(&json!({ "version": 2, "watched_apps": [] })), 2);
+        assert_eq!(version(&json!({ "version": 0 })), 0);
+        assert_eq!(version(&json!({ "version": 7 })), 7);
+        // Without a version field
+        assert_eq!(version(&version_1()), 1);
+        assert_eq!(version(&json!({ "watched_apps": [] })), 2);
+    }
+
+    #[test]
+    fn upgrades_version_1_to_current() {
+        let upgraded = upgrade(version_1(), 1).unwrap();
+        assert_eq!(
+            upgraded,
+            json!({
+                "version": 2,
+                "watched_apps": [{
+                    "name": "demo",
+                    "osc_in_port": 1234,
+                    "osc_out_port": "12ab",
+                    "heartbeat_timeout": 5,
+                    "health_checks": [{ "kind": "tcp", "interval": 10 }]
+                }],
+                "email_client": { "enabled": false, "limit_per_day": 3 }
+            })
+        );
+        assert_eq!(version(&upgraded), CONFIG_VERSION);
+    }
+
+    #[test]
+    fn current_version_is_left_as_is() {
+        let current = json!({ "version": 2, "watched_apps": [{ "name": "demo", "valid": true }] });
+        assert_eq!(upgrade(current.clone(), CONFIG_VERSION), Ok(current));
+    }
+
+    #[test]
+    fn version_0_is_rejected() {
+        let err = upgrade(json!({ "version": 0, "watched_apps": [] }), 0).unwrap_err();
+        assert_eq!(err.0, 0);
+    }
+
+    #[test]
+    fn newer_version_has_no_migration() {
+        // The caller refuses newer files before upgrading, which leaves them untouched
+        let newer = json!({ "version": 3 });
+        assert!(version(&newer) > CONFIG_VERSION);
+        assert_eq!(upgrade(newer.clone(), 3), Ok(newer));
+    }
+
+    #[test]
+    fn migration_error_names_the_version() {
+        assert_eq!(upgrade(json!([1, 2]), 1).unwrap_err().0, 1);
+        assert!(plain_values(json!("text")).is_err());
+    }
+}
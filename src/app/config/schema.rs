@@ -0,0 +1,512 @@
+use super::migrate::CONFIG_VERSION;
+use super::{
+    Announce, Config, ConfigData, ConfigDataType, EmailClient, HealthCheckConfig, OscQuery,
+    RemoteControl, WatchedApp,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+//
+// Schema
+//
+// The config as it is saved in the JSON file, with plain values only, like: "heartbeat_timeout": 5
+// The Config holds the text edited in the Ui and its validation instead, and is loaded from and
+// saved to this schema. A value is saved as the text in the Ui, as a number where the field is
+// a number, so values that are not valid yet are kept as typed. Missing values are defaults
+//
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ConfigFile {
+    pub version: u64,
+    pub watched_apps: Vec<WatchedAppFile>,
+    pub email_client: EmailClientFile,
+    pub remote_control: RemoteControlFile,
+    pub oscquery: OscQueryFile,
+    pub announce: AnnounceFile,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct WatchedAppFile {
+    pub name: Value,
+    pub run: Value,
+    pub group: Value,
+    pub osc_in_port: Value,
+    pub osc_out_port: Value,
+    pub osc_bind_address: Value,
+    pub osc_client_address: Value,
+    pub osc_transport: Value,
+    pub heartbeat_channel: Value,
+    pub heartbeat_interval: Value,
+    pub heartbeat_timeout: Value,
+    pub startup_timeout: Value,
+    pub restart_delay: Value,
+    pub memory_limit: Value,
+    pub cpu_limit: Value,
+    pub limit_duration: Value,
+    pub memory_max: Value,
+    pub cpu_max: Value,
+    pub pids_max: Value,
+    pub restart_schedule: Value,
+    pub cpu_idle_timeout: Value,
+    pub cpu_pinned_timeout: Value,
+    pub uninterruptible_timeout: Value,
+    pub log_file: Value,
+    pub log_silence_timeout: Value,
+    pub health_checks: Vec<HealthCheckFile>,
+    pub telemetry_alerts: Vec<String>,
+    pub relay_rules: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct HealthCheckFile {
+    pub kind: Value,
+    pub target: Value,
+    pub expect: Value,
+    pub interval: Value,
+    pub timeout: Value,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct EmailClientFile {
+    pub enabled: bool,
+    pub address: Value,
+    pub password: Value,
+    pub email_on_startup: Value,
+    pub email_on_failure: Value,
+    pub limit_per_day: Value,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct RemoteControlFile {
+    pub enabled: bool,
+    pub port: Value,
+    pub bind_address: Value,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct OscQueryFile {
+    pub enabled: bool,
+    pub port: Value,
+    pub bind_address: Value,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct AnnounceFile {
+    pub enabled: bool,
+    pub group: Value,
+    pub port: Value,
+    pub interface: Value,
+    pub interval: Value,
+}
+
+//
+// A value of the file as the text of a field, or the field's default when it is missing.
+// Validated on its own, as validating the whole config stops at the first invalid field
+//
+fn load(value: Value, default: ConfigData) -> ConfigData {
+    let str = match value {
+        Value::Null => return default,
+        Value::String(s) => s,
+        v => v.to_string(),
+    };
+    let mut data = ConfigData { str, ..default };
+    data.validate();
+    data
+}
+
+// The text of a field as a value of the file
+fn save(data: &ConfigData) -> Value {
+    match data.val {
+        ConfigDataType::Port(_)
+        | ConfigDataType::Seconds(_)
+        | ConfigDataType::Megabytes(_)
+        | ConfigDataType::Percent(_)
+        | ConfigDataType::Count(_)
+        | ConfigDataType::OptionalSeconds(_) => match data.str.trim().parse::<u64>() {
+            Ok(n) => Value::from(n),
+            Err(_) => Value::String(data.str.clone()),
+        },
+        _ => Value::String(data.str.clone()),
+    }
+}
+
+//
+// Loading
+//
+impl From<ConfigFile> for Config {
+    fn from(f: ConfigFile) -> Self {
+        let d = Config::default();
+
+        let mut watched_apps: Vec<WatchedApp> =
+            f.watched_apps.into_iter().map(WatchedApp::from).collect();
+        // The Ui always has an app selected
+        if watched_apps.is_empty() {
+            watched_apps = d.watched_apps;
+        }
+
+        Config {
+            valid: false,
+            watched_apps,
+            email_client: EmailClient::from(f.email_client),
+            remote_control: RemoteControl::from(f.remote_control),
+            oscquery: OscQuery::from(f.oscquery),
+            announce: Announce::from(f.announce),
+        }
+    }
+}
+
+impl From<WatchedAppFile> for WatchedApp {
+    fn from(f: WatchedAppFile) -> Self {
+        let d = WatchedApp::default();
+        WatchedApp {
+            valid: false,
+            name: load(f.name, d.name),
+            run: load(f.run, d.run),
+            group: load(f.group, d.group),
+            osc_in_port: load(f.osc_in_port, d.osc_in_port),
+            osc_out_port: load(f.osc_out_port, d.osc_out_port),
+            osc_bind_address: load(f.osc_bind_address, d.osc_bind_address),
+            osc_client_address: load(f.osc_client_address, d.osc_client_address),
+            osc_transport: load(f.osc_transport, d.osc_transport),
+            heartbeat_channel: load(f.heartbeat_channel, d.heartbeat_channel),
+            heartbeat_interval: load(f.heartbeat_interval, d.heartbeat_interval),
+            heartbeat_timeout: load(f.heartbeat_timeout, d.heartbeat_timeout),
+            startup_timeout: load(f.startup_timeout, d.startup_timeout),
+            restart_delay: load(f.restart_delay, d.restart_delay),
+            memory_limit: load(f.memory_limit, d.memory_limit),
+            cpu_limit: load(f.cpu_limit, d.cpu_limit),
+            limit_duration: load(f.limit_duration, d.limit_duration),
+            memory_max: load(f.memory_max, d.memory_max),
+            cpu_max: load(f.cpu_max, d.cpu_max),
+            pids_max: load(f.pids_max, d.pids_max),
+            restart_schedule: load(f.restart_schedule, d.restart_schedule),
+            cpu_idle_timeout: load(f.cpu_idle_timeout, d.cpu_idle_timeout),
+            cpu_pinned_timeout: load(f.cpu_pinned_timeout, d.cpu_pinned_timeout),
+            uninterruptible_timeout: load(f.uninterruptible_timeout, d.uninterruptible_timeout),
+            log_file: load(f.log_file, d.log_file),
+            log_silence_timeout: load(f.log_silence_timeout, d.log_silence_timeout),
+            health_checks: f
+                .health_checks
+                .into_iter()
+                .map(HealthCheckConfig::from)
+                .collect(),
+            telemetry_alerts: f
+                .telemetry_alerts
+                .into_iter()
+                .map(|a| load(Value::from(a), ConfigData::new_alert("")))
+                .collect(),
+            relay_rules: f
+                .relay_rules
+                .into_iter()
+                .map(|r| load(Value::from(r), ConfigData::new_relay("")))
+                .collect(),
+        }
+    }
+}
+
+impl From<HealthCheckFile> for HealthCheckConfig {
+    fn from(f: HealthCheckFile) -> Self {
+        let d = HealthCheckConfig::default();
+        HealthCheckConfig {
+            kind: load(f.kind, d.kind),
+            target: load(f.target, d.target),
+            expect: load(f.expect, d.expect),
+            interval: load(f.interval, d.interval),
+            timeout: load(f.timeout, d.timeout),
+        }
+    }
+}
+
+impl From<EmailClientFile> for EmailClient {
+    fn from(f: EmailClientFile) -> Self {
+        let d = Config::default().email_client;
+        EmailClient {
+            enabled: f.enabled,
+            valid: false,
+            address: load(f.address, d.address),
+            password: load(f.password, d.password),
+            email_on_startup: load(f.email_on_startup, d.email_on_startup),
+            email_on_failure: load(f.email_on_failure, d.email_on_failure),
+            limit_per_day: load(f.limit_per_day, d.limit_per_day),
+        }
+    }
+}
+
+impl From<RemoteControlFile> for RemoteControl {
+    fn from(f: RemoteControlFile) -> Self {
+        let d = RemoteControl::default();
+        RemoteControl {
+            enabled: f.enabled,
+            valid: false,
+            port: load(f.port, d.port),
+            bind_address: load(f.bind_address, d.bind_address),
+        }
+    }
+}
+
+impl From<OscQueryFile> for OscQuery {
+    fn from(f: OscQueryFile) -> Self {
+        let d = OscQuery::default();
+        OscQuery {
+            enabled: f.enabled,
+            valid: false,
+            port: load(f.port, d.port),
+            bind_address: load(f.bind_address, d.bind_address),
+        }
+    }
+}
+
+impl From<AnnounceFile> for Announce {
+    fn from(f: AnnounceFile) -> Self {
+        let d = Announce::default();
+        Announce {
+            enabled: f.enabled,
+            valid: false,
+            group: load(f.group, d.group),
+            port: load(f.port, d.port),
+            interface: load(f.interface, d.interface),
+            interval: load(f.interval, d.interval),
+        }
+    }
+}
+
+//
+// Saving
+//
+impl From<&Config> for ConfigFile {
+    fn from(c: &Config) -> Self {
+        ConfigFile {
+            version: CONFIG_VERSION,
+            watched_apps: c.watched_apps.iter().map(WatchedAppFile::from).collect(),
+            email_client: EmailClientFile::from(&c.email_client),
+            remote_control: RemoteControlFile::from(&c.remote_control),
+            oscquery: OscQueryFile::from(&c.oscquery),
+            announce: AnnounceFile::from(&c.announce),
+        }
+    }
+}
+
+impl From<&WatchedApp> for WatchedAppFile {
+    fn from(w: &WatchedApp) -> Self {
+        WatchedAppFile {
+            name: save(&w.name),
+            run: save(&w.run),
+            group: save(&w.group),
+            osc_in_port: save(&w.osc_in_port),
+            osc_out_port: save(&w.osc_out_port),
+            osc_bind_address: save(&w.osc_bind_address),
+            osc_client_address: save(&w.osc_client_address),
+            osc_transport: save(&w.osc_transport),
+            heartbeat_channel: save(&w.heartbeat_channel),
+            heartbeat_interval: save(&w.heartbeat_interval),
+            heartbeat_timeout: save(&w.heartbeat_timeout),
+            startup_timeout: save(&w.startup_timeout),
+            restart_delay: save(&w.restart_delay),
+            memory_limit: save(&w.memory_limit),
+            cpu_limit: save(&w.cpu_limit),
+            limit_duration: save(&w.limit_duration),
+            memory_max: save(&w.memory_max),
+            cpu_max: save(&w.cpu_max),
+            pids_max: save(&w.pids_max),
+            restart_schedule: save(&w.restart_schedule),
+            cpu_idle_timeout: save(&w.cpu_idle_timeout),
+            cpu_pinned_timeout: save(&w.cpu_pinned_timeout),
+            uninterruptible_timeout: save(&w.uninterruptible_timeout),
+            log_file: save(&w.log_file),
+            log_silence_timeout: save(&w.log_silence_timeout),
+            health_checks: w.health_checks.iter().map(HealthCheckFile::from).collect(),
+            telemetry_alerts: w.telemetry_alerts.iter().map(|a| a.str.clone()).collect(),
+            relay_rules: w.relay_rules.iter().map(|r| r.str.clone()).collect(),
+        }
+    }
+}
+
+impl From<&HealthCheckConfig> for HealthCheckFile {
+    fn from(h: &HealthCheckConfig) -> Self {
+        HealthCheckFile {
+            kind: save(&h.kind),
+            target: save(&h.target),
+            expect: save(&h.expect),
+            interval: save(&h.interval),
+            timeout: save(&h.timeout),
+        }
+    }
+}
+
+impl From<&EmailClient> for EmailClientFile {
+    fn from(e: &EmailClient) -> Self {
+        EmailClientFile {
+            enabled: e.enabled,
+            address: save(&e.address),
+            password: save(&e.password),
+            email_on_startup: save(&e.email_on_startup),
+            email_on_failure: save(&e.email_on_failure),
+            limit_per_day: save(&e.limit_per_day),
+        }
+    }
+}
+
+impl From<&RemoteControl> for RemoteControlFile {
+    fn from(r: &RemoteControl) -> Self {
+        RemoteControlFile {
+            enabled: r.enabled,
+            port: save(&r.port),
+            bind_address: save(&r.bind_address),
+        }
+    }
+}
+
+impl From<&OscQuery> for OscQueryFile {
+    fn from(o: &OscQuery) -> Self {
+        OscQueryFile {
+            enabled: o.enabled,
+            port: save(&o.port),
+            bind_address: save(&o.bind_address),
+        }
+    }
+}
+
+impl From<&Announce> for AnnounceFile {
+    fn from(a: &Announce) -> Self {
+        AnnounceFile {
+            enabled: a.enabled,
+            group: save(&a.group),
+            port: save(&a.port),
+            interface: save(&a.interface),
+            interval: save(&a.interval),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn load_file(value: Value) -> Config {
+        Config::from(serde_json::from_value::<ConfigFile>(value).unwrap())
+    }
+
+    fn save_file(config: &Config) -> Value {
+        serde_json::to_value(ConfigFile::from(config)).unwrap()
+    }
+
+    // A file with every field, values that are not valid, and each of the lists
+    fn full_file() -> Value {
+        let mut file = save_file(&Config::default());
+        let default_app = file["watched_apps"][0].clone();
+        file["watched_apps"]
+            .as_array_mut()
+            .unwrap()
+            .push(default_app);
+
+        let app = &mut file["watched_apps"][0];
+        app["name"] = json!("show");
+        app["osc_in_port"] = json!("12ab");
+        app["heartbeat_timeout"] = json!(12);
+        app["restart_schedule"] = json!("04:30");
+        app["health_checks"] = json!([{
+            "kind": "tcp",
+            "target": "127.0.0.1:9000",
+            "expect": "",
+            "interval": 10,
+            "timeout": 2
+        }]);
+        app["telemetry_alerts"] = json!(["fps < 30 for 60s"]);
+        app["relay_rules"] = json!(["/show/* -> app as /*"]);
+        file["email_client"]["enabled"] = json!(true);
+        file["announce"]["interval"] = json!("soon");
+
+        file
+    }
+
+    #[test]
+    fn load_then_save_is_unchanged() {
+        let file = full_file();
+        let c = load_file(file.clone());
+        assert_eq!(c.watched_apps.len(), 2);
+        assert_eq!(c.watched_apps[0].health_checks.len(), 1);
+        assert!(!c.watched_apps[0].osc_in_port.valid);
+        assert_eq!(save_file(&c), file);
+    }
+
+    #[test]
+    fn saved_text_is_unchanged() {
+        let text = load_file(full_file()).to_json();
+        let c = load_file(serde_json::from_str(&text).unwrap());
+        assert_eq!(c.to_json(), text);
+    }
+
+    #[test]
+    fn values_are_kept_as_typed() {
+        let c = load_file(json!({
+            "watched_apps": [{ "osc_in_port": "1234", "osc_out_port": " 99x", "heartbeat_interval": 3 }]
+        }));
+        let app = &c.watched_apps[0];
+        assert_eq!(app.osc_in_port.str, "1234");
+        assert_eq!(app.osc_out_port.str, " 99x");
+        assert_eq!(app.heartbeat_interval.str, "3");
+
+        // Numeric fields are saved as numbers when they are numbers
+        let saved = save_file(&c);
+        assert_eq!(saved["watched_apps"][0]["osc_in_port"], json!(1234));
+        assert_eq!(saved["watched_apps"][0]["osc_out_port"], json!(" 99x"));
+        assert_eq!(saved["watched_apps"][0]["heartbeat_interval"], json!(3));
+    }
+
+    #[test]
+    fn missing_fields_are_defaults() {
+        let d = Config::default();
+        let c = load_file(json!({ "watched_apps": [{ "name": "show" }] }));
+        let app = &c.watched_apps[0];
+        assert_eq!(app.name.str, "show");
+        assert_eq!(app.run.str, d.watched_apps[0].run.str);
+        assert_eq!(app.osc_in_port.str, d.watched_apps[0].osc_in_port.str);
+        assert!(app.health_checks.is_empty());
+        assert!(!c.email_client.enabled);
+        assert_eq!(
+            c.email_client.limit_per_day.str,
+            d.email_client.limit_per_day.str
+        );
+
+        let saved = save_file(&c);
+        assert_eq!(saved["version"], json!(CONFIG_VERSION));
+        assert_eq!(saved["remote_control"], save_file(&d)["remote_control"]);
+
+        // The Ui always has an app selected
+        let c = load_file(json!({}));
+        assert_eq!(c.watched_apps.len(), 1);
+        assert_eq!(c.watched_apps[0].name.str, d.watched_apps[0].name.str);
+    }
+
+    #[test]
+    fn unknown_fields_are_ignored() {
+        let c = load_file(json!({
+            "theme": "dark",
+            "watched_apps": [{ "name": "show", "colour": "red" }],
+            "announce": { "enabled": true, "ttl": 4 }
+        }));
+        assert_eq!(c.watched_apps[0].name.str, "show");
+        assert!(c.announce.enabled);
+
+        let saved = save_file(&c);
+        assert!(saved.get("theme").is_none());
+        assert!(saved["watched_apps"][0].get("colour").is_none());
+        assert!(saved["announce"].get("ttl").is_none());
+    }
+
+    #[test]
+    fn wrong_types_are_errors() {
+        let file = json!({ "watched_apps": { "name": "show" } });
+        assert!(serde_json::from_value::<ConfigFile>(file).is_err());
+        let file = json!({ "watched_apps": [{ "health_checks": 5 }] });
+        assert!(serde_json::from_value::<ConfigFile>(file).is_err());
+    }
+}
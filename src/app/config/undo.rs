@@ -0,0 +1,265 @@
+use super::{schema, Config};
+use std::time::{Duration, Instant};
+
+// Steps kept to undo, the oldest are forgotten past this
+const MAX_STEPS: usize = 100;
+
+// Edits to values closer together than this are undone as one, like typing a value
+const MERGE_EDITS: Duration = Duration::from_secs(1);
+
+//
+// Undo
+//
+// Every edit is saved to the file at once, so undoing restores the config as it was before
+// the edit, and saves that. Each step is the plain values of the config, as saved to the
+// file, with the watched app that was selected in the Config tab. Creating and deleting
+// watched apps are steps of their own, never merged with edits to values
+//
+
+struct Step {
+    file: String,
+    apps: usize,
+    app_index: usize,
+}
+
+pub struct Undo {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    // The config as of the last edit, which the next edit is undone to
+    current: Option<Step>,
+    // When the last edit to a value was, for the next to be merged with it
+    last_edit: Option<Instant>,
+}
+
+impl Step {
+    fn new(config: &Config, app_index: usize) -> Self {
+        Step {
+            file: config.to_json(),
+            apps: config.watched_apps.len(),
+            app_index,
+        }
+    }
+
+    fn config(&self) -> Option<Config> {
+        let file: schema::ConfigFile = serde_json::from_str(&self.file).ok()?;
+        let mut c = Config::from(file);
+        c.validate_all();
+        Some(c)
+    }
+}
+
+impl Undo {
+    pub fn new() -> Self {
+        Undo {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            current: None,
+            last_edit: None,
+        }
+    }
+
+    // Forget every step, when the config is replaced by another file or the defaults
+    pub fn reset(&mut self, config: &Config, app_index: usize) {
+        self.undo.clear();
+        self.redo.clear();
+        self.current = Some(Step::new(config, app_index));
+        self.last_edit = None;
+    }
+
+    // Add a step for the config as edited, unless nothing changed
+    pub fn record(&mut self, config: &Config, app_index: usize) {
+        let step = Step::new(config, app_index);
+        let current = match self.current.take() {
+            Some(c) if c.file == step.file => {
+                self.current = Some(c);
+                return;
+            }
+            Some(c) => c,
+            None => {
+                self.current = Some(step);
+                return;
+            }
+        };
+
+        let structural = step.apps != current.apps;
+        let merge = !structural
+            && self
+                .last_edit
+                .map(|t| t.elapsed() < MERGE_EDITS)
+                .unwrap_or(false);
+        if !merge {
+            self.undo.push(current);
+            if self.undo.len() > MAX_STEPS {
+                self.undo.remove(0);
+            }
+        }
+        self.redo.clear();
+        self.current = Some(step);
+        self.last_edit = if structural {
+            None
+        } else {
+            Some(Instant::now())
+        };
+    }
+
+    // Add a step that is never merged, for a config loaded from the file
+    pub fn record_step(&mut self, config: &Config, app_index: usize) {
+        self.last_edit = None;
+        self.record(config, app_index);
+        self.last_edit = None;
+    }
+
+    // The config before the last step, with the watched app that step edited selected
+    pub fn undo(&mut self) -> Option<(Config, usize)> {
+        let step = self.undo.pop()?;
+        let app_index = self.current.as_ref().map(|c| c.app_index);
+        self.step(step, app_index, false)
+    }
+
+    // The config of the last step undone, with the watched app it edited selected
+    pub fn redo(&mut self) -> Option<(Config, usize)> {
+        let step = self.redo.pop()?;
+        let app_index = Some(step.app_index);
+        self.step(step, app_index, true)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    // Make the step current, keeping the config it replaces to redo, or undo again
+    fn step(
+        &mut self,
+        step: Step,
+        app_index: Option<usize>,
+        redo: bool,
+    ) -> Option<(Config, usize)> {
+        let config = step.config()?;
+        let app_index = app_index
+            .unwrap_or(step.app_index)
+            .min(config.watched_apps.len().saturating_sub(1));
+        if let Some(c) = self.current.replace(step) {
+            if redo {
+                self.undo.push(c);
+            } else {
+                self.redo.push(c);
+            }
+        }
+        self.last_edit = None;
+        Some((config, app_index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::config::{ConfigData, WatchedApp};
+
+    fn named(name: &str) -> Config {
+        let mut c = Config::default();
+        c.watched_apps[0].name = ConfigData::new_text(name);
+        c
+    }
+
+    fn name(step: Option<(Config, usize)>) -> String {
+        step.unwrap().0.watched_apps[0].name.str.clone()
+    }
+
+    // As if the last edit was long enough ago not to be merged with the next
+    fn wait(undo: &mut Undo) {
+        undo.last_edit = undo.last_edit.and_then(|t| t.checked_sub(MERGE_EDITS));
+    }
+
+    #[test]
+    fn unchanged_config_is_not_a_step() {
+        let mut undo = Undo::new();
+        undo.reset(&named("a"), 0);
+        undo.record(&named("a"), 0);
+        assert!(!undo.can_undo());
+    }
+
+    #[test]
+    fn edits_within_a_second_are_merged() {
+        let mut undo = Undo::new();
+        undo.reset(&named("a"), 0);
+        undo.record(&named("ab"), 0);
+        undo.record(&named("abc"), 0);
+
+        assert_eq!(name(undo.undo()), "a");
+        assert!(!undo.can_undo());
+        assert_eq!(name(undo.redo()), "abc");
+    }
+
+    #[test]
+    fn edits_further_apart_are_steps() {
+        let mut undo = Undo::new();
+        undo.reset(&named("a"), 0);
+        undo.record(&named("b"), 0);
+        wait(&mut undo);
+        undo.record(&named("c"), 0);
+
+        assert_eq!(name(undo.undo()), "b");
+        assert_eq!(name(undo.undo()), "a");
+        assert!(undo.undo().is_none());
+    }
+
+    #[test]
+    fn creating_and_deleting_apps_are_steps_of_their_own() {
+        let mut undo = Undo::new();
+        let mut c = named("a");
+        undo.reset(&c, 0);
+
+        c.watched_apps[0].name = ConfigData::new_text("b");
+        undo.record(&c, 0);
+        // Created right after the edit, still not merged with it
+        c.watched_apps.push(WatchedApp::default());
+        undo.record(&c, 1);
+        // Nor is an edit right after creating
+        c.watched_apps[1].name = ConfigData::new_text("new");
+        undo.record(&c, 1);
+        c.watched_apps.remove(0);
+        undo.record(&c, 0);
+
+        let (before_delete, index) = undo.undo().unwrap();
+        assert_eq!(before_delete.watched_apps.len(), 2);
+        assert_eq!(index, 0);
+        let (before_edit, index) = undo.undo().unwrap();
+        assert_eq!(before_edit.watched_apps[1].name.str, "demo");
+        assert_eq!(index, 1);
+        let (before_create, index) = undo.undo().unwrap();
+        assert_eq!(before_create.watched_apps.len(), 1);
+        assert_eq!(before_create.watched_apps[0].name.str, "b");
+        // Selects an app that exists in the config undone to
+        assert_eq!(index, 0);
+        assert_eq!(name(undo.undo()), "a");
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut undo = Undo::new();
+        undo.reset(&named("a"), 0);
+        undo.record(&named("b"), 0);
+        assert_eq!(name(undo.undo()), "a");
+        assert!(undo.can_redo());
+
+        undo.record(&named("c"), 0);
+        assert!(!undo.can_redo());
+        assert!(undo.redo().is_none());
+        assert_eq!(name(undo.undo()), "a");
+    }
+
+    #[test]
+    fn reset_forgets_every_step() {
+        let mut undo = Undo::new();
+        undo.reset(&named("a"), 0);
+        undo.record(&named("b"), 0);
+        undo.undo();
+        undo.reset(&named("c"), 0);
+        assert!(!undo.can_undo());
+        assert!(!undo.can_redo());
+    }
+}
@@ -0,0 +1,71 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+// How often the config file is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+//
+// Watch
+//
+// Deploying a config with git pull or rsync writes the file behind Watchdog's back. The
+// modified time and size of the file are checked every second, and a change is only
+// reported once they have settled for a check, so a file still being written is not read
+//
+
+pub struct Watch {
+    filepath: PathBuf,
+    // Modified time and size of the file when last checked
+    stamp: Option<(SystemTime, u64)>,
+    // Changed since the check before, waiting to settle
+    changed: bool,
+    last_poll: Option<Instant>,
+}
+
+impl Watch {
+    pub fn new() -> Self {
+        Watch {
+            filepath: PathBuf::new(),
+            stamp: None,
+            changed: false,
+            last_poll: None,
+        }
+    }
+
+    //
+    // Whether the file has changed on disk and settled since, including Watchdog's own saves.
+    // Watching starts over when the filepath is another one
+    //
+    pub fn poll(&mut self, filepath: &Path) -> bool {
+        if filepath != self.filepath {
+            self.filepath = filepath.to_path_buf();
+            self.stamp = stamp(filepath);
+            self.changed = false;
+            return false;
+        }
+
+        if let Some(last) = self.last_poll {
+            if last.elapsed() < POLL_INTERVAL {
+                return false;
+            }
+        }
+        self.last_poll = Some(Instant::now());
+
+        let stamp = stamp(filepath);
+        if stamp != self.stamp {
+            self.stamp = stamp;
+            self.changed = true;
+            return false;
+        }
+
+        // A file that was removed is left alone, it is written again on the next edit
+        let settled = self.changed && stamp.is_some();
+        self.changed = false;
+        settled
+    }
+}
+
+fn stamp(filepath: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(filepath).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
@@ -0,0 +1,250 @@
+use super::apps;
+use super::config;
+use super::osc;
+
+use rosc::{encoder, OscMessage, OscPacket, OscType};
+use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+
+//
+// Remote Control
+//
+// Listens on the configured control port for OSC commands from a show control system,
+// like QLab or Companion. Commands are dispatched by the apply fn in app.rs, and replied
+// to the address they came from. Where a command takes a target, it is an app name or
+// group, and no target means every app:
+//
+// /watchdog/start [target]     start watching, or launch stopped apps
+// /watchdog/stop [target]      stop watching, or stop apps until started again
+// /watchdog/restart [target]
+// /watchdog/pause              keep apps running, but stop checking and restarting them
+// /watchdog/resume
+// /watchdog/reload             read the config file again
+// /watchdog/query              reply with the status of Watchdog and every app
+//
+// The start, stop and restart commands of a single app can also be sent to its own
+// address, as published by the OSCQuery server: /watchdog/apps/<name>/restart
+//
+// Replies are /watchdog/ack or /watchdog/error, with the command and a message, and for
+// a query /watchdog/state watching paused apps, then /watchdog/app name group state pid restarts
+//
+
+#[derive(Debug)]
+pub enum Command {
+    Start(Option<String>),
+    Stop(Option<String>),
+    Restart(Option<String>),
+    Pause,
+    Resume,
+    Reload,
+    Query,
+}
+
+impl Command {
+    fn from_message(msg: &OscMessage) -> Option<Self> {
+        let target = match msg.args.first() {
+            Some(OscType::String(s)) if !s.is_empty() => Some(s.clone()),
+            _ => None,
+        };
+
+        match msg.addr.as_str() {
+            "/watchdog/start" => Some(Command::Start(target)),
+            "/watchdog/stop" => Some(Command::Stop(target)),
+            "/watchdog/restart" => Some(Command::Restart(target)),
+            "/watchdog/pause" => Some(Command::Pause),
+            "/watchdog/resume" => Some(Command::Resume),
+            "/watchdog/reload" => Some(Command::Reload),
+            "/watchdog/query" => Some(Command::Query),
+            addr => Self::from_app_address(addr),
+        }
+    }
+
+    // /watchdog/apps/<name>/<command>, targeting the app named in the address
+    fn from_app_address(addr: &str) -> Option<Self> {
+        let rest = addr.strip_prefix("/watchdog/apps/")?;
+        let (name, command) = rest.split_once('/')?;
+        let target = Some(name.to_string());
+
+        match command {
+            "start" => Some(Command::Start(target)),
+            "stop" => Some(Command::Stop(target)),
+            "restart" => Some(Command::Restart(target)),
+            _ => None,
+        }
+    }
+
+    // The address of the command, echoed in replies
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Start(_) => "/watchdog/start",
+            Command::Stop(_) => "/watchdog/stop",
+            Command::Restart(_) => "/watchdog/restart",
+            Command::Pause => "/watchdog/pause",
+            Command::Resume => "/watchdog/resume",
+            Command::Reload => "/watchdog/reload",
+            Command::Query => "/watchdog/query",
+        }
+    }
+}
+
+pub struct Control {
+    socket: Option<UdpSocket>,
+    // Address the config asks for, to rebind when it changes
+    wanted: Option<SocketAddr>,
+    // Binding again after it failed
+    retry: osc::Retry,
+}
+
+impl Control {
+    pub fn new() -> Self {
+        Control {
+            socket: None,
+            wanted: None,
+            retry: osc::Retry::new(),
+        }
+    }
+
+    //
+    // Bind, rebind or close the control port to match the config, called every tick
+    // of the event loop. A failed bind is retried with a backoff. Returns the error if
+    // binding failed on this tick, the first time only
+    //
+    pub fn update(&mut self, c: &config::RemoteControl) -> Option<osc::BindError> {
+        let wanted = if c.enabled && c.valid {
+            let ip = String::from(&c.bind_address.val).parse::<IpAddr>().ok();
+            ip.map(|ip| SocketAddr::new(ip, u16::from(&c.port.val)))
+        } else {
+            None
+        };
+
+        if wanted != self.wanted {
+            self.wanted = wanted;
+            self.socket = None;
+            self.retry.reset();
+        }
+
+        let address = self.wanted?;
+        if self.socket.is_some() || !self.retry.due() {
+            return None;
+        }
+        let bound = UdpSocket::bind(address).and_then(|s| {
+            s.set_nonblocking(true)?;
+            Ok(s)
+        });
+        match bound {
+            Ok(s) => {
+                println!("Remote control listening on {}", address);
+                self.socket = Some(s);
+                self.retry.reset();
+                None
+            }
+            Err(e) => {
+                let e = osc::BindError::from_io(address, e);
+                let (wait, first) = self.retry.failed();
+                println!(
+                    "Remote control failed to bind: {}, retrying in {:?}",
+                    e, wait
+                );
+                first.then_some(e)
+            }
+        }
+    }
+
+    // Read every command waiting on the control port, without blocking
+    pub fn receive(&self) -> Vec<(Command, SocketAddr)> {
+        let mut commands = Vec::new();
+        let socket = match &self.socket {
+            Some(s) => s,
+            None => return commands,
+        };
+
+        let mut buffer = [0u8; rosc::decoder::MTU];
+        loop {
+            match socket.recv_from(&mut buffer) {
+                Ok((size, from)) => match rosc::decoder::decode_udp(&buffer[..size]) {
+                    Ok((_, packet)) => collect(packet, from, &mut commands),
+                    Err(e) => println!("Remote control dropped invalid OSC packet: {:?}", e),
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return commands,
+                // Windows reports an unreachable sender on the next receive, keep reading
+                Err(e) if e.kind() == ErrorKind::ConnectionReset => continue,
+                Err(e) => {
+                    println!("Remote control failed to receive: {}", e);
+                    return commands;
+                }
+            }
+        }
+    }
+
+    pub fn reply(&self, to: SocketAddr, addr: &str, args: Vec<OscType>) {
+        let socket = match &self.socket {
+            Some(s) => s,
+            None => return,
+        };
+
+        let msg_buf = encoder::encode(&OscPacket::Message(OscMessage {
+            addr: addr.to_string(),
+            args,
+        }))
+        .unwrap();
+
+        if let Err(e) = socket.send_to(&msg_buf, to) {
+            println!("Remote control failed to reply to {}: {}", to, e);
+        }
+    }
+
+    pub fn ack(&self, to: SocketAddr, command: &Command, message: &str) {
+        let args = vec![
+            OscType::String(command.name().to_string()),
+            OscType::String(message.to_string()),
+        ];
+        self.reply(to, "/watchdog/ack", args);
+    }
+
+    pub fn error(&self, to: SocketAddr, command: &Command, message: &str) {
+        let args = vec![
+            OscType::String(command.name().to_string()),
+            OscType::String(message.to_string()),
+        ];
+        self.reply(to, "/watchdog/error", args);
+    }
+
+    // Reply to a query with the status of Watchdog, then of each app
+    pub fn reply_status(&self, to: SocketAddr, apps: &apps::Apps) {
+        let args = vec![
+            OscType::Int(apps.watching() as i32),
+            OscType::Int(apps.paused() as i32),
+            OscType::Int(apps.iter().count() as i32),
+        ];
+        self.reply(to, "/watchdog/state", args);
+
+        for a in apps.iter() {
+            let args = vec![
+                OscType::String(a.name().to_string()),
+                OscType::String(a.group()),
+                OscType::String(a.state().name().to_string()),
+                OscType::Int(a.pid().unwrap_or(0) as i32),
+                OscType::Int(a.restarts() as i32),
+            ];
+            self.reply(to, "/watchdog/app", args);
+        }
+    }
+}
+
+fn collect(packet: OscPacket, from: SocketAddr, commands: &mut Vec<(Command, SocketAddr)>) {
+    match packet {
+        OscPacket::Message(msg) => match Command::from_message(&msg) {
+            Some(c) => commands.push((c, from)),
+            None => println!("Remote control unhandled OSC address: {}", msg.addr),
+        },
+        OscPacket::Bundle(bundle) => {
+            for p in bundle.content {
+                collect(p, from, commands);
+            }
+        }
+    }
+}
+
+pub fn init() -> Control {
+    Control::new()
+}
@@ -0,0 +1,29 @@
+use super::state::State;
+use chrono::{DateTime, Local};
+
+// Keep the most recent notifications only
+const MAX_NOTIFICATIONS: usize = 100;
+
+pub struct Notification {
+    pub time: DateTime<Local>,
+    pub title: String,
+    pub body: String,
+}
+
+//
+// Raise a notification for the operator, shown in the Ui as a toast
+//
+pub fn notify(state: &mut State, title: &str, body: &str) {
+    println!("Notification: {} - {}", title, body);
+
+    state.notifications.push_back(Notification {
+        time: Local::now(),
+        title: title.to_string(),
+        body: body.to_string(),
+    });
+
+    // Ensure the list is below max length, removing oldest elements
+    while state.notifications.len() > MAX_NOTIFICATIONS {
+        state.notifications.pop_front();
+    }
+}
@@ -2,100 +2,227 @@ use rosc::{encoder, OscMessage, OscPacket, OscType};
 
 // use super::utils::now;
 
-use super::config;
-use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use super::config::{self, RelayRule, RelayTarget};
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr, TcpListener, UdpSocket};
 use std::str::FromStr;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 // const LOCAL_IP: String = ;
 
+mod latency;
+mod reactor;
+mod retry;
+mod slip;
+mod telemetry;
+mod traffic;
+mod transport;
+
+pub use latency::Latency;
+pub use reactor::Reactor;
+pub use retry::Retry;
+pub use telemetry::{Telemetry, TelemetryValue};
+pub use traffic::{Direction, Packet, Traffic};
+
+use transport::Transport;
+
+// A heartbeat not echoed back within this long is counted as lost
+const LOSS_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Addresses an app can send to, to tell Watchdog things besides its heartbeat
+const ADDRESS_STATUS: &str = "/watchdog/status";
+const ADDRESS_ALERT: &str = "/watchdog/alert";
+const ADDRESS_REQUEST_RESTART: &str = "/watchdog/request_restart";
+
+// A message from an app, handled by the app state machine
+#[derive(Debug, Clone)]
+pub enum AppMessage {
+    // What the app is doing, shown in the Ui, like: loading content
+    Status(String),
+    // Raised as a notification, like: media drive missing
+    Alert(String),
+    // Ask to be restarted, with an optional reason
+    RequestRestart(Option<String>),
+}
+
 #[derive(Debug)]
 pub struct Interface {
-    socket_send: UdpSocket,
-    socket_recv: UdpSocket,
-    client: SocketAddrV4,
-    empty: SocketAddr,
+    // Non-blocking, polled by the reactor for both receiving and sending
+    transport: Transport,
     channel: String,
+    // Sequence number of the next heartbeat sent
+    sequence: i32,
+    // Heartbeats sent and not yet echoed back, oldest first, with the send time in their arguments
+    pending: VecDeque<(i32, f64, Instant)>,
+    latency: Arc<Mutex<Latency>>,
+    // Latest telemetry reported by the app
+    telemetry: Arc<Mutex<Telemetry>>,
+    // Messages received from the app, until taken by the app state machine
+    inbox: Arc<Mutex<Vec<AppMessage>>>,
+    // Every packet sent and received, for the traffic inspector
+    traffic: Arc<Mutex<Traffic>>,
+    // Where other messages on the app's port are forwarded to
+    relay_rules: Vec<RelayRule>,
+}
+
+// Why an OSC interface could not be built for an app
+#[derive(Debug, Clone)]
+pub enum BindError {
+    // Another program (or another watched app) already has the port
+    PortInUse(SocketAddr),
+    // Ports below 1024 need elevated permissions on some systems
+    PermissionDenied(SocketAddr),
+    InvalidAddress(String),
+    Other(SocketAddr, ErrorKind),
+}
+
+impl std::fmt::Display for BindError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindError::PortInUse(a) => write!(f, "port {} is already in use", a.port()),
+            BindError::PermissionDenied(a) => {
+                write!(f, "permission denied binding to {}", a)
+            }
+            BindError::InvalidAddress(a) => write!(f, "invalid address {}", a),
+            BindError::Other(a, e) => write!(f, "failed to bind to {}: {}", a, e),
+        }
+    }
+}
+
+impl BindError {
+    pub fn from_io(address: SocketAddr, e: std::io::Error) -> Self {
+        match e.kind() {
+            ErrorKind::AddrInUse => BindError::PortInUse(address),
+            ErrorKind::PermissionDenied => BindError::PermissionDenied(address),
+            ErrorKind::AddrNotAvailable => BindError::InvalidAddress(address.to_string()),
+            kind => BindError::Other(address, kind),
+        }
+    }
+}
+
+//
+// An app name as a part of an OSC address, characters with a meaning in OSC addresses
+// (like spaces, slashes or wildcards) are replaced with an underscore
+//
+pub fn address_segment(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect()
 }
 
 impl Interface {
-    pub fn build(c: &config::WatchedApp) -> Option<Interface> {
+    pub fn build(c: &config::WatchedApp) -> Result<Interface, BindError> {
         // Convert ports to u16
         let port_host: u16 = u16::from(&c.osc_out_port.val);
         let port_client: u16 = u16::from(&c.osc_in_port.val);
 
-        // Create IPV4 validated type for local host
-        let host_str = "127.0.0.1";
-        let host = match Ipv4Addr::from_str(host_str) {
-            Ok(host) => host,
-            Err(_) => panic!("Could not create IPV4 from address: {}", host_str),
-        };
-
-        // Create IPV4 addresses for both ports
-        let address_host = SocketAddrV4::new(host, port_host);
-        let address_client = SocketAddrV4::new(host, port_client);
+        // Parse the validated addresses, IPv4 or IPv6
+        let bind_str = String::from(&c.osc_bind_address.val);
+        let bind = IpAddr::from_str(&bind_str).map_err(|_| BindError::InvalidAddress(bind_str))?;
+        let client_str = String::from(&c.osc_client_address.val);
+        let client =
+            IpAddr::from_str(&client_str).map_err(|_| BindError::InvalidAddress(client_str))?;
+
+        // A socket can only send to addresses of the family it is bound with
+        if bind.is_ipv4() != client.is_ipv4() {
+            return Err(BindError::InvalidAddress(format!(
+                "{}, not the same IP version as {}",
+                client, bind
+            )));
+        }
 
-        // Bind to the port
-        let host_err_str = format!("OSC failed to bind to {:?}", address_host);
-        let socket_host = UdpSocket::bind(address_host).expect(&host_err_str);
+        // Create socket addresses for both ports
+        let address_host = SocketAddr::new(bind, port_host);
+        let address_client = SocketAddr::new(client, port_client);
 
-        let _ = socket_host.set_read_timeout(Some(Duration::from_millis(10)));
+        // Bind to the port, over TCP the app connects to it instead of being sent to
+        let bind_error = |e| BindError::from_io(address_host, e);
+        let transport = match String::from(&c.osc_transport.val).as_str() {
+            "tcp" => {
+                let listener = TcpListener::bind(address_host).map_err(bind_error)?;
 
-        let empty = SocketAddr::from_str("127.0.0.1:9999").unwrap();
-        //
-        // TODO: handle the error without panicking
-        //
+                // The reactor waits on the socket instead of blocking on it
+                listener.set_nonblocking(true).map_err(bind_error)?;
+                Transport::tcp(listener, client)
+            }
+            _ => {
+                let socket = UdpSocket::bind(address_host).map_err(bind_error)?;
 
-        let socket_host_clone = socket_host.try_clone().unwrap();
+                // The reactor waits on the socket instead of blocking on it
+                socket.set_nonblocking(true).map_err(bind_error)?;
+                Transport::udp(socket, address_client)
+            }
+        };
 
         let i = Interface {
-            socket_recv: socket_host,
-            socket_send: socket_host_clone,
-            client: address_client,
+            transport,
             channel: String::from(&c.heartbeat_channel.val),
-            empty: empty,
+            sequence: 0,
+            pending: VecDeque::new(),
+            latency: Arc::new(Mutex::new(Latency::new())),
+            telemetry: Arc::new(Mutex::new(Telemetry::new())),
+            inbox: Arc::new(Mutex::new(Vec::new())),
+            traffic: Arc::new(Mutex::new(Traffic::new())),
+            // Rules are validated with the config, before any app is watched
+            relay_rules: c
+                .relay_rules
+                .iter()
+                .filter_map(|r| RelayRule::parse(&String::from(&r.val)).ok())
+                .collect(),
         };
 
-        Some(i)
+        Ok(i)
     }
 
-    pub fn listen(&self, buffer: &mut [u8; rosc::decoder::MTU]) -> bool {
-        // Retrieve message from the socket
-        // let msg: usize = self.socket_recv.recv_from(buffer).unwrap_or_else(|_| []);
-        let empty = (0 as usize, self.empty);
-        let msg: (usize, SocketAddr) = self.socket_recv.recv_from(buffer).unwrap_or(empty);
-        if msg.0 > 0 {
-            let (_, packet) = rosc::decoder::decode_udp(&buffer[..msg.0]).unwrap();
-            let is_heartbeat = self.receive_heartbeat(packet);
-            return is_heartbeat;
-        } else {
-            return false;
+    pub fn register(&mut self, registry: &mio::Registry, token: mio::Token) -> std::io::Result<()> {
+        self.transport.register(registry, token)
+    }
+
+    pub fn deregister(&mut self, registry: &mio::Registry) {
+        self.transport.deregister(registry);
+    }
+
+    //
+    // Read every packet waiting on the socket, until it would block, as the reactor
+    // is only woken again by new packets. Returns true if any of them was a heartbeat
+    //
+    pub fn receive(
+        &mut self,
+        buffer: &mut [u8; rosc::decoder::MTU],
+        registry: &mio::Registry,
+    ) -> bool {
+        let mut received_heartbeat = false;
+        for (from, content) in self.transport.receive(buffer, registry) {
+            let mut traffic = self.traffic.lock().unwrap();
+            traffic.record(Direction::Received, from, content.clone());
+            drop(traffic);
+
+            match content {
+                Ok(packet) => {
+                    received_heartbeat = self.receive_heartbeat(packet, from) || received_heartbeat;
+                }
+                Err(e) => println!("Dropped {}", e),
+            }
         }
+        received_heartbeat
     }
 
-    // Handler for incoming OSC messages, parse if it is a valid heartbeat
-    fn receive_heartbeat(&self, packet: OscPacket) -> bool {
+    // Handler for incoming OSC packets, parse if it is a valid heartbeat
+    fn receive_heartbeat(&mut self, packet: OscPacket, from: SocketAddr) -> bool {
         let mut is_heartbeat = false;
         match packet {
             OscPacket::Message(msg) => {
-                if msg.addr == self.channel {
-                    is_heartbeat = true;
-                } else {
-                    self.receive_unexpected(msg);
-                    is_heartbeat = false;
-                };
+                is_heartbeat = self.receive_message(msg, from);
             }
             OscPacket::Bundle(bundle) => {
                 for p in bundle.content {
-                    match p {
-                        OscPacket::Message(msg) => {
-                            if msg.addr == self.channel {
-                                is_heartbeat = true;
-                            } else {
-                                self.receive_unexpected(msg);
-                            }
-                        }
-                        _ => {}
+                    if let OscPacket::Message(msg) = p {
+                        is_heartbeat = self.receive_message(msg, from) || is_heartbeat;
                     }
                 }
             }
@@ -104,19 +231,197 @@ impl Interface {
         is_heartbeat
     }
 
-    pub fn send_heartbeat(&self) {
-        let msg_buf = encoder::encode(&OscPacket::Message(OscMessage {
+    // A heartbeat, possibly echoed and carrying telemetry, or telemetry on a sub-address
+    fn receive_message(&mut self, msg: OscMessage, from: SocketAddr) -> bool {
+        if msg.addr == self.channel {
+            self.receive_echo(&msg);
+            let mut t = self.telemetry.lock().unwrap();
+            telemetry::from_heartbeat(&msg.args, &mut t);
+            return true;
+        }
+
+        let first_string = || match msg.args.first() {
+            Some(OscType::String(s)) => Some(s.clone()),
+            _ => None,
+        };
+        let message = match msg.addr.as_str() {
+            ADDRESS_STATUS => first_string().map(AppMessage::Status),
+            ADDRESS_ALERT => first_string().map(AppMessage::Alert),
+            ADDRESS_REQUEST_RESTART => Some(AppMessage::RequestRestart(first_string())),
+            _ => None,
+        };
+        if let Some(m) = message {
+            self.inbox.lock().unwrap().push(m);
+            return false;
+        }
+
+        let sub_address = msg
+            .addr
+            .strip_prefix(self.channel.as_str())
+            .and_then(|a| a.strip_prefix('/'));
+        match sub_address {
+            Some(key) if !key.is_empty() => {
+                let mut t = self.telemetry.lock().unwrap();
+                telemetry::from_sub_address(key, &msg.args, &mut t);
+            }
+            _ => self.receive_unexpected(msg, from),
+        }
+
+        false
+    }
+
+    // Shared with the app, to show in the Ui
+    pub fn latency(&self) -> Arc<Mutex<Latency>> {
+        Arc::clone(&self.latency)
+    }
+
+    // Shared with the app, for stats and the Ui
+    pub fn telemetry(&self) -> Arc<Mutex<Telemetry>> {
+        Arc::clone(&self.telemetry)
+    }
+
+    // Shared with the app, which takes the messages received
+    pub fn inbox(&self) -> Arc<Mutex<Vec<AppMessage>>> {
+        Arc::clone(&self.inbox)
+    }
+
+    // Shared with the app, for TCP only, to raise an alert when the connection drops
+    pub fn connected(&self) -> Option<Arc<std::sync::atomic::AtomicBool>> {
+        self.transport.connected()
+    }
+
+    // Shared with the app, to show in the traffic inspector
+    pub fn traffic(&self) -> Arc<Mutex<Traffic>> {
+        Arc::clone(&self.traffic)
+    }
+
+    //
+    // Heartbeats carry a sequence number and the time they were sent, in seconds since the
+    // epoch. Apps that echo them back, with the same arguments, have their latency measured
+    //
+    pub fn send_heartbeat(&mut self) {
+        let sent = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let packet = OscPacket::Message(OscMessage {
             addr: self.channel.clone(),
-            args: vec![OscType::Int(1)],
-        }))
-        .unwrap();
+            args: vec![OscType::Int(self.sequence), OscType::Double(sent)],
+        });
+        let msg_buf = encoder::encode(&packet).unwrap();
+
+        // The client may be on another machine, which can be unreachable for a while
+        match self.transport.send(&msg_buf) {
+            Ok(Some(to)) => {
+                let mut traffic = self.traffic.lock().unwrap();
+                traffic.record(Direction::Sent, to, Ok(packet));
+            }
+            // Nothing is due from an app that is not connected over TCP
+            Ok(None) => return,
+            Err(e) => println!("Failed to send heartbeat to {}: {}", self.transport, e),
+        }
+
+        let mut latency = self.latency.lock().unwrap();
+        latency.sent();
 
-        self.socket_send.send_to(&msg_buf, &self.client).unwrap();
+        // Heartbeats that were never echoed back, only lost if the app echoes at all
+        while let Some((_, _, time)) = self.pending.front() {
+            if time.elapsed() < LOSS_TIMEOUT {
+                break;
+            }
+            self.pending.pop_front();
+            if latency.echoing() {
+                latency.lost();
+            }
+        }
+
+        self.pending
+            .push_back((self.sequence, sent, Instant::now()));
+        self.sequence = self.sequence.wrapping_add(1);
     }
 
-    fn receive_unexpected(&self, msg: OscMessage) {
-        println!("Unhandled OSC address: {}", msg.addr);
-        println!("Unhandled OSC arguments: {:?}", msg.args);
+    //
+    // Measure the round trip of a heartbeat echoed back with its sequence number and send
+    // time. Both must match, as apps that do not echo send a plain int in their heartbeats
+    //
+    fn receive_echo(&mut self, msg: &OscMessage) {
+        let (sequence, sent) = match msg.args.as_slice() {
+            [OscType::Int(s), OscType::Double(t), ..] => (*s, *t),
+            _ => return,
+        };
+
+        let echo = self
+            .pending
+            .iter()
+            .position(|(s, t, _)| *s == sequence && *t == sent);
+        if let Some(index) = echo {
+            // Earlier heartbeats still pending were not echoed, and so are lost
+            let mut latency = self.latency.lock().unwrap();
+            for _ in 0..index {
+                latency.lost();
+            }
+            if let Some((_, _, time)) = self.pending.drain(..=index).next_back() {
+                latency.echoed(time.elapsed());
+            }
+        }
+    }
+
+    // Forward a message by the first of the relay rules it matches, if any
+    fn receive_unexpected(&mut self, msg: OscMessage, from: SocketAddr) {
+        let route = self
+            .relay_rules
+            .iter()
+            .find_map(|r| r.route(&msg.addr).map(|address| (r.target, address)));
+
+        match route {
+            Some((target, address)) => self.relay(msg.args, address, target, from),
+            None => {
+                println!("Unhandled OSC address: {}", msg.addr);
+                println!("Unhandled OSC arguments: {:?}", msg.args);
+            }
+        }
+    }
+
+    fn relay(
+        &mut self,
+        args: Vec<OscType>,
+        address: String,
+        target: RelayTarget,
+        from: SocketAddr,
+    ) {
+        // Never send a message back where it came from, two relays could loop forever
+        let to = match target {
+            RelayTarget::App => self.transport.peer(),
+            RelayTarget::Address(a) => Some(a),
+        };
+        if to.is_none() || to == Some(from) {
+            return;
+        }
+
+        let packet = OscPacket::Message(OscMessage {
+            addr: address,
+            args,
+        });
+        let msg_buf = match encoder::encode(&packet) {
+            Ok(b) => b,
+            Err(e) => {
+                println!("Failed to encode relayed message: {:?}", e);
+                return;
+            }
+        };
+
+        let sent = match target {
+            RelayTarget::App => self.transport.send(&msg_buf),
+            RelayTarget::Address(a) => self.transport.send_to(&msg_buf, a).map(|_| Some(a)),
+        };
+        match sent {
+            Ok(Some(to)) => {
+                let mut traffic = self.traffic.lock().unwrap();
+                traffic.record(Direction::Sent, to, Ok(packet));
+            }
+            Ok(None) => (),
+            Err(e) => println!("Failed to relay OSC message to {}: {}", target, e),
+        }
     }
 }
 /*
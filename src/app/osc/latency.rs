@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+// Keep the most recent round trip times only
+const MAX_RTT_SAMPLES: usize = 100;
+
+// Recent samples compared against the rest, to see if the round trip time is rising
+const RECENT_RTT_SAMPLES: usize = 10;
+const MIN_RTT_SAMPLES_FOR_TREND: usize = 30;
+const RISING_RTT_FACTOR: f64 = 1.5;
+
+//
+// Latency
+//
+// Round trip time, jitter and loss of an app's heartbeats, measured from the heartbeats
+// it echoes back. Written by the OSC reactor, read by the Ui
+//
+
+#[derive(Debug, Default)]
+pub struct Latency {
+    rtts: VecDeque<Duration>,
+    // Smoothed mean difference between consecutive round trip times, as in RFC 3550
+    jitter: Duration,
+    sent: u64,
+    echoed: u64,
+    lost: u64,
+}
+
+impl Latency {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sent(&mut self) {
+        self.sent += 1;
+    }
+
+    pub fn echoed(&mut self, rtt: Duration) {
+        self.echoed += 1;
+
+        if let Some(previous) = self.rtts.back() {
+            let d = rtt.abs_diff(*previous);
+            let jitter = self.jitter.as_secs_f64();
+            self.jitter = Duration::from_secs_f64(jitter + (d.as_secs_f64() - jitter) / 16.0);
+        }
+
+        self.rtts.push_back(rtt);
+
+        // Ensure the list is below max length, removing oldest elements
+        while self.rtts.len() > MAX_RTT_SAMPLES {
+            self.rtts.pop_front();
+        }
+    }
+
+    pub fn lost(&mut self) {
+        self.lost += 1;
+    }
+
+    // Only apps that echo heartbeats back can be measured
+    pub fn echoing(&self) -> bool {
+        self.echoed > 0
+    }
+
+    pub fn rtt(&self) -> Option<Duration> {
+        self.rtts.back().copied()
+    }
+
+    pub fn mean_rtt(&self) -> Option<Duration> {
+        mean(self.rtts.iter())
+    }
+
+    pub fn jitter(&self) -> Duration {
+        self.jitter
+    }
+
+    // Percentage of heartbeats that were never echoed back, of those that were due
+    pub fn loss(&self) -> f64 {
+        let due = self.echoed + self.lost;
+        if due == 0 {
+            return 0.0;
+        }
+        self.lost as f64 / due as f64 * 100.0
+    }
+
+    pub fn total_sent(&self) -> u64 {
+        self.sent
+    }
+
+    //
+    // An early warning that the app is struggling to keep up,
+    // when the most recent round trips are well above the earlier ones
+    //
+    pub fn rising(&self) -> bool {
+        if self.rtts.len() < MIN_RTT_SAMPLES_FOR_TREND {
+            return false;
+        }
+
+        let split = self.rtts.len() - RECENT_RTT_SAMPLES;
+        let earlier = mean(self.rtts.iter().take(split));
+        let recent = mean(self.rtts.iter().skip(split));
+        match (earlier, recent) {
+            (Some(e), Some(r)) => r.as_secs_f64() > e.as_secs_f64() * RISING_RTT_FACTOR,
+            _ => false,
+        }
+    }
+}
+
+fn mean<'a>(rtts: impl Iterator<Item = &'a Duration>) -> Option<Duration> {
+    let mut total = Duration::ZERO;
+    let mut count = 0;
+    for rtt in rtts {
+        total += *rtt;
+        count += 1;
+    }
+    (count > 0).then(|| total / count)
+}
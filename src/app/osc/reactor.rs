@@ -0,0 +1,207 @@
+use super::Interface;
+
+use mio::{Events, Poll, Token, Waker};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Token of the waker, used to interrupt the poll when a command is sent
+const WAKER: Token = Token(usize::MAX);
+
+// Sending heartbeats faster than this would only flood the watched app
+const MIN_HEARTBEAT_INTERVAL: Duration = Duration::from_millis(100);
+
+// Longest wait for the reactor to close a socket, in case its thread is stuck
+const DEREGISTER_TIMEOUT: Duration = Duration::from_secs(1);
+
+//
+// Reactor
+//
+// A single thread multiplexing the OSC sockets of every watched app. Received packets
+// are handled as soon as the socket is readable, and heartbeats are sent on timers,
+// so no thread ever sleeps or holds a lock while waiting on the network
+//
+
+pub struct Reactor {
+    commands: mpsc::Sender<Command>,
+    waker: Arc<Waker>,
+    thread: Option<thread::JoinHandle<()>>,
+    next_token: usize,
+}
+
+enum Command {
+    Register(Token, Box<Endpoint>),
+    // Answered once the socket is closed
+    Deregister(Token, mpsc::Sender<()>),
+    Interval(Token, Duration),
+    Shutdown,
+}
+
+// An app's interface, with its heartbeat timer and what to do when a heartbeat is received
+struct Endpoint {
+    interface: Interface,
+    interval: Duration,
+    next_heartbeat: Instant,
+    on_heartbeat: Box<dyn Fn() + Send>,
+}
+
+impl Reactor {
+    pub fn start() -> std::io::Result<Self> {
+        let poll = Poll::new()?;
+        let waker = Arc::new(Waker::new(poll.registry(), WAKER)?);
+        let (commands, receiver) = mpsc::channel();
+
+        let thread = thread::spawn(move || run(poll, receiver));
+
+        Ok(Reactor {
+            commands,
+            waker,
+            thread: Some(thread),
+            next_token: 0,
+        })
+    }
+
+    //
+    // Hand an app's interface over to the reactor, which sends a heartbeat once per interval
+    // and calls on_heartbeat for each heartbeat received. Returns the token to deregister with
+    //
+    pub fn register(
+        &mut self,
+        interface: Interface,
+        interval: Duration,
+        on_heartbeat: Box<dyn Fn() + Send>,
+    ) -> Token {
+        let token = Token(self.next_token);
+        self.next_token += 1;
+
+        let interval = interval.max(MIN_HEARTBEAT_INTERVAL);
+        let endpoint = Endpoint {
+            interface,
+            interval,
+            next_heartbeat: Instant::now(),
+            on_heartbeat,
+        };
+        self.send(Command::Register(token, Box::new(endpoint)));
+
+        token
+    }
+
+    //
+    // Stop sending and receiving for an app, closing its socket. Waits for the socket to be
+    // closed, so its ports can be bound again right away
+    //
+    pub fn deregister(&mut self, token: Token) {
+        let (done, closed) = mpsc::channel();
+        self.send(Command::Deregister(token, done));
+        let _ = closed.recv_timeout(DEREGISTER_TIMEOUT);
+    }
+
+    // Change how often heartbeats are sent to an app, from its next heartbeat on
+    pub fn set_interval(&mut self, token: Token, interval: Duration) {
+        let interval = interval.max(MIN_HEARTBEAT_INTERVAL);
+        self.send(Command::Interval(token, interval));
+    }
+
+    fn send(&self, command: Command) {
+        if self.commands.send(command).is_err() {
+            println!("OSC reactor is not running");
+            return;
+        }
+        if let Err(e) = self.waker.wake() {
+            println!("Failed to wake OSC reactor: {}", e);
+        }
+    }
+}
+
+impl Drop for Reactor {
+    fn drop(&mut self) {
+        self.send(Command::Shutdown);
+        if let Some(t) = self.thread.take() {
+            let _ = t.join();
+        }
+    }
+}
+
+// The reactor thread, runs until shut down
+fn run(mut poll: Poll, commands: mpsc::Receiver<Command>) {
+    let mut events = Events::with_capacity(64);
+    let mut endpoints: HashMap<Token, Endpoint> = HashMap::new();
+    let mut buffer = [0u8; rosc::decoder::MTU];
+
+    println!("OSC reactor started");
+
+    loop {
+        // Sleep until a socket is readable, a command arrives, or the next heartbeat is due
+        let timeout = endpoints
+            .values()
+            .map(|e| e.next_heartbeat.saturating_duration_since(Instant::now()))
+            .min();
+
+        if let Err(e) = poll.poll(&mut events, timeout) {
+            if e.kind() == ErrorKind::Interrupted {
+                continue;
+            }
+            println!("OSC reactor failed to poll: {}", e);
+            return;
+        }
+
+        //
+        // Received packets
+        for event in events.iter() {
+            if let Some(e) = endpoints.get_mut(&event.token()) {
+                if e.interface.receive(&mut buffer, poll.registry()) {
+                    (e.on_heartbeat)();
+                }
+            }
+        }
+
+        //
+        // Commands from the app
+        for command in commands.try_iter() {
+            match command {
+                Command::Register(token, mut e) => {
+                    let registered = e.interface.register(poll.registry(), token);
+                    match registered {
+                        Ok(_) => {
+                            // Packets that arrived before registering do not raise an event
+                            if e.interface.receive(&mut buffer, poll.registry()) {
+                                (e.on_heartbeat)();
+                            }
+                            endpoints.insert(token, *e);
+                        }
+                        Err(err) => println!("OSC reactor failed to register socket: {}", err),
+                    }
+                }
+                Command::Deregister(token, done) => {
+                    if let Some(mut e) = endpoints.remove(&token) {
+                        e.interface.deregister(poll.registry());
+                    }
+                    let _ = done.send(());
+                }
+                Command::Interval(token, interval) => {
+                    if let Some(e) = endpoints.get_mut(&token) {
+                        e.next_heartbeat = e.next_heartbeat.min(Instant::now() + interval);
+                        e.interval = interval;
+                    }
+                }
+                Command::Shutdown => {
+                    println!("OSC reactor stopped");
+                    return;
+                }
+            }
+        }
+
+        //
+        // Heartbeat timers
+        let now = Instant::now();
+        for e in endpoints.values_mut() {
+            if e.next_heartbeat <= now {
+                e.interface.send_heartbeat();
+                e.next_heartbeat = now + e.interval;
+            }
+        }
+    }
+}
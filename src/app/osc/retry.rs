@@ -0,0 +1,44 @@
+use std::time::{Duration, Instant};
+
+// Wait after the first failure, doubled on each failure after it, up to the longest
+const FIRST_WAIT: Duration = Duration::from_secs(1);
+const LONGEST_WAIT: Duration = Duration::from_secs(30);
+
+//
+// Retry
+//
+// Backoff for a socket that could not be bound, as a port is often in use for a moment
+// only, like while a previous instance of Watchdog is still closing
+//
+
+pub struct Retry {
+    // When the next attempt is due, and how long was waited for it
+    next: Option<(Instant, Duration)>,
+}
+
+impl Retry {
+    pub fn new() -> Self {
+        Retry { next: None }
+    }
+
+    // Whether an attempt is due, right away when nothing has failed
+    pub fn due(&self) -> bool {
+        !matches!(self.next, Some((at, _)) if Instant::now() < at)
+    }
+
+    // Wait longer before the next attempt. Returns the wait, and whether it is the first failure
+    pub fn failed(&mut self) -> (Duration, bool) {
+        let first = self.next.is_none();
+        let wait = match self.next {
+            Some((_, wait)) => (wait * 2).min(LONGEST_WAIT),
+            None => FIRST_WAIT,
+        };
+        self.next = Some((Instant::now() + wait, wait));
+        (wait, first)
+    }
+
+    // Start over, after a success or when the address changes
+    pub fn reset(&mut self) {
+        self.next = None;
+    }
+}
@@ -0,0 +1,177 @@
+// Special bytes of SLIP framing, RFC 1055, as used by OSC 1.1 over stream transports
+const END: u8 = 0xC0;
+const ESC: u8 = 0xDB;
+const ESC_END: u8 = 0xDC;
+const ESC_ESC: u8 = 0xDD;
+
+// Packets longer than this are dropped, a peer not speaking SLIP would grow the buffer forever
+pub const MAX_PACKET_SIZE: usize = 64 * 1024;
+
+//
+// SLIP
+//
+// Frames OSC packets on a TCP stream. Each packet is escaped and ends with an END byte,
+// and OSC 1.1 also starts each packet with one, which decoding skips as an empty packet
+//
+
+pub fn encode(packet: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(packet.len() + 2);
+    out.push(END);
+    for b in packet {
+        match *b {
+            END => out.extend_from_slice(&[ESC, ESC_END]),
+            ESC => out.extend_from_slice(&[ESC, ESC_ESC]),
+            b => out.push(b),
+        }
+    }
+    out.push(END);
+    out
+}
+
+// Reassembles packets from the bytes of a stream, as they are read
+#[derive(Debug, Default)]
+pub struct Decoder {
+    packet: Vec<u8>,
+    escaped: bool,
+    // Skip the rest of a packet that was too long, until its END
+    overflowed: bool,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The packets completed by these bytes, the start of the next one is kept
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Vec<u8>> {
+        let mut packets = Vec::new();
+        for b in bytes {
+            if *b == END {
+                if !self.packet.is_empty() && !self.overflowed {
+                    packets.push(std::mem::take(&mut self.packet));
+                }
+                self.packet.clear();
+                self.escaped = false;
+                self.overflowed = false;
+                continue;
+            }
+
+            let b = match (self.escaped, *b) {
+                (false, ESC) => {
+                    self.escaped = true;
+                    continue;
+                }
+                (true, ESC_END) => END,
+                (true, ESC_ESC) => ESC,
+                // Invalid escape, keep the byte as is
+                (_, b) => b,
+            };
+            self.escaped = false;
+
+            if self.packet.len() >= MAX_PACKET_SIZE {
+                self.overflowed = true;
+                self.packet.clear();
+            }
+            if !self.overflowed {
+                self.packet.push(b);
+            }
+        }
+        packets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_frames_and_escapes() {
+        assert_eq!(encode(b"ab"), vec![END, b'a', b'b', END]);
+        assert_eq!(
+            encode(&[1, END, 2, ESC, 3]),
+            vec![END, 1, ESC, ESC_END, 2, ESC, ESC_ESC, 3, END]
+        );
+        assert_eq!(encode(&[]), vec![END, END]);
+    }
+
+    #[test]
+    fn decodes_what_was_encoded() {
+        let packet = vec![0, END, ESC, ESC_END, ESC_ESC, 255];
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.push(&encode(&packet)), vec![packet]);
+    }
+
+    #[test]
+    fn decodes_several_packets_in_one_read() {
+        let mut bytes = encode(b"one");
+        bytes.extend(encode(b"two"));
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.push(&bytes), vec![b"one".to_vec(), b"two".to_vec()]);
+    }
+
+    #[test]
+    fn decodes_packets_split_across_reads() {
+        let packet = vec![1, END, 2, ESC, 3];
+        let bytes = encode(&packet);
+        let mut decoder = Decoder::new();
+        let mut packets = Vec::new();
+        // One byte at a time splits every escape between two reads
+        for b in bytes.iter() {
+            packets.extend(decoder.push(&[*b]));
+        }
+        assert_eq!(packets, vec![packet]);
+    }
+
+    #[test]
+    fn keeps_an_unfinished_packet_for_the_next_read() {
+        let mut decoder = Decoder::new();
+        assert!(decoder.push(&[END, b'a', ESC]).is_empty());
+        assert_eq!(
+            decoder.push(&[ESC_END, b'b', END]),
+            vec![vec![b'a', END, b'b']]
+        );
+    }
+
+    #[test]
+    fn skips_empty_packets() {
+        let mut decoder = Decoder::new();
+        assert_eq!(
+            decoder.push(&[END, END, END, b'x', END, END]),
+            vec![vec![b'x']]
+        );
+    }
+
+    #[test]
+    fn keeps_invalid_escapes_as_is() {
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.push(&[ESC, b'x', END]), vec![vec![b'x']]);
+    }
+
+    #[test]
+    fn drops_packets_over_the_maximum_size() {
+        let mut decoder = Decoder::new();
+        let mut bytes = vec![END];
+        bytes.extend(vec![7u8; MAX_PACKET_SIZE + 10]);
+        bytes.push(END);
+        bytes.extend(encode(b"next"));
+        assert_eq!(decoder.push(&bytes), vec![b"next".to_vec()]);
+    }
+
+    #[test]
+    fn drops_oversized_packets_across_reads() {
+        let mut decoder = Decoder::new();
+        let chunk = vec![7u8; 4096];
+        for _ in 0..(MAX_PACKET_SIZE / chunk.len() + 2) {
+            assert!(decoder.push(&chunk).is_empty());
+        }
+        assert!(decoder.push(&[END]).is_empty());
+        assert_eq!(decoder.push(&encode(b"next")), vec![b"next".to_vec()]);
+    }
+
+    #[test]
+    fn keeps_packets_of_exactly_the_maximum_size() {
+        let packet = vec![7u8; MAX_PACKET_SIZE];
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.push(&encode(&packet)), vec![packet]);
+    }
+}
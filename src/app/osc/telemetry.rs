@@ -0,0 +1,75 @@
+use crate::utils::now;
+use rosc::OscType;
+use std::collections::HashMap;
+use std::time::Duration;
+
+//
+// Telemetry
+//
+// Key/value pairs an app reports along with its heartbeats, either as arguments of the
+// heartbeat itself: /heart "fps" 59.9 "scene" "intro", or one key per sub-address of the
+// heartbeat channel: /heart/fps 59.9. Numbers can be used in alert rules, text is only shown
+//
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TelemetryValue {
+    Number(f64),
+    Text(String),
+}
+
+impl TelemetryValue {
+    fn from_osc(arg: &OscType) -> Option<Self> {
+        match arg {
+            OscType::Int(i) => Some(TelemetryValue::Number(*i as f64)),
+            OscType::Long(l) => Some(TelemetryValue::Number(*l as f64)),
+            OscType::Float(f) => Some(TelemetryValue::Number(*f as f64)),
+            OscType::Double(d) => Some(TelemetryValue::Number(*d)),
+            OscType::Bool(b) => Some(TelemetryValue::Number(if *b { 1.0 } else { 0.0 })),
+            OscType::String(s) => Some(TelemetryValue::Text(s.clone())),
+            _ => None,
+        }
+    }
+
+    pub fn number(&self) -> Option<f64> {
+        match self {
+            TelemetryValue::Number(n) => Some(*n),
+            TelemetryValue::Text(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for TelemetryValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TelemetryValue::Number(n) => write!(f, "{}", (n * 100.0).round() / 100.0),
+            TelemetryValue::Text(t) => write!(f, "{}", t),
+        }
+    }
+}
+
+// Latest value of each key an app has reported, with the time it was reported at
+pub type Telemetry = HashMap<String, (TelemetryValue, Duration)>;
+
+//
+// Pairs of a string key followed by its value, anywhere in the arguments of a heartbeat,
+// so that the sequence number and time of an echoed heartbeat may come first
+//
+pub fn from_heartbeat(args: &[OscType], telemetry: &mut Telemetry) {
+    let mut i = 0;
+    while i + 1 < args.len() {
+        match (&args[i], TelemetryValue::from_osc(&args[i + 1])) {
+            (OscType::String(key), Some(value)) => {
+                telemetry.insert(key.clone(), (value, now()));
+                i += 2;
+            }
+            _ => i += 1,
+        }
+    }
+}
+
+// A single key on a sub-address of the heartbeat channel, the value is the first argument
+pub fn from_sub_address(key: &str, args: &[OscType], telemetry: &mut Telemetry) {
+    if let Some(value) = args.first().and_then(TelemetryValue::from_osc) {
+        telemetry.insert(key.to_string(), (value, now()));
+    }
+}
@@ -0,0 +1,132 @@
+use chrono::{DateTime, Local};
+use rosc::{OscPacket, OscType};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+
+// Keep the most recent packets of each app only
+const MAX_PACKETS: usize = 500;
+
+//
+// Traffic
+//
+// Every OSC packet sent to and received from an app, as decoded, for the traffic
+// inspector in the Ui. Written by the OSC reactor
+//
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+#[derive(Debug, Clone)]
+pub struct Packet {
+    pub time: DateTime<Local>,
+    pub direction: Direction,
+    // Where a received packet came from, or where a sent packet went to
+    pub peer: SocketAddr,
+    // The decoded packet, or why it could not be decoded
+    pub content: Result<OscPacket, String>,
+}
+
+impl Packet {
+    // Number of lines the packet is shown on, without formatting them
+    pub fn line_count(&self) -> usize {
+        match &self.content {
+            Ok(p) => packet_line_count(p),
+            Err(_) => 1,
+        }
+    }
+
+    //
+    // The packet as lines of text, a message per line as its address and arguments,
+    // and the messages of a bundle indented below the bundle's time tag
+    //
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        match &self.content {
+            Ok(p) => packet_lines(p, 0, &mut lines),
+            Err(e) => lines.push(e.clone()),
+        }
+        lines
+    }
+}
+
+fn packet_line_count(packet: &OscPacket) -> usize {
+    match packet {
+        OscPacket::Message(_) => 1,
+        OscPacket::Bundle(bundle) => {
+            1 + bundle.content.iter().map(packet_line_count).sum::<usize>()
+        }
+    }
+}
+
+fn packet_lines(packet: &OscPacket, depth: usize, lines: &mut Vec<String>) {
+    let indent = "    ".repeat(depth);
+    match packet {
+        OscPacket::Message(msg) => {
+            let args: Vec<String> = msg.args.iter().map(format_arg).collect();
+            lines.push(format!("{}{} {}", indent, msg.addr, args.join(" ")));
+        }
+        OscPacket::Bundle(bundle) => {
+            lines.push(format!(
+                "{}#bundle {}.{}",
+                indent, bundle.timetag.seconds, bundle.timetag.fractional
+            ));
+            for p in &bundle.content {
+                packet_lines(p, depth + 1, lines);
+            }
+        }
+    }
+}
+
+// An argument with its OSC type tag, like i:5 or s:"intro"
+fn format_arg(arg: &OscType) -> String {
+    match arg {
+        OscType::Int(i) => format!("i:{}", i),
+        OscType::Long(l) => format!("h:{}", l),
+        OscType::Float(f) => format!("f:{}", f),
+        OscType::Double(d) => format!("d:{}", d),
+        OscType::String(s) => format!("s:\"{}\"", s),
+        OscType::Blob(b) => format!("b:[{} bytes]", b.len()),
+        OscType::Bool(b) => (if *b { "T" } else { "F" }).to_string(),
+        OscType::Nil => "N".to_string(),
+        OscType::Inf => "I".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Traffic {
+    packets: VecDeque<Packet>,
+}
+
+impl Traffic {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(
+        &mut self,
+        direction: Direction,
+        peer: SocketAddr,
+        content: Result<OscPacket, String>,
+    ) {
+        self.packets.push_back(Packet {
+            time: Local::now(),
+            direction,
+            peer,
+            content,
+        });
+
+        // Ensure the list is below max length, removing oldest elements
+        while self.packets.len() > MAX_PACKETS {
+            self.packets.pop_front();
+        }
+    }
+
+    // Oldest first
+    pub fn packets(&self) -> &VecDeque<Packet> {
+        &self.packets
+    }
+}
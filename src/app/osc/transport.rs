@@ -0,0 +1,338 @@
+use super::slip;
+
+use mio::net::{TcpListener, TcpStream, UdpSocket};
+use mio::{Interest, Registry, Token};
+use rosc::OscPacket;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// Framed bytes waiting to be written to an app that stopped reading, past this it is dropped
+const MAX_OUTGOING: usize = slip::MAX_PACKET_SIZE;
+
+// A packet received, with the address it came from, or why it could not be decoded
+pub type Received = (SocketAddr, Result<OscPacket, String>);
+
+//
+// Transport
+//
+// How OSC packets travel between Watchdog and an app. UDP sends datagrams to the app's
+// OSC port in. TCP listens on Watchdog's port instead, for the app to connect to, and
+// frames packets with SLIP as in OSC 1.1. Either way the sockets are non-blocking,
+// polled by the OSC reactor
+//
+
+pub enum Transport {
+    Udp {
+        socket: UdpSocket,
+        client: SocketAddr,
+    },
+    Tcp(Box<Tcp>),
+}
+
+pub struct Tcp {
+    listener: TcpListener,
+    // Only connections from this address are accepted, any when unspecified
+    client: IpAddr,
+    connection: Option<Connection>,
+    // Registration of the listener, shared by the accepted connection
+    token: Token,
+    // Shared with the app, which raises an alert when the connection drops
+    connected: Arc<AtomicBool>,
+    // Sends relayed messages to other targets, opened on the first one
+    relay: Option<std::net::UdpSocket>,
+}
+
+// The app's connection, one at a time, a new connection replaces the previous one
+struct Connection {
+    stream: TcpStream,
+    peer: SocketAddr,
+    decoder: slip::Decoder,
+    // Framed bytes not yet written, while the stream would block
+    outgoing: Vec<u8>,
+}
+
+impl std::fmt::Debug for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl std::fmt::Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transport::Udp { client, .. } => write!(f, "udp {}", client),
+            Transport::Tcp(t) => match &t.connection {
+                Some(c) => write!(f, "tcp {}", c.peer),
+                None => write!(f, "tcp, not connected"),
+            },
+        }
+    }
+}
+
+impl Transport {
+    pub fn udp(socket: std::net::UdpSocket, client: SocketAddr) -> Self {
+        Transport::Udp {
+            socket: UdpSocket::from_std(socket),
+            client,
+        }
+    }
+
+    pub fn tcp(listener: std::net::TcpListener, client: IpAddr) -> Self {
+        Transport::Tcp(Box::new(Tcp {
+            listener: TcpListener::from_std(listener),
+            client,
+            connection: None,
+            token: Token(0),
+            connected: Arc::new(AtomicBool::new(false)),
+            relay: None,
+        }))
+    }
+
+    // Whether the app is connected, for TCP only
+    pub fn connected(&self) -> Option<Arc<AtomicBool>> {
+        match self {
+            Transport::Udp { .. } => None,
+            Transport::Tcp(t) => Some(Arc::clone(&t.connected)),
+        }
+    }
+
+    // Where packets to the app are sent, None when the app is not connected over TCP
+    pub fn peer(&self) -> Option<SocketAddr> {
+        match self {
+            Transport::Udp { client, .. } => Some(*client),
+            Transport::Tcp(t) => t.connection.as_ref().map(|c| c.peer),
+        }
+    }
+
+    pub fn register(&mut self, registry: &Registry, token: Token) -> std::io::Result<()> {
+        match self {
+            Transport::Udp { socket, .. } => registry.register(socket, token, Interest::READABLE),
+            Transport::Tcp(t) => {
+                t.token = token;
+                registry.register(&mut t.listener, token, Interest::READABLE)
+            }
+        }
+    }
+
+    pub fn deregister(&mut self, registry: &Registry) {
+        match self {
+            Transport::Udp { socket, .. } => {
+                let _ = registry.deregister(socket);
+            }
+            Transport::Tcp(t) => {
+                let _ = registry.deregister(&mut t.listener);
+                t.disconnect(Some(registry));
+            }
+        }
+    }
+
+    //
+    // Every packet waiting on the socket, until it would block, as the reactor
+    // is only woken again by new data. For TCP, accepts the app's connection first
+    //
+    pub fn receive(
+        &mut self,
+        buffer: &mut [u8; rosc::decoder::MTU],
+        registry: &Registry,
+    ) -> Vec<Received> {
+        let mut received = Vec::new();
+        match self {
+            Transport::Udp { socket, .. } => loop {
+                match socket.recv_from(buffer) {
+                    Ok((size, from)) => received.push((from, decode(&buffer[..size]))),
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    // Windows reports an unreachable client on the next receive, keep reading
+                    Err(e) if e.kind() == ErrorKind::ConnectionReset => continue,
+                    Err(e) => {
+                        println!("OSC failed to receive: {}", e);
+                        break;
+                    }
+                }
+            },
+            Transport::Tcp(t) => {
+                t.accept(registry);
+                t.read(buffer, &mut received, registry);
+                t.flush(Some(registry));
+            }
+        }
+        received
+    }
+
+    //
+    // Send a packet to the app. Returns where it was sent to, or None when the app is not
+    // connected over TCP, or was disconnected for not reading what was sent before
+    //
+    pub fn send(&mut self, packet: &[u8]) -> std::io::Result<Option<SocketAddr>> {
+        match self {
+            Transport::Udp { socket, client } => {
+                socket.send_to(packet, *client)?;
+                Ok(Some(*client))
+            }
+            Transport::Tcp(t) => {
+                let peer = match &mut t.connection {
+                    Some(c) => {
+                        c.outgoing.extend_from_slice(&slip::encode(packet));
+                        if c.outgoing.len() > MAX_OUTGOING {
+                            println!(
+                                "OSC connection from {} is not reading, {} bytes are waiting",
+                                c.peer,
+                                c.outgoing.len()
+                            );
+                            t.disconnect(None);
+                            return Ok(None);
+                        }
+                        c.peer
+                    }
+                    None => return Ok(None),
+                };
+                t.flush(None);
+                Ok(Some(peer))
+            }
+        }
+    }
+
+    //
+    // Send a packet somewhere other than the app, as a UDP datagram. Over UDP it is sent from
+    // the app's socket, so replies arrive on the same port as the app's own messages
+    //
+    pub fn send_to(&mut self, packet: &[u8], to: SocketAddr) -> std::io::Result<()> {
+        match self {
+            Transport::Udp { socket, .. } => socket.send_to(packet, to).map(|_| ()),
+            Transport::Tcp(t) => {
+                if t.relay.is_none() {
+                    let bind = match to {
+                        SocketAddr::V4(_) => SocketAddr::from(([0, 0, 0, 0], 0)),
+                        SocketAddr::V6(_) => SocketAddr::from(([0u16; 8], 0)),
+                    };
+                    let socket = std::net::UdpSocket::bind(bind)?;
+                    socket.set_nonblocking(true)?;
+                    t.relay = Some(socket);
+                }
+                match &t.relay {
+                    Some(socket) => socket.send_to(packet, to).map(|_| ()),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl Tcp {
+    fn accept(&mut self, registry: &Registry) {
+        loop {
+            let (mut stream, peer) = match self.listener.accept() {
+                Ok(s) => s,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+                Err(e) => {
+                    println!("OSC failed to accept connection: {}", e);
+                    return;
+                }
+            };
+
+            if !self.client.is_unspecified() && peer.ip() != self.client {
+                println!(
+                    "OSC refused connection from {}, expected {}",
+                    peer, self.client
+                );
+                continue;
+            }
+
+            let interest = Interest::READABLE | Interest::WRITABLE;
+            if let Err(e) = registry.register(&mut stream, self.token, interest) {
+                println!("OSC failed to register connection from {}: {}", peer, e);
+                continue;
+            }
+
+            if self.connection.is_some() {
+                println!("OSC connection from {} replaces the previous one", peer);
+                self.disconnect(Some(registry));
+            } else {
+                println!("OSC connection from {}", peer);
+            }
+            self.connection = Some(Connection {
+                stream,
+                peer,
+                decoder: slip::Decoder::new(),
+                outgoing: Vec::new(),
+            });
+            self.connected.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn read(
+        &mut self,
+        buffer: &mut [u8; rosc::decoder::MTU],
+        received: &mut Vec<Received>,
+        registry: &Registry,
+    ) {
+        let c = match &mut self.connection {
+            Some(c) => c,
+            None => return,
+        };
+
+        loop {
+            match c.stream.read(buffer) {
+                // Closed by the app
+                Ok(0) => break,
+                Ok(size) => {
+                    for packet in c.decoder.push(&buffer[..size]) {
+                        received.push((c.peer, decode(&packet)));
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    println!("OSC connection from {} failed: {}", c.peer, e);
+                    break;
+                }
+            }
+        }
+
+        self.disconnect(Some(registry));
+    }
+
+    // Write as much of the outgoing bytes as the stream takes without blocking
+    fn flush(&mut self, registry: Option<&Registry>) {
+        let c = match &mut self.connection {
+            Some(c) => c,
+            None => return,
+        };
+
+        while !c.outgoing.is_empty() {
+            match c.stream.write(&c.outgoing) {
+                Ok(0) => break,
+                Ok(size) => {
+                    c.outgoing.drain(..size);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    println!("OSC connection to {} failed: {}", c.peer, e);
+                    break;
+                }
+            }
+        }
+
+        if !c.outgoing.is_empty() {
+            self.disconnect(registry);
+        }
+    }
+
+    fn disconnect(&mut self, registry: Option<&Registry>) {
+        if let Some(mut c) = self.connection.take() {
+            println!("OSC connection from {} closed", c.peer);
+            if let Some(r) = registry {
+                let _ = r.deregister(&mut c.stream);
+            }
+        }
+        self.connected.store(false, Ordering::Relaxed);
+    }
+}
+
+fn decode(bytes: &[u8]) -> Result<OscPacket, String> {
+    rosc::decoder::decode_udp(bytes)
+        .map(|(_, packet)| packet)
+        .map_err(|e| format!("invalid OSC packet of {} bytes: {:?}", bytes.len(), e))
+}
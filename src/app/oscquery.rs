@@ -0,0 +1,847 @@
+use super::apps;
+use super::config;
+use super::osc;
+
+use rosc::{encoder, OscMessage, OscPacket, OscType};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod websocket;
+
+use websocket::Frame;
+
+// How often the namespace is rebuilt from the watched apps, and changes streamed to listeners
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(100);
+
+// How often the listening thread checks for new connections, or for being stopped
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+
+// Give up on clients that do not send a whole request within this long
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+// Each connection holds a thread, or two for a WebSocket, so the server refuses more than this
+const MAX_CONNECTIONS: usize = 32;
+
+// WebSocket clients are pinged this often, and dropped when nothing arrives for the timeout
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+const SESSION_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Access of a node, as defined by OSCQuery
+const ACCESS_READ: u8 = 1;
+const ACCESS_WRITE: u8 = 2;
+
+//
+// OSCQuery
+//
+// Serves the state and controls of Watchdog as an OSCQuery namespace over HTTP, so show
+// control tools can discover every watched app without anyone typing in addresses:
+//
+// GET /                        the whole namespace as JSON
+// GET /watchdog/apps/<name>    a single node and its children
+// GET /<path>?VALUE            a single attribute of a node
+// GET /?HOST_INFO              the server, and where to send OSC to
+//
+// Clients can upgrade the connection to a WebSocket, and LISTEN to paths to have their
+// value changes streamed as binary OSC messages. Controls are written by sending OSC to
+// the remote control port, which is given in the host info while remote control is enabled
+//
+// Values are read from a snapshot of the namespace, published from the event loop, so the
+// server threads never touch the apps themselves
+//
+
+// A node with a value or a control, containers are implied by the paths of their children
+#[derive(Debug, Clone)]
+struct Node {
+    type_tag: &'static str,
+    access: u8,
+    value: Option<OscType>,
+    description: &'static str,
+}
+
+// Every node with a value or a control, by its full path
+type Namespace = BTreeMap<String, Node>;
+
+// Shared between the event loop and the server threads
+struct Shared {
+    namespace: Mutex<Namespace>,
+    host_info: Mutex<Value>,
+    // Cleared to stop the listening thread and every connection
+    running: AtomicBool,
+    // Connections being handled, up to the maximum
+    connections: AtomicUsize,
+}
+
+// An open connection, counted until it is dropped
+struct Connection(Arc<Shared>);
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub struct OscQuery {
+    shared: Option<Arc<Shared>>,
+    thread: Option<thread::JoinHandle<()>>,
+    // Address the server is bound to, to rebind when the config changes
+    bound: Option<SocketAddr>,
+    last_publish: Option<Instant>,
+}
+
+impl OscQuery {
+    pub fn new() -> Self {
+        OscQuery {
+            shared: None,
+            thread: None,
+            bound: None,
+            last_publish: None,
+        }
+    }
+
+    //
+    // Start, restart or stop the server to match the config, called every tick
+    // of the event loop. Returns the error if binding failed on this tick
+    //
+    pub fn update(&mut self, c: &config::OscQuery) -> Option<osc::BindError> {
+        let wanted = if c.enabled && c.valid {
+            let ip = String::from(&c.bind_address.val).parse::<IpAddr>().ok();
+            ip.map(|ip| SocketAddr::new(ip, u16::from(&c.port.val)))
+        } else {
+            None
+        };
+
+        if wanted == self.bound {
+            return None;
+        }
+        self.bound = wanted;
+        self.stop();
+
+        let address = wanted?;
+        let bound = TcpListener::bind(address).and_then(|l| {
+            l.set_nonblocking(true)?;
+            Ok(l)
+        });
+        match bound {
+            Ok(listener) => {
+                println!("OSCQuery server listening on {}", address);
+                let shared = Arc::new(Shared {
+                    namespace: Mutex::new(Namespace::new()),
+                    host_info: Mutex::new(Value::Null),
+                    running: AtomicBool::new(true),
+                    connections: AtomicUsize::new(0),
+                });
+                let s = shared.clone();
+                self.thread = Some(thread::spawn(move || listen(listener, s)));
+                self.shared = Some(shared);
+                self.last_publish = None;
+                None
+            }
+            Err(e) => {
+                let e = osc::BindError::from_io(address, e);
+                println!("OSCQuery server failed to bind: {}", e);
+                Some(e)
+            }
+        }
+    }
+
+    //
+    // Snapshot the state of Watchdog and the watched apps for the server threads,
+    // at most once per publish interval
+    //
+    pub fn publish(&mut self, apps: &apps::Apps, remote: &config::RemoteControl) {
+        let shared = match &self.shared {
+            Some(s) => s,
+            None => return,
+        };
+        if let Some(last) = self.last_publish {
+            if last.elapsed() < PUBLISH_INTERVAL {
+                return;
+            }
+        }
+        self.last_publish = Some(Instant::now());
+
+        *shared.namespace.lock().unwrap() = namespace(apps);
+        *shared.host_info.lock().unwrap() = host_info(remote);
+    }
+
+    fn stop(&mut self) {
+        if let Some(s) = self.shared.take() {
+            s.running.store(false, Ordering::Relaxed);
+        }
+        if let Some(t) = self.thread.take() {
+            let _ = t.join();
+        }
+    }
+}
+
+impl Drop for OscQuery {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+pub fn init() -> OscQuery {
+    OscQuery::new()
+}
+
+//
+// Namespace
+//
+
+fn namespace(apps: &apps::Apps) -> Namespace {
+    let mut n = Namespace::new();
+
+    let int = |i: i32| Some(OscType::Int(i));
+    let string = |s: &str| Some(OscType::String(s.to_string()));
+
+    n.insert(
+        "/watchdog/watching".to_string(),
+        read(
+            "i",
+            int(apps.watching() as i32),
+            "1 while the apps are watched",
+        ),
+    );
+    n.insert(
+        "/watchdog/paused".to_string(),
+        read(
+            "i",
+            int(apps.paused() as i32),
+            "1 while supervision is paused",
+        ),
+    );
+    n.insert(
+        "/watchdog/start".to_string(),
+        write("Start watching every app, or launch the stopped apps"),
+    );
+    n.insert(
+        "/watchdog/stop".to_string(),
+        write("Stop watching every app"),
+    );
+    n.insert("/watchdog/restart".to_string(), write("Restart every app"));
+    n.insert(
+        "/watchdog/pause".to_string(),
+        write("Keep the apps running, but stop checking and restarting them"),
+    );
+    n.insert("/watchdog/resume".to_string(), write("Resume supervision"));
+    n.insert(
+        "/watchdog/reload".to_string(),
+        write("Read the config file again"),
+    );
+
+    for a in apps.iter() {
+        let base = format!("/watchdog/apps/{}", osc::address_segment(a.name()));
+        let mut insert = |key: &str, node: Node| {
+            n.insert(format!("{}/{}", base, key), node);
+        };
+
+        insert("name", read("s", string(a.name()), "Name of the app"));
+        insert("group", read("s", string(&a.group()), "Group of the app"));
+        insert(
+            "state",
+            read(
+                "s",
+                string(a.state().name()),
+                "stopped, starting, running or restarting",
+            ),
+        );
+        insert(
+            "pid",
+            read(
+                "i",
+                int(a.pid().unwrap_or(0) as i32),
+                "Process id, 0 when not running",
+            ),
+        );
+        insert(
+            "restarts",
+            read("i", int(a.restarts() as i32), "Times the app was restarted"),
+        );
+        insert(
+            "status",
+            read(
+                "s",
+                string(a.reported_status().unwrap_or("")),
+                "Latest status the app reported about itself",
+            ),
+        );
+
+        // Only apps that echo their heartbeats back can be measured
+        let rtt = a
+            .latency()
+            .and_then(|l| l.rtt())
+            .map(|rtt| OscType::Float(rtt.as_secs_f32() * 1000.0));
+        insert(
+            "rtt",
+            read(
+                "f",
+                rtt,
+                "Round trip time of the latest heartbeat, in milliseconds",
+            ),
+        );
+
+        for (key, (value, _)) in a.telemetry() {
+            let node = match value {
+                osc::TelemetryValue::Number(v) => read(
+                    "f",
+                    Some(OscType::Float(v as f32)),
+                    "Telemetry reported by the app",
+                ),
+                osc::TelemetryValue::Text(t) => {
+                    read("s", string(&t), "Telemetry reported by the app")
+                }
+            };
+            insert(&format!("telemetry/{}", osc::address_segment(&key)), node);
+        }
+
+        insert("start", write("Launch the app, if it was stopped"));
+        insert("stop", write("Stop the app, until started again"));
+        insert("restart", write("Restart the app"));
+    }
+
+    n
+}
+
+fn read(type_tag: &'static str, value: Option<OscType>, description: &'static str) -> Node {
+    Node {
+        type_tag,
+        access: ACCESS_READ,
+        value,
+        description,
+    }
+}
+
+// Controls take no arguments, they are triggered by any message sent to them
+fn write(description: &'static str) -> Node {
+    Node {
+        type_tag: "N",
+        access: ACCESS_WRITE,
+        value: None,
+        description,
+    }
+}
+
+fn host_info(remote: &config::RemoteControl) -> Value {
+    let mut info = json!({
+        "NAME": "Watchdog",
+        "EXTENSIONS": {
+            "ACCESS": true,
+            "VALUE": true,
+            "DESCRIPTION": true,
+            "LISTEN": true,
+            "PATH_ADDED": true,
+            "PATH_REMOVED": true,
+        },
+    });
+
+    // Controls are sent to the remote control port, over UDP
+    if remote.enabled && remote.valid {
+        info["OSC_PORT"] = json!(u16::from(&remote.port.val));
+        info["OSC_TRANSPORT"] = json!("UDP");
+
+        // Clients send to the host they reached the server on, unless bound to a single address
+        let address = String::from(&remote.bind_address.val);
+        if let Ok(ip) = address.parse::<IpAddr>() {
+            if !ip.is_unspecified() {
+                info["OSC_IP"] = json!(ip.to_string());
+            }
+        }
+    }
+
+    info
+}
+
+fn value_json(value: &OscType) -> Value {
+    match value {
+        OscType::Int(i) => json!(i),
+        OscType::Float(f) => json!(f),
+        OscType::String(s) => json!(s),
+        _ => Value::Null,
+    }
+}
+
+//
+// The JSON of the node at a path, with every node below it as its contents.
+// None if there is no node at or below the path
+//
+fn node_json(namespace: &Namespace, path: &str) -> Option<Value> {
+    let mut node = Map::new();
+    node.insert("FULL_PATH".to_string(), json!(path));
+
+    if let Some(n) = namespace.get(path) {
+        node.insert("TYPE".to_string(), json!(n.type_tag));
+        node.insert("ACCESS".to_string(), json!(n.access));
+        node.insert("DESCRIPTION".to_string(), json!(n.description));
+        if let Some(v) = &n.value {
+            node.insert("VALUE".to_string(), json!([value_json(v)]));
+        }
+        return Some(Value::Object(node));
+    }
+
+    // Containers hold the next segment of the paths below them
+    let prefix = if path == "/" {
+        "/".to_string()
+    } else {
+        format!("{}/", path)
+    };
+    let children: Vec<&str> = namespace
+        .range(prefix.clone()..)
+        .take_while(|(p, _)| p.starts_with(&prefix))
+        .filter_map(|(p, _)| p[prefix.len()..].split('/').next())
+        .collect();
+    if children.is_empty() {
+        return None;
+    }
+
+    let mut contents = Map::new();
+    for child in children {
+        if contents.contains_key(child) {
+            continue;
+        }
+        if let Some(c) = node_json(namespace, &format!("{}{}", prefix, child)) {
+            contents.insert(child.to_string(), c);
+        }
+    }
+    node.insert("ACCESS".to_string(), json!(0));
+    node.insert("CONTENTS".to_string(), Value::Object(contents));
+
+    Some(Value::Object(node))
+}
+
+//
+// Server threads
+//
+
+//
+// Accepts connections until stopped, handling each one on its own thread. Connections past
+// the maximum are closed right away, so clients on the network cannot use up every thread
+//
+fn listen(listener: TcpListener, shared: Arc<Shared>) {
+    while shared.running.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, from)) => {
+                if shared.connections.fetch_add(1, Ordering::Relaxed) >= MAX_CONNECTIONS {
+                    shared.connections.fetch_sub(1, Ordering::Relaxed);
+                    println!("OSCQuery server refused {}, too many connections", from);
+                    continue;
+                }
+                let connection = Connection(shared.clone());
+                thread::spawn(move || {
+                    if let Err(e) = handle(stream, connection.0.clone()) {
+                        println!("OSCQuery connection failed: {}", e);
+                    }
+                    drop(connection);
+                });
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_INTERVAL),
+            Err(e) => {
+                println!("OSCQuery server failed to accept: {}", e);
+                thread::sleep(ACCEPT_INTERVAL);
+            }
+        }
+    }
+    println!("OSCQuery server stopped");
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: Option<String>,
+    // Header names in lowercase
+    headers: HashMap<String, String>,
+}
+
+fn read_request(stream: &mut impl Read) -> std::io::Result<Request> {
+    let invalid = |m: &str| std::io::Error::new(ErrorKind::InvalidData, m.to_string());
+
+    // Read until the end of the headers, requests to the server have no body
+    let mut data = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !data.windows(4).any(|w| w == b"\r\n\r\n") {
+        if data.len() > MAX_REQUEST_SIZE {
+            return Err(invalid("request too large"));
+        }
+        let size = stream.read(&mut buffer)?;
+        if size == 0 {
+            return Err(invalid("connection closed before the end of the request"));
+        }
+        data.extend_from_slice(&buffer[..size]);
+    }
+
+    let text = String::from_utf8_lossy(&data);
+    let mut lines = text.split("\r\n");
+    let mut request_line = lines.next().unwrap_or("").split_whitespace();
+    let method = request_line.next().ok_or_else(|| invalid("no method"))?;
+    let target = request_line.next().ok_or_else(|| invalid("no path"))?;
+
+    let (path, query) = match target.split_once('?') {
+        Some((p, q)) => (p, Some(q.to_string())),
+        None => (target, None),
+    };
+    // The root is the only path with a trailing slash
+    let path = match path.trim_end_matches('/') {
+        "" => "/",
+        p => p,
+    };
+
+    let headers = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+        .collect();
+
+    Ok(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query,
+        headers,
+    })
+}
+
+fn handle(mut stream: TcpStream, shared: Arc<Shared>) -> std::io::Result<()> {
+    // Accepted sockets inherit non-blocking from the listener on some platforms
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let request = read_request(&mut stream)?;
+
+    let upgrade = request
+        .headers
+        .get("upgrade")
+        .map(|u| u.eq_ignore_ascii_case("websocket"))
+        .unwrap_or(false);
+    if upgrade {
+        return websocket_session(stream, &request, shared);
+    }
+
+    if request.method != "GET" {
+        return respond(&mut stream, "405 Method Not Allowed", None);
+    }
+
+    if request.query.as_deref() == Some("HOST_INFO") {
+        let info = shared.host_info.lock().unwrap().clone();
+        return respond(&mut stream, "200 OK", Some(&info));
+    }
+
+    let node = node_json(&shared.namespace.lock().unwrap(), &request.path);
+    match (node, request.query) {
+        (None, _) => respond(&mut stream, "404 Not Found", None),
+        (Some(node), None) => respond(&mut stream, "200 OK", Some(&node)),
+        // A single attribute of the node, no content if the node does not have it
+        (Some(node), Some(attribute)) => match node.get(&attribute) {
+            Some(v) => {
+                let mut body = Map::new();
+                body.insert(attribute, v.clone());
+                respond(&mut stream, "200 OK", Some(&Value::Object(body)))
+            }
+            None => respond(&mut stream, "204 No Content", None),
+        },
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: Option<&Value>) -> std::io::Result<()> {
+    let body = body.map(|b| b.to_string()).unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes())
+}
+
+//
+// WebSocket session, streaming the values of the listened paths as they change,
+// and the paths added and removed as apps are watched or not
+//
+fn websocket_session(
+    mut stream: TcpStream,
+    request: &Request,
+    shared: Arc<Shared>,
+) -> std::io::Result<()> {
+    let key = match request.headers.get("sec-websocket-key") {
+        Some(k) => k,
+        None => return respond(&mut stream, "400 Bad Request", None),
+    };
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        websocket::accept_key(key)
+    );
+    stream.write_all(response.as_bytes())?;
+
+    //
+    // Frames from the client are read on their own thread, which may also write pongs.
+    // Clients answer the pings sent below, so one that sends nothing is gone
+    let listening: Arc<Mutex<HashSet<String>>> = Arc::new(Mutex::new(HashSet::new()));
+    let closed = Arc::new(AtomicBool::new(false));
+    stream.set_read_timeout(Some(SESSION_TIMEOUT))?;
+    let writer = Arc::new(Mutex::new(stream.try_clone()?));
+    let reader = {
+        let listening = listening.clone();
+        let closed = closed.clone();
+        let writer = writer.clone();
+        thread::spawn(move || read_commands(stream, listening, closed, writer))
+    };
+
+    let mut sent: HashMap<String, OscType> = HashMap::new();
+    let mut paths: HashSet<String> = shared.namespace.lock().unwrap().keys().cloned().collect();
+    let mut last_ping = Instant::now();
+
+    while shared.running.load(Ordering::Relaxed) && !closed.load(Ordering::Relaxed) {
+        thread::sleep(PUBLISH_INTERVAL);
+
+        let mut frames = Vec::new();
+        if last_ping.elapsed() >= PING_INTERVAL {
+            last_ping = Instant::now();
+            frames.push(Frame::Ping(Vec::new()));
+        }
+        {
+            let namespace = shared.namespace.lock().unwrap();
+
+            // Paths of apps that started or stopped being watched
+            let current: HashSet<String> = namespace.keys().cloned().collect();
+            for p in current.difference(&paths) {
+                frames.push(path_command("PATH_ADDED", p));
+            }
+            for p in paths.difference(&current) {
+                frames.push(path_command("PATH_REMOVED", p));
+                sent.remove(p);
+            }
+            paths = current;
+
+            // Values that changed since they were last sent
+            for path in listening.lock().unwrap().iter() {
+                let value = match namespace.get(path).and_then(|n| n.value.clone()) {
+                    Some(v) => v,
+                    None => continue,
+                };
+                if sent.get(path) == Some(&value) {
+                    continue;
+                }
+                let packet = OscPacket::Message(OscMessage {
+                    addr: path.clone(),
+                    args: vec![value.clone()],
+                });
+                if let Ok(bytes) = encoder::encode(&packet) {
+                    frames.push(Frame::Binary(bytes));
+                }
+                sent.insert(path.clone(), value);
+            }
+        }
+
+        let mut w = writer.lock().unwrap();
+        for frame in frames {
+            if websocket::write_frame(&mut *w, &frame).is_err() {
+                closed.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+    }
+
+    // Unblock the reader thread, whichever side closed the session
+    {
+        let mut w = writer.lock().unwrap();
+        let _ = websocket::write_frame(&mut *w, &Frame::Close);
+        let _ = w.shutdown(std::net::Shutdown::Both);
+    }
+    let _ = reader.join();
+
+    Ok(())
+}
+
+fn path_command(command: &str, path: &str) -> Frame {
+    Frame::Text(json!({ "COMMAND": command, "DATA": path }).to_string())
+}
+
+// LISTEN and IGNORE commands from the client, until it closes the session
+fn read_commands(
+    mut stream: TcpStream,
+    listening: Arc<Mutex<HashSet<String>>>,
+    closed: Arc<AtomicBool>,
+    writer: Arc<Mutex<TcpStream>>,
+) {
+    while !closed.load(Ordering::Relaxed) {
+        match websocket::read_frame(&mut stream) {
+            Ok(Frame::Text(text)) => {
+                let command: Value = match serde_json::from_str(&text) {
+                    Ok(c) => c,
+                    Err(_) => continue,
+                };
+                let path = match command["DATA"].as_str() {
+                    Some(p) => p.to_string(),
+                    None => continue,
+                };
+                match command["COMMAND"].as_str() {
+                    Some("LISTEN") => {
+                        listening.lock().unwrap().insert(path);
+                    }
+                    Some("IGNORE") => {
+                        listening.lock().unwrap().remove(&path);
+                    }
+                    _ => println!("OSCQuery unhandled command: {}", text),
+                }
+            }
+            // Answered with the same payload
+            Ok(Frame::Ping(payload)) => {
+                let _ = websocket::write_frame(&mut *writer.lock().unwrap(), &Frame::Pong(payload));
+            }
+            Ok(Frame::Binary(_)) => {
+                println!(
+                    "OSCQuery ignored OSC over WebSocket, send controls to the remote control port"
+                )
+            }
+            Ok(Frame::Pong(_)) => {}
+            Ok(Frame::Close) | Err(_) => closed.store(true, Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // Hands out its data a few bytes at a time, like a slow client
+    struct Chunked {
+        data: Vec<u8>,
+        at: usize,
+        size: usize,
+    }
+
+    impl Read for Chunked {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let end = (self.at + self.size)
+                .min(self.data.len())
+                .min(self.at + buf.len());
+            let n = end - self.at;
+            buf[..n].copy_from_slice(&self.data[self.at..end]);
+            self.at = end;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn reads_request_with_query_and_headers() {
+        let data =
+            b"GET /watchdog/apps/?VALUE HTTP/1.1\r\nHost: localhost\r\nUpgrade: WebSocket\r\n\r\n";
+        let r = read_request(&mut Cursor::new(&data[..])).unwrap();
+        assert_eq!(r.method, "GET");
+        assert_eq!(r.path, "/watchdog/apps");
+        assert_eq!(r.query.as_deref(), Some("VALUE"));
+        assert_eq!(
+            r.headers.get("upgrade").map(|s| s.as_str()),
+            Some("WebSocket")
+        );
+        assert_eq!(r.headers.get("host").map(|s| s.as_str()), Some("localhost"));
+    }
+
+    #[test]
+    fn root_keeps_its_slash() {
+        let r = read_request(&mut Cursor::new(&b"GET /?HOST_INFO HTTP/1.1\r\n\r\n"[..])).unwrap();
+        assert_eq!(r.path, "/");
+        assert_eq!(r.query.as_deref(), Some("HOST_INFO"));
+    }
+
+    #[test]
+    fn reads_request_split_across_reads() {
+        let mut stream = Chunked {
+            data: b"GET /watchdog HTTP/1.1\r\nHost: localhost\r\n\r\n".to_vec(),
+            at: 0,
+            size: 3,
+        };
+        let r = read_request(&mut stream).unwrap();
+        assert_eq!(r.path, "/watchdog");
+        assert_eq!(r.headers.len(), 1);
+    }
+
+    #[test]
+    fn partial_request_is_an_error() {
+        let data = b"GET /watchdog HTTP/1.1\r\nHost: local";
+        let e = read_request(&mut Cursor::new(&data[..])).err().unwrap();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn oversized_request_is_an_error() {
+        let mut data = b"GET / HTTP/1.1\r\nX-Padding: ".to_vec();
+        data.extend_from_slice(&vec![b'a'; MAX_REQUEST_SIZE * 2]);
+        data.extend_from_slice(b"\r\n\r\n");
+        let mut stream = Chunked {
+            data,
+            at: 0,
+            size: 1024,
+        };
+        let e = read_request(&mut stream).err().unwrap();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+        // Gives up soon after the limit, without reading the rest
+        assert!(stream.at <= MAX_REQUEST_SIZE + 2048);
+    }
+
+    #[test]
+    fn request_without_path_is_an_error() {
+        assert!(read_request(&mut Cursor::new(&b"GET\r\n\r\n"[..])).is_err());
+    }
+
+    fn namespace() -> Namespace {
+        let mut n = Namespace::new();
+        n.insert(
+            "/watchdog/apps/show/state".to_string(),
+            read("s", Some(OscType::String("running".to_string())), "State"),
+        );
+        n.insert(
+            "/watchdog/apps/show/restart".to_string(),
+            write("Restart the app"),
+        );
+        n.insert(
+            "/watchdog/watching".to_string(),
+            read("i", Some(OscType::Int(1)), "Watching"),
+        );
+        n
+    }
+
+    #[test]
+    fn node_json_of_a_value() {
+        let node = node_json(&namespace(), "/watchdog/apps/show/state").unwrap();
+        assert_eq!(node["FULL_PATH"], "/watchdog/apps/show/state");
+        assert_eq!(node["TYPE"], "s");
+        assert_eq!(node["ACCESS"], ACCESS_READ);
+        assert_eq!(node["VALUE"], json!(["running"]));
+    }
+
+    #[test]
+    fn node_json_of_a_control_has_no_value() {
+        let node = node_json(&namespace(), "/watchdog/apps/show/restart").unwrap();
+        assert_eq!(node["ACCESS"], ACCESS_WRITE);
+        assert!(node.get("VALUE").is_none());
+    }
+
+    #[test]
+    fn node_json_of_containers() {
+        let root = node_json(&namespace(), "/").unwrap();
+        assert_eq!(root["FULL_PATH"], "/");
+        assert_eq!(root["ACCESS"], 0);
+        let watchdog = &root["CONTENTS"]["watchdog"];
+        assert_eq!(watchdog["FULL_PATH"], "/watchdog");
+        assert_eq!(watchdog["CONTENTS"]["watching"]["VALUE"], json!([1]));
+
+        let show = &watchdog["CONTENTS"]["apps"]["CONTENTS"]["show"];
+        assert_eq!(show["FULL_PATH"], "/watchdog/apps/show");
+        let contents = show["CONTENTS"].as_object().unwrap();
+        assert_eq!(contents.len(), 2);
+        assert!(contents.contains_key("state"));
+        assert!(contents.contains_key("restart"));
+    }
+
+    #[test]
+    fn node_json_of_missing_path() {
+        assert!(node_json(&namespace(), "/watchdog/apps/other").is_none());
+        // A prefix of a segment is not a container
+        assert!(node_json(&namespace(), "/watchdog/app").is_none());
+    }
+}
@@ -0,0 +1,333 @@
+use std::io::{self, Read, Write};
+
+// GUID every WebSocket server appends to the client's key, from RFC 6455
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// Frames larger than this are refused, OSCQuery commands are small JSON objects
+const MAX_FRAME_SIZE: u64 = 64 * 1024;
+
+//
+// WebSocket
+//
+// Just enough of RFC 6455 for OSCQuery: the opening handshake, and reading and writing
+// unfragmented frames. Frames from the client are masked, frames from the server are not
+//
+
+pub enum Frame {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close,
+}
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+// The Sec-WebSocket-Accept header value answering a client's Sec-WebSocket-Key
+pub fn accept_key(key: &str) -> String {
+    let mut input = key.trim().as_bytes().to_vec();
+    input.extend_from_slice(ACCEPT_GUID.as_bytes());
+    base64(&sha1(&input))
+}
+
+pub fn read_frame(stream: &mut impl Read) -> io::Result<Frame> {
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header)?;
+
+    let opcode = header[0] & 0x0F;
+    let masked = header[1] & 0x80 != 0;
+    let mut len = (header[1] & 0x7F) as u64;
+    if len == 126 {
+        let mut ext = [0u8; 2];
+        stream.read_exact(&mut ext)?;
+        len = u16::from_be_bytes(ext) as u64;
+    } else if len == 127 {
+        let mut ext = [0u8; 8];
+        stream.read_exact(&mut ext)?;
+        len = u64::from_be_bytes(ext);
+    }
+    if len > MAX_FRAME_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "frame too large",
+        ));
+    }
+
+    let mut mask = [0u8; 4];
+    if masked {
+        stream.read_exact(&mut mask)?;
+    }
+
+    let mut payload = vec![0u8; len as usize];
+    stream.read_exact(&mut payload)?;
+    if masked {
+        for (i, b) in payload.iter_mut().enumerate() {
+            *b ^= mask[i % 4];
+        }
+    }
+
+    match opcode {
+        OPCODE_TEXT => Ok(Frame::Text(String::from_utf8_lossy(&payload).to_string())),
+        OPCODE_BINARY => Ok(Frame::Binary(payload)),
+        OPCODE_PING => Ok(Frame::Ping(payload)),
+        OPCODE_PONG => Ok(Frame::Pong(payload)),
+        OPCODE_CLOSE => Ok(Frame::Close),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unsupported frame opcode",
+        )),
+    }
+}
+
+pub fn write_frame(stream: &mut impl Write, frame: &Frame) -> io::Result<()> {
+    let (opcode, payload): (u8, &[u8]) = match frame {
+        Frame::Text(t) => (OPCODE_TEXT, t.as_bytes()),
+        Frame::Binary(b) => (OPCODE_BINARY, b),
+        Frame::Ping(p) => (OPCODE_PING, p),
+        Frame::Pong(p) => (OPCODE_PONG, p),
+        Frame::Close => (OPCODE_CLOSE, &[]),
+    };
+
+    // Final fragment, unmasked
+    let mut out = vec![0x80 | opcode];
+    let len = payload.len();
+    if len < 126 {
+        out.push(len as u8);
+    } else if len <= u16::MAX as usize {
+        out.push(126);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        out.push(127);
+        out.extend_from_slice(&(len as u64).to_be_bytes());
+    }
+    out.extend_from_slice(payload);
+
+    stream.write_all(&out)
+}
+
+//
+// SHA-1, only used for the handshake, where it is required by the protocol
+//
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    // Pad to a multiple of 64 bytes, ending with the message length in bits
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes([
+                chunk[i * 4],
+                chunk[i * 4 + 1],
+                chunk[i * 4 + 2],
+                chunk[i * 4 + 3],
+            ]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        h[0] = h[0].wrapping_add(a);
+        h[1] = h[1].wrapping_add(b);
+        h[2] = h[2].wrapping_add(c);
+        h[3] = h[3].wrapping_add(d);
+        h[4] = h[4].wrapping_add(e);
+    }
+
+    let mut out = [0u8; 20];
+    for (i, word) in h.iter().enumerate() {
+        out[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - i * 6)) as usize & 0x3F] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // A frame from the client, masked with the given key
+    fn masked(opcode: u8, payload: &[u8], mask: [u8; 4]) -> Vec<u8> {
+        let mut out = vec![0x80 | opcode];
+        if payload.len() < 126 {
+            out.push(0x80 | payload.len() as u8);
+        } else if payload.len() <= u16::MAX as usize {
+            out.push(0x80 | 126);
+            out.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        } else {
+            out.push(0x80 | 127);
+            out.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+        }
+        out.extend_from_slice(&mask);
+        out.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        out
+    }
+
+    #[test]
+    fn accept_key_matches_rfc_6455() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn sha1_and_base64_match_known_vectors() {
+        assert_eq!(base64(&sha1(b"")), "2jmj7l5rSw0yVb/vlWAYkK/YBwk=");
+        assert_eq!(base64(&sha1(b"abc")), "qZk+NkcGgWq6PiVxeFDCbJzQ2J0=");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+    }
+
+    #[test]
+    fn reads_masked_text_frame_from_rfc_6455() {
+        let data = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        match read_frame(&mut Cursor::new(data)).unwrap() {
+            Frame::Text(t) => assert_eq!(t, "Hello"),
+            _ => panic!("expected a text frame"),
+        }
+    }
+
+    #[test]
+    fn reads_unmasked_frame() {
+        let data = [0x89, 0x02, b'h', b'i'];
+        match read_frame(&mut Cursor::new(data)).unwrap() {
+            Frame::Ping(p) => assert_eq!(p, b"hi"),
+            _ => panic!("expected a ping frame"),
+        }
+    }
+
+    #[test]
+    fn reads_16_bit_length() {
+        let payload: Vec<u8> = (0..300).map(|i| i as u8).collect();
+        let data = masked(OPCODE_BINARY, &payload, [1, 2, 3, 4]);
+        match read_frame(&mut Cursor::new(data)).unwrap() {
+            Frame::Binary(b) => assert_eq!(b, payload),
+            _ => panic!("expected a binary frame"),
+        }
+    }
+
+    #[test]
+    fn reads_64_bit_length() {
+        let payload = vec![7u8; 200];
+        let mut data = vec![0x80 | OPCODE_BINARY, 0x80 | 127];
+        data.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+        data.extend_from_slice(&[9, 8, 7, 6]);
+        data.extend(
+            payload
+                .iter()
+                .enumerate()
+                .map(|(i, b)| b ^ [9, 8, 7, 6][i % 4]),
+        );
+        match read_frame(&mut Cursor::new(data)).unwrap() {
+            Frame::Binary(b) => assert_eq!(b, payload),
+            _ => panic!("expected a binary frame"),
+        }
+    }
+
+    #[test]
+    fn refuses_oversized_frame_before_reading_it() {
+        let mut data = vec![0x80 | OPCODE_BINARY, 127];
+        data.extend_from_slice(&u64::MAX.to_be_bytes());
+        let e = read_frame(&mut Cursor::new(data)).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn refuses_unsupported_opcode() {
+        let data = [0x83, 0x00];
+        assert!(read_frame(&mut Cursor::new(data)).is_err());
+    }
+
+    #[test]
+    fn truncated_frame_is_an_error() {
+        let mut data = masked(OPCODE_TEXT, b"Hello", [1, 2, 3, 4]);
+        data.truncate(data.len() - 2);
+        let e = read_frame(&mut Cursor::new(data)).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn written_frames_use_each_length_form() {
+        for len in [0usize, 125, 126, 65535, 65536] {
+            let mut out = Vec::new();
+            write_frame(&mut out, &Frame::Binary(vec![1; len])).unwrap();
+            assert_eq!(out[0], 0x80 | OPCODE_BINARY);
+            let header = match len {
+                0..=125 => {
+                    assert_eq!(out[1] as usize, len);
+                    2
+                }
+                126..=65535 => {
+                    assert_eq!(out[1], 126);
+                    assert_eq!(u16::from_be_bytes([out[2], out[3]]) as usize, len);
+                    4
+                }
+                _ => {
+                    assert_eq!(out[1], 127);
+                    let mut ext = [0u8; 8];
+                    ext.copy_from_slice(&out[2..10]);
+                    assert_eq!(u64::from_be_bytes(ext) as usize, len);
+                    10
+                }
+            };
+            assert_eq!(out.len(), header + len);
+        }
+    }
+}
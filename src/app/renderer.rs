@@ -125,11 +125,15 @@ pub fn render(
             &egui::Context,
             &mut super::state::State,
             &mut super::config::Config,
+            &super::apps::Apps,
+            &super::stats::Stats,
             &winit::window::Window,
         ),
     >,
     state: &mut super::state::State,
     config: &mut super::config::Config,
+    apps: &super::apps::Apps,
+    stats: &super::stats::Stats,
 ) {
     let start_time = Instant::now();
 
@@ -158,7 +162,14 @@ pub fn render(
     renderer.platform.begin_frame();
 
     // Draw the application
-    (draw_ui(&renderer.platform.context(), state, config, window));
+    (draw_ui(
+        &renderer.platform.context(),
+        state,
+        config,
+        apps,
+        stats,
+        window,
+    ));
 
     // End the UI frame. We could now handle the output and draw the UI with the backend.
     let full_output = renderer.platform.end_frame(Some(window));
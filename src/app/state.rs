@@ -1,4 +1,7 @@
-use super::config::Store;
+use super::announce::Peer;
+use super::config::{BackupDiff, Store, Undo, Watch};
+use super::notifications::Notification;
+use super::osc::Packet;
 use super::perf::Frame;
 use std::collections::HashMap;
 use std::collections::VecDeque;
@@ -23,6 +26,15 @@ pub struct State {
 
     // Config status
     pub json: Json,
+
+    // Edits to the config that can be undone
+    pub undo: Undo,
+
+    // Notifications raised for the operator, oldest first
+    pub notifications: VecDeque<Notification>,
+
+    // Other Watchdog instances heard on the announce multicast group
+    pub peers: Vec<Peer>,
 }
 
 pub struct Actions {
@@ -33,6 +45,9 @@ pub struct Actions {
     pub window_maximize: bool,
     pub window_unmaximize: bool,
     pub config_edited: bool,
+    pub config_reload: bool,
+    pub config_undo: bool,
+    pub config_redo: bool,
     pub build_listeners: bool,
     pub destroy_listeners: bool,
 }
@@ -61,14 +76,32 @@ pub struct UiState {
     pub resize_start_corner: (u8, u8),
     pub exit_tooltip_clickout: bool,
     pub config_watched_app_index: usize,
+    // Config edits allowed while apps are watched, until they stop being watched
+    pub config_unlocked: bool,
+    // Backup of the config file shown as a diff against the current file
+    pub config_backup_diff: Option<BackupDiff>,
+    // Traffic inspector, the app shown, a text filter, and the packets as they were when
+    // the list was paused, shown until it is resumed
+    pub traffic_app: String,
+    pub traffic_filter: String,
+    pub traffic_paused: Option<Vec<Packet>>,
 }
 
 pub struct Json {
     pub store: Store,
     pub filepath: std::path::PathBuf,
     pub exists: bool,
+    // False while the file could not be read, so that it is not saved over
     pub parsed: bool,
+    // Edits that are not saved to the file, as it could not be read or written
     pub dirty: bool,
+    // Why the file could not be read, until the operator chooses how to recover
+    pub error: Option<String>,
+    // Checks the file for changes made by something else, like a deploy
+    pub watch: Watch,
+    // Why a change to the file on disk was not applied, until the operator chooses
+    // to load it or keep the current config
+    pub changed: Option<String>,
 }
 
 #[derive(PartialEq)]
@@ -76,6 +109,7 @@ pub enum TabState {
     Config,
     Apps,
     Stats,
+    Traffic,
 }
 
 //
@@ -92,6 +126,9 @@ pub fn init() -> State {
         window_maximize: false,
         window_unmaximize: false,
         config_edited: false,
+        config_reload: false,
+        config_undo: false,
+        config_redo: false,
         build_listeners: false,
         destroy_listeners: false,
     };
@@ -111,6 +148,9 @@ pub fn init() -> State {
         exists: false,
         parsed: false,
         dirty: false,
+        error: None,
+        watch: Watch::new(),
+        changed: None,
     };
 
     let ui = UiState {
@@ -132,6 +172,11 @@ pub fn init() -> State {
         resize_start_corner: (0, 0),
         exit_tooltip_clickout: false,
         config_watched_app_index: 0,
+        config_unlocked: false,
+        config_backup_diff: None,
+        traffic_app: String::new(),
+        traffic_filter: String::new(),
+        traffic_paused: None,
     };
 
     State {
@@ -139,5 +184,8 @@ pub fn init() -> State {
         perf,
         ui,
         json,
+        undo: Undo::new(),
+        notifications: VecDeque::new(),
+        peers: Vec::new(),
     }
 }
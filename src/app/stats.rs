@@ -1,9 +1,365 @@
-pub struct Stats {}
+use super::apps;
+use super::config;
+use super::osc;
+use crate::utils::now;
 
-pub fn init() -> Stats {
-    println!("TODO: init stats section");
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+mod procfs;
+
+// How often to sample the resources of each watched app
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+// Keep one day of samples for each watched app
+const MAX_SAMPLE_CACHE: usize = 86400;
+
+const BYTES_PER_MEGABYTE: f64 = 1024.0 * 1024.0;
+
+// Cpu usage, in percent of one core, considered stuck for hang detection
+const CPU_IDLE_PERCENT: f32 = 0.5;
+const CPU_PINNED_PERCENT: f32 = 99.0;
+
+//
+// One point in the time series of a watched app's resource usage
+//
+pub struct Sample {
+    pub time: Duration,
+    // Percent of a single core, may exceed 100 for multi threaded apps
+    pub cpu: f32,
+    // Resident set size in bytes
+    pub rss: u64,
+    pub threads: u32,
+    pub fds: u32,
+    pub state: char,
+}
+
+impl Sample {
+    pub fn value(&self, metric: &Metric) -> f64 {
+        match metric {
+            Metric::Cpu => self.cpu as f64,
+            Metric::Rss => self.rss as f64,
+        }
+    }
+}
+
+pub enum Metric {
+    Cpu,
+    Rss,
+}
+
+//
+// A limit on one metric, breached when the metric stays above the limit for the whole duration
+//
+pub struct Threshold {
+    pub metric: Metric,
+    pub limit: f64,
+    pub duration: Duration,
+}
+
+impl Threshold {
+    // Build the enabled thresholds from a watched app's config, a limit of 0 is disabled
+    pub fn from_config(c: &super::config::WatchedApp) -> Vec<Threshold> {
+        let mut thresholds = Vec::new();
+        let duration = Duration::from_secs(u64::from(&c.limit_duration.val));
+
+        let memory_limit = u64::from(&c.memory_limit.val);
+        if memory_limit > 0 {
+            thresholds.push(Threshold {
+                metric: Metric::Rss,
+                limit: memory_limit as f64 * BYTES_PER_MEGABYTE,
+                duration,
+            });
+        }
+
+        let cpu_limit = u64::from(&c.cpu_limit.val);
+        if cpu_limit > 0 {
+            thresholds.push(Threshold {
+                metric: Metric::Cpu,
+                limit: cpu_limit as f64,
+                duration,
+            });
+        }
+
+        thresholds
+    }
+}
+
+pub struct AppStats {
+    pub pid: u32,
+    pub samples: VecDeque<Sample>,
+    // Time of the first sample of the current process
+    since: Duration,
+    // Total cpu ticks of the process at the previous sample, to calculate usage between samples
+    cpu_ticks: Option<(u64, Duration)>,
+    // History of each numeric telemetry key the app reports, as it was reported
+    pub telemetry: HashMap<String, VecDeque<(Duration, f64)>>,
+    // Telemetry alert rules currently breached
+    pub alerting: Vec<config::AlertRule>,
+}
+
+impl AppStats {
+    fn new(pid: u32) -> Self {
+        AppStats {
+            pid,
+            samples: VecDeque::new(),
+            since: now(),
+            cpu_ticks: None,
+            telemetry: HashMap::new(),
+            alerting: Vec::new(),
+        }
+    }
+
+    pub fn latest(&self) -> Option<&Sample> {
+        self.samples.back()
+    }
+
+    // Test if the most recent samples have all been over the limit, for at least the duration
+    pub fn exceeds(&self, threshold: &Threshold) -> bool {
+        self.holds_for(threshold.duration, |s| {
+            s.value(&threshold.metric) > threshold.limit
+        })
+    }
+
+    // Test if the most recent samples of the current process all pass the test, for at least the duration
+    fn holds_for(&self, duration: Duration, test: impl Fn(&Sample) -> bool) -> bool {
+        let newest = match self.samples.back() {
+            Some(s) => s.time,
+            None => return false,
+        };
+
+        let mut held_since = None;
+        for s in self.samples.iter().rev() {
+            if s.time >= self.since && test(s) {
+                held_since = Some(s.time);
+            } else {
+                break;
+            }
+        }
+
+        match held_since {
+            Some(since) => newest.saturating_sub(since) >= duration,
+            None => false,
+        }
+    }
+
+    // Test if a telemetry key of the current process has breached the rule, for at least its duration
+    fn telemetry_breaches(&self, rule: &config::AlertRule) -> bool {
+        let history = match self.telemetry.get(&rule.key) {
+            Some(h) => h,
+            None => return false,
+        };
+        let newest = match history.back() {
+            Some((time, _)) => *time,
+            None => return false,
+        };
 
-    let stats = Stats {};
+        let mut held_since = None;
+        for (time, value) in history.iter().rev() {
+            if *time >= self.since && rule.breached_by(*value) {
+                held_since = Some(*time);
+            } else {
+                break;
+            }
+        }
+
+        match held_since {
+            Some(since) => newest.saturating_sub(since) >= Duration::from_secs(rule.duration),
+            None => false,
+        }
+    }
+
+    // Sample only the values reported since the previous sample, at the time they were reported,
+    // so a key the app stopped reporting does not keep a rule breached on its last value
+    fn sample_telemetry(&mut self, telemetry: &osc::Telemetry) {
+        for (key, (value, reported)) in telemetry.iter() {
+            if let Some(v) = value.number() {
+                let history = self.telemetry.entry(key.clone()).or_default();
+                if matches!(history.back(), Some((time, _)) if time >= reported) {
+                    continue;
+                }
+                history.push_back((*reported, v));
+
+                // Ensure the list is below max length, removing oldest elements
+                while history.len() > MAX_SAMPLE_CACHE {
+                    history.pop_front();
+                }
+            }
+        }
+    }
+
+    fn sample(&mut self, reading: procfs::Reading, time: Duration) {
+        // Cpu usage is the change in cpu time over the change in wall clock time
+        let cpu = match self.cpu_ticks {
+            Some((ticks, at)) => {
+                let elapsed = time.saturating_sub(at).as_secs_f64();
+                if elapsed > 0.0 {
+                    let used =
+                        reading.cpu_ticks.saturating_sub(ticks) as f64 / procfs::TICKS_PER_SECOND;
+                    (used / elapsed * 100.0) as f32
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        };
+        self.cpu_ticks = Some((reading.cpu_ticks, time));
+
+        self.samples.push_back(Sample {
+            time,
+            cpu,
+            rss: reading.rss,
+            threads: reading.threads,
+            fds: reading.fds,
+            state: reading.state,
+        });
+
+        // Ensure the list is below max length, removing oldest elements
+        while self.samples.len() > MAX_SAMPLE_CACHE {
+            self.samples.pop_front();
+        }
+    }
+}
+
+pub struct Stats {
+    // Resource sampling is only available where /proc exists
+    pub supported: bool,
+    pub apps: HashMap<String, AppStats>,
+    last_sample: Duration,
+    // Telemetry alerts raised since they were last taken, as app name and message
+    alerts: Vec<(String, String)>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Stats {
+            supported: procfs::SUPPORTED,
+            apps: HashMap::new(),
+            last_sample: Duration::from_secs(0),
+            alerts: Vec::new(),
+        }
+    }
+
+    //
+    // Sample each running watched app, called every tick of the event loop but throttled
+    // to the sample interval. Returns the names of any apps that breached a threshold,
+    // and should be restarted. Telemetry alerts are raised, to be taken with take_alerts
+    //
+    pub fn update(&mut self, apps: &apps::Apps) -> Vec<String> {
+        let mut breached = Vec::new();
+
+        let n = now();
+        if n.saturating_sub(self.last_sample) < SAMPLE_INTERVAL {
+            return breached;
+        }
+        self.last_sample = n;
+
+        for a in apps.iter() {
+            let pid = match a.pid() {
+                Some(pid) => pid,
+                None => continue,
+            };
+
+            let app_stats = self
+                .apps
+                .entry(a.name().to_string())
+                .or_insert_with(|| AppStats::new(pid));
+
+            // App was relaunched, keep the history but restart the cpu baseline
+            if app_stats.pid != pid {
+                app_stats.pid = pid;
+                app_stats.since = n;
+                app_stats.cpu_ticks = None;
+                app_stats.alerting.clear();
+            }
+
+            if self.supported {
+                if let Some(reading) = procfs::read(pid) {
+                    app_stats.sample(reading, n);
+                }
+            }
+
+            //
+            // Telemetry alerts, raised once each time a rule starts being breached
+            app_stats.sample_telemetry(&a.telemetry());
+            for alert in a.config().telemetry_alerts.iter() {
+                let rule = match config::AlertRule::parse(&String::from(&alert.val)) {
+                    Ok(rule) => rule,
+                    Err(_) => continue,
+                };
+                let breached = app_stats.telemetry_breaches(&rule);
+                let alerting = app_stats.alerting.contains(&rule);
+                if breached && !alerting {
+                    println!("App {} telemetry alert: {}", a.name(), rule);
+                    self.alerts.push((a.name().to_string(), rule.to_string()));
+                    app_stats.alerting.push(rule);
+                } else if !breached && alerting {
+                    app_stats.alerting.retain(|r| *r != rule);
+                }
+            }
+
+            for t in Threshold::from_config(a.config()) {
+                if app_stats.exceeds(&t) {
+                    println!(
+                        "App {} exceeded its {} limit for {:?}, restarting",
+                        a.name(),
+                        match t.metric {
+                            Metric::Cpu => "cpu",
+                            Metric::Rss => "memory",
+                        },
+                        t.duration
+                    );
+                    breached.push(a.name().to_string());
+                    break;
+                }
+            }
+        }
+
+        breached
+    }
+
+    pub fn take_alerts(&mut self) -> Vec<(String, String)> {
+        std::mem::take(&mut self.alerts)
+    }
+
+    //
+    // Secondary liveness checks from the resource samples, for apps that still
+    // answer heartbeats while their main loop is frozen
+    //
+    pub fn hang(&self, a: &apps::App) -> Option<apps::Hang> {
+        let app_stats = self.apps.get(a.name())?;
+        if a.pid() != Some(app_stats.pid) {
+            return None;
+        }
+
+        let c = a.config();
+        let timeout = |data: &super::config::ConfigData| {
+            let secs = u64::from(&data.val);
+            (secs > 0).then(|| Duration::from_secs(secs))
+        };
+
+        if let Some(t) = timeout(&c.cpu_idle_timeout) {
+            if app_stats.holds_for(t, |s| s.cpu < CPU_IDLE_PERCENT) {
+                return Some(apps::Hang::CpuIdle);
+            }
+        }
+        if let Some(t) = timeout(&c.cpu_pinned_timeout) {
+            if app_stats.holds_for(t, |s| s.cpu >= CPU_PINNED_PERCENT) {
+                return Some(apps::Hang::CpuPinned);
+            }
+        }
+        if let Some(t) = timeout(&c.uninterruptible_timeout) {
+            if app_stats.holds_for(t, |s| s.state == 'D') {
+                return Some(apps::Hang::Uninterruptible);
+            }
+        }
+
+        None
+    }
+}
+
+pub fn init() -> Stats {
+    let stats = Stats::new();
 
     return stats;
 }
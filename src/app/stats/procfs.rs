@@ -0,0 +1,64 @@
+//
+// Read the resource usage of a process from the /proc filesystem, only available on Linux
+//
+
+#[cfg(target_os = "linux")]
+use std::fs;
+
+pub const SUPPORTED: bool = cfg!(target_os = "linux");
+
+// Clock ticks per second used by the times in /proc/<pid>/stat, USER_HZ is 100 on all mainstream kernels
+pub const TICKS_PER_SECOND: f64 = 100.0;
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub struct Reading {
+    // Total user + system time, in clock ticks
+    pub cpu_ticks: u64,
+    // Resident set size in bytes
+    pub rss: u64,
+    pub threads: u32,
+    pub fds: u32,
+    // Scheduler state, eg. R running, S sleeping, D uninterruptible sleep
+    pub state: char,
+}
+
+#[cfg(target_os = "linux")]
+pub fn read(pid: u32) -> Option<Reading> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+
+    // The process name may contain spaces, so skip past its closing paren,
+    // the remaining fields start at field 3 (state) of proc(5)
+    let rest = stat.get(stat.rfind(')')? + 2..)?;
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let state = fields.first()?.chars().next()?;
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    let threads: u32 = fields.get(17)?.parse().ok()?;
+
+    // VmRSS is reported in kB
+    let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let rss_kb: u64 = status
+        .lines()
+        .find(|l| l.starts_with("VmRSS:"))
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|kb| kb.parse().ok())
+        .unwrap_or(0);
+
+    // Each entry in the fd directory is one open file descriptor
+    let fds = fs::read_dir(format!("/proc/{}/fd", pid))
+        .map(|d| d.count() as u32)
+        .unwrap_or(0);
+
+    Some(Reading {
+        cpu_ticks: utime + stime,
+        rss: rss_kb * 1024,
+        threads,
+        fds,
+        state,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read(_pid: u32) -> Option<Reading> {
+    None
+}
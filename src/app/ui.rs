@@ -1,7 +1,9 @@
 use egui::{Color32, Vec2};
 
+use crate::app::apps::Apps;
 use crate::app::config::Config;
 use crate::app::state::{State, TabState};
+use crate::app::stats::Stats;
 use crate::utils;
 
 // Import submodules
@@ -9,11 +11,13 @@ mod components;
 mod exit;
 mod icons;
 mod nav_bar;
+mod notifications;
 mod perf;
 mod resize_borders;
 mod section_apps;
 mod section_config;
 mod section_stats;
+mod section_traffic;
 mod sections;
 mod title_bar;
 mod typography;
@@ -47,13 +51,16 @@ pub const COLOR_GREEN: Color32 = Color32::from_rgb(44, 221, 126);
 // that will be called from the primary event loop, and renderer module
 pub fn init(
     state: &mut State,
-) -> Box<dyn FnMut(&egui::Context, &mut State, &mut Config, &winit::window::Window)> {
+) -> Box<dyn FnMut(&egui::Context, &mut State, &mut Config, &Apps, &Stats, &winit::window::Window)>
+{
     icons::init(state);
 
     Box::new(
         |context: &egui::Context,
          state: &mut State,
          config: &mut Config,
+         apps: &Apps,
+         stats: &Stats,
          window: &winit::window::Window| {
             // Ensure all icon image files are loaded
             // Called every frame, but guarded with boolean flags so
@@ -81,7 +88,10 @@ pub fn init(
             perf::draw(context, state);
 
             // Draw the main content
-            sections::draw(context, state, config);
+            sections::draw(context, state, config, apps, stats);
+
+            // Draw toasts for any recent notifications
+            notifications::draw(context, state);
         },
     )
 }
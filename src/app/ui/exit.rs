@@ -91,9 +91,8 @@ pub fn draw(context: &egui::Context, state: &mut State, mut anchor: egui::Pos2)
                                         "Exit, and kill Watched Apps",
                                     );
                                     if r.clicked() {
-                                        //
-                                        // TO DO: kill watched apps
-                                        //
+                                        // Kill watched apps
+                                        state.actions.destroy_listeners = true;
 
                                         // Hide the exit tooltip
                                         state.ui.show_exit_tooltip = false;
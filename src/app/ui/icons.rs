@@ -14,12 +14,15 @@ const ASSET_ICON_CLOSE: &[u8] = include_bytes!("../../../assets/icons/icon-close
 const ASSET_ICON_CONFIG: &[u8] = include_bytes!("../../../assets/icons/icon-config.png");
 const ASSET_ICON_APPS: &[u8] = include_bytes!("../../../assets/icons/icon-apps.png");
 const ASSET_ICON_STATS: &[u8] = include_bytes!("../../../assets/icons/icon-stats.png");
+const ASSET_ICON_TRAFFIC: &[u8] = include_bytes!("../../../assets/icons/icon-traffic.png");
 const ASSET_ICON_EXIT: &[u8] = include_bytes!("../../../assets/icons/icon-exit.png");
 
 // Config section
 const ASSET_ICON_LOAD: &[u8] = include_bytes!("../../../assets/icons/icon-load.png");
 const ASSET_ICON_SAVE: &[u8] = include_bytes!("../../../assets/icons/icon-save.png");
 const ASSET_ICON_RESET: &[u8] = include_bytes!("../../../assets/icons/icon-reset.png");
+const ASSET_ICON_UNDO: &[u8] = include_bytes!("../../../assets/icons/icon-undo.png");
+const ASSET_ICON_REDO: &[u8] = include_bytes!("../../../assets/icons/icon-redo.png");
 const ASSET_ICON_CREATE: &[u8] = include_bytes!("../../../assets/icons/icon-create.png");
 const ASSET_ICON_DELETE: &[u8] = include_bytes!("../../../assets/icons/icon-delete.png");
 
@@ -41,11 +44,14 @@ pub fn init(state: &mut State) {
         ("icon_config", ASSET_ICON_CONFIG),
         ("icon_apps", ASSET_ICON_APPS),
         ("icon_stats", ASSET_ICON_STATS),
+        ("icon_traffic", ASSET_ICON_TRAFFIC),
         ("icon_exit", ASSET_ICON_EXIT),
         // Config
         ("icon_load", ASSET_ICON_LOAD),
         ("icon_save", ASSET_ICON_SAVE),
         ("icon_reset", ASSET_ICON_RESET),
+        ("icon_undo", ASSET_ICON_UNDO),
+        ("icon_redo", ASSET_ICON_REDO),
         ("icon_create", ASSET_ICON_CREATE),
         ("icon_delete", ASSET_ICON_DELETE),
         //
@@ -31,6 +31,10 @@ pub fn draw(context: &egui::Context, state: &mut State, config: &Config) {
                 let stats_valid = false; // TO DO: make real valid / invalid status
                 draw_nav_button(ui, state, TabState::Stats, stats_valid);
 
+                // Draw Traffic Button
+                let traffic_valid = false; // TO DO: make real valid / invalid status
+                draw_nav_button(ui, state, TabState::Traffic, traffic_valid);
+
                 // Float to bottom
                 ui.add_space(ui.available_height() - ICON_SIZE - MARGIN);
 
@@ -83,6 +87,7 @@ fn draw_nav_button(ui: &mut egui::Ui, state: &mut State, tab_state: TabState, is
         TabState::Config => "icon_config",
         TabState::Apps => "icon_apps",
         TabState::Stats => "icon_stats",
+        TabState::Traffic => "icon_traffic",
     };
 
     // Lookup the texture handle with the slug
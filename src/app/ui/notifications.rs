@@ -0,0 +1,81 @@
+use crate::app::ui::*;
+
+// How long each notification is shown for
+const TOAST_SECS: i64 = 8;
+const TOAST_WIDTH: f32 = 280.0;
+
+//
+// Draw the most recent notifications as toasts in the bottom right corner of the window
+//
+pub fn draw(context: &egui::Context, state: &mut State) {
+    let now = chrono::Local::now();
+    let recent: Vec<&crate::app::notifications::Notification> = state
+        .notifications
+        .iter()
+        .filter(|n| (now - n.time).num_seconds() < TOAST_SECS)
+        .collect();
+
+    if recent.is_empty() {
+        return;
+    }
+
+    // Keep the toasts redrawing, so they disappear on time
+    context.request_repaint();
+
+    let mut anchor = context.screen_rect().right_bottom();
+    anchor.x -= 20.0;
+    anchor.y -= 20.0;
+
+    egui::Area::new("notifications")
+        .order(egui::Order::Foreground)
+        .movable(false)
+        .interactable(false)
+        .pivot(egui::Align2::RIGHT_BOTTOM)
+        .fixed_pos(anchor)
+        .show(context, |ui| {
+            for n in recent {
+                egui::Frame::none()
+                    .inner_margin(egui::Margin {
+                        left: 18.0,
+                        right: 18.0,
+                        top: 14.0,
+                        bottom: 14.0,
+                    })
+                    .outer_margin(egui::Margin {
+                        left: 0.0,
+                        right: 0.0,
+                        top: 0.0,
+                        bottom: 8.0,
+                    })
+                    .fill(COLOR_DARKER_GREY)
+                    .stroke(egui::Stroke {
+                        color: COLOR_YELLOW,
+                        width: 1.0,
+                    })
+                    .rounding(egui::Rounding {
+                        nw: 4.0,
+                        ne: 4.0,
+                        se: 4.0,
+                        sw: 4.0,
+                    })
+                    .show(ui, |ui| {
+                        ui.set_width(TOAST_WIDTH);
+                        ui.label(
+                            egui::RichText::new(&n.title)
+                                .text_style(egui::TextStyle::Name("TextButton".into()))
+                                .color(COLOR_TEXT_WHITE),
+                        );
+                        ui.add_space(4.0);
+                        ui.add(
+                            egui::Label::new(egui::RichText::new(&n.body).color(COLOR_OFFWHITE))
+                                .wrap(true),
+                        );
+                        ui.label(
+                            egui::RichText::new(n.time.format("%H:%M:%S").to_string())
+                                .text_style(egui::TextStyle::Name("TextButtonSmall".into()))
+                                .color(COLOR_GRAY_TINT),
+                        );
+                    });
+            }
+        });
+}
@@ -1,5 +1,16 @@
+use crate::app::apps::AppState;
 use crate::app::ui::*;
-pub fn draw(ui: &mut egui::Ui, state: &mut State, _config: &mut Config) {
+
+// Number of stop events to list for each app
+const MAX_EVENTS_SHOWN: usize = 5;
+
+pub fn draw(
+    ui: &mut egui::Ui,
+    state: &mut State,
+    _config: &mut Config,
+    apps: &Apps,
+    stats: &Stats,
+) {
     ui.allocate_ui_with_layout(
         egui::Vec2 {
             x: ui.available_width(),
@@ -33,4 +44,195 @@ pub fn draw(ui: &mut egui::Ui, state: &mut State, _config: &mut Config) {
     if r.clicked() {
         state.actions.destroy_listeners = true;
     }
+
+    if apps.paused() {
+        ui.add_space(5.0);
+        ui.label(
+            egui::RichText::new("Supervision is paused, apps are not checked or restarted.")
+                .color(COLOR_OFFWHITE),
+        );
+    }
+
+    //
+    // Status of each watched app
+    for a in apps.iter() {
+        components::draw_separator(ui);
+
+        ui.label(
+            egui::RichText::new(a.name())
+                .text_style(egui::TextStyle::Name("Subheading".into()))
+                .color(COLOR_TEXT_WHITE),
+        );
+
+        ui.add_space(ROW_MARGIN);
+
+        if let Some(e) = a.bind_error() {
+            components::draw_row_static(ui, "Error", &mut e.to_string());
+        }
+
+        components::draw_row_static(
+            ui,
+            "PID",
+            &mut match a.pid() {
+                Some(pid) => pid.to_string(),
+                None => "not running".to_string(),
+            },
+        );
+
+        components::draw_row_static(
+            ui,
+            "State",
+            &mut match a.state() {
+                AppState::Stopped => "stopped".to_string(),
+                AppState::Starting { .. } => "starting".to_string(),
+                AppState::Running => "running".to_string(),
+                AppState::Restarting { at } => format!(
+                    "restarting in {}s",
+                    at.saturating_sub(utils::now()).as_secs()
+                ),
+            },
+        );
+
+        components::draw_row_static(ui, "Restarts", &mut a.restarts().to_string());
+
+        if let Some(status) = a.reported_status() {
+            components::draw_row_static(ui, "Reported Status", &mut status.to_string());
+        }
+
+        if let Some(connected) = a.connected() {
+            components::draw_row_static(
+                ui,
+                "OSC Connection",
+                &mut if connected {
+                    "connected".to_string()
+                } else {
+                    "waiting for the app to connect".to_string()
+                },
+            );
+        }
+
+        if let Some(hang) = a.hang() {
+            components::draw_row_static(ui, "Looks Hung", &mut hang.to_string());
+        }
+
+        //
+        // Time since each health check last passed
+        for c in a.checks() {
+            let last_pass = c.status.last_pass();
+            components::draw_row_static(
+                ui,
+                &c.description,
+                &mut if last_pass.is_zero() {
+                    "not passed yet".to_string()
+                } else {
+                    format!(
+                        "passed {}s ago",
+                        utils::now().saturating_sub(last_pass).as_secs()
+                    )
+                },
+            );
+        }
+
+        //
+        // Heartbeat round trips, for apps that echo their heartbeats back
+        if let Some(latency) = a.latency() {
+            if latency.echoing() {
+                let rtt = latency.rtt().unwrap_or_default().as_secs_f64() * 1000.0;
+                let mean = latency.mean_rtt().unwrap_or_default().as_secs_f64() * 1000.0;
+                components::draw_row_static(
+                    ui,
+                    "Round Trip",
+                    &mut if latency.rising() {
+                        format!("{:.1} ms (mean {:.1} ms), rising", rtt, mean)
+                    } else {
+                        format!("{:.1} ms (mean {:.1} ms)", rtt, mean)
+                    },
+                );
+                components::draw_row_static(
+                    ui,
+                    "Jitter",
+                    &mut format!("{:.1} ms", latency.jitter().as_secs_f64() * 1000.0),
+                );
+                components::draw_row_static(
+                    ui,
+                    "Heartbeat Loss",
+                    &mut format!("{:.1}% of {} sent", latency.loss(), latency.total_sent()),
+                );
+            } else if latency.total_sent() > 0 {
+                components::draw_row_static(
+                    ui,
+                    "Round Trip",
+                    &mut "heartbeats not echoed".to_string(),
+                );
+            }
+        }
+
+        //
+        // Telemetry reported in the heartbeats, sorted by key so the order is stable between frames
+        let telemetry = a.telemetry();
+        let mut keys: Vec<&String> = telemetry.keys().collect();
+        keys.sort();
+        for key in keys {
+            components::draw_row_static(ui, key, &mut telemetry[key].0.to_string());
+        }
+
+        if let Some(app_stats) = stats.apps.get(a.name()) {
+            for rule in app_stats.alerting.iter() {
+                components::draw_row_static(ui, "Alert", &mut rule.to_string());
+            }
+        }
+
+        //
+        // Most recent stop events first
+        for e in a.events().iter().rev().take(MAX_EVENTS_SHOWN) {
+            components::draw_row_static(
+                ui,
+                &e.time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                &mut e.reason.to_string(),
+            );
+        }
+    }
+
+    //
+    // Other Watchdog instances heard on the announce multicast group
+    for p in state.peers.iter() {
+        components::draw_separator(ui);
+
+        ui.label(
+            egui::RichText::new(format!("{} (Watchdog {})", p.host, p.pid))
+                .text_style(egui::TextStyle::Name("Subheading".into()))
+                .color(COLOR_TEXT_WHITE),
+        );
+
+        ui.add_space(ROW_MARGIN);
+
+        components::draw_row_static(
+            ui,
+            "Heard",
+            &mut format!("{} from {}", p.received.format("%H:%M:%S"), p.from),
+        );
+
+        components::draw_row_static(
+            ui,
+            "Supervision",
+            &mut match (p.watching, p.paused) {
+                (false, _) => "not watching".to_string(),
+                (true, true) => "paused".to_string(),
+                (true, false) => "watching".to_string(),
+            },
+        );
+
+        for app in p.apps.iter() {
+            let label = if app.group.is_empty() {
+                app.name.clone()
+            } else {
+                format!("{} ({})", app.name, app.group)
+            };
+            components::draw_row_static(
+                ui,
+                &label,
+                &mut format!("{}, {} restarts", app.state, app.restarts),
+            );
+        }
+    }
 }
@@ -1,5 +1,11 @@
 use crate::app::config;
 use crate::app::ui::*;
+
+// Height of the diff of a backup, which scrolls past this
+const DIFF_HEIGHT: f32 = 320.0;
+
+// Distance of the lock overlay's message from the top of the locked config
+const LOCK_OFFSET: f32 = 60.0;
 //
 // Config section all ui elements
 //
@@ -11,7 +17,47 @@ enum ButtonState {
     Selected,
 }
 
-pub fn draw(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
+//
+// While apps are watched, the config is locked so a running show is not broken by accident.
+// It is drawn disabled under an overlay, until the apps are stopped or it is unlocked to
+// edit, with each edit applied to the running apps. A config file that could not be read,
+// or a change to it that was not applied, can still be resolved while it is locked
+//
+pub fn draw(ui: &mut egui::Ui, state: &mut State, config: &mut Config, apps: &Apps) {
+    let locked = apps.watching() && !state.ui.config_unlocked;
+
+    if apps.watching() && state.ui.config_unlocked {
+        draw_unlocked(ui, state);
+    }
+
+    let header = ui.add_enabled_ui(!locked, |ui| draw_header(ui, state, config));
+
+    draw_recovery(ui, state, config);
+
+    draw_changed(ui, state, config);
+
+    let contents = ui.add_enabled_ui(!locked, |ui| draw_contents(ui, state, config));
+
+    if locked {
+        draw_lock(ui, state, header.response.rect, contents.response.rect);
+    }
+}
+
+fn draw_header(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
+    //
+    // Undo and redo shortcuts, taken before the text fields would undo their own text
+    let shortcut_undo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+    let shortcut_redo = egui::KeyboardShortcut::new(
+        egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+        egui::Key::Z,
+    );
+    if ui.is_enabled() && ui.input_mut(|i| i.consume_shortcut(&shortcut_redo)) {
+        state.actions.config_redo = true;
+    }
+    if ui.is_enabled() && ui.input_mut(|i| i.consume_shortcut(&shortcut_undo)) {
+        state.actions.config_undo = true;
+    }
+
     //
     // Header and JSON File Buttons
     ui.allocate_ui_with_layout(
@@ -54,6 +100,8 @@ pub fn draw(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
                     let icon_load = state.ui.textures.get("icon_load").unwrap();
                     let icon_save = state.ui.textures.get("icon_save").unwrap();
                     let icon_reset = state.ui.textures.get("icon_reset").unwrap();
+                    let icon_undo = state.ui.textures.get("icon_undo").unwrap();
+                    let icon_redo = state.ui.textures.get("icon_redo").unwrap();
 
                     // Reset Button UI
                     let r_reset = ui.add(egui::ImageButton::new(&icon_reset.1, icon_w));
@@ -63,6 +111,34 @@ pub fn draw(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
                     ui.add_space(8.0);
                     // Load Button UI
                     let r_load = ui.add(egui::ImageButton::new(&icon_load.1, icon_w));
+                    ui.add_space(24.0);
+                    // Redo Button UI
+                    let r_redo = ui.add_enabled(
+                        state.undo.can_redo(),
+                        egui::ImageButton::new(&icon_redo.1, icon_w),
+                    );
+                    ui.add_space(8.0);
+                    // Undo Button UI
+                    let r_undo = ui.add_enabled(
+                        state.undo.can_undo(),
+                        egui::ImageButton::new(&icon_undo.1, icon_w),
+                    );
+
+                    // Interaction for Undo Button
+                    if r_undo.clicked() {
+                        state.actions.config_undo = true;
+                    }
+                    r_undo
+                        .on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .on_hover_text("Undo (Ctrl+Z)");
+
+                    // Interaction for Redo Button
+                    if r_redo.clicked() {
+                        state.actions.config_redo = true;
+                    }
+                    r_redo
+                        .on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .on_hover_text("Redo (Ctrl+Shift+Z)");
 
                     // Interaction for Load Button
                     if r_load.clicked() {
@@ -107,7 +183,9 @@ pub fn draw(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
         // and the file must be selected with the buttons
         &mut state.json.filepath.to_str().unwrap().to_string(),
     );
+}
 
+fn draw_contents(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
     ui.add_space(SECTION_HEADING_MARGIN);
 
     components::draw_separator(ui);
@@ -383,6 +461,13 @@ pub fn draw(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
         &mut config.watched_apps[state.ui.config_watched_app_index].run,
     );
 
+    components::draw_row(
+        ui,
+        state,
+        "Group",
+        &mut config.watched_apps[state.ui.config_watched_app_index].group,
+    );
+
     components::draw_row(
         ui,
         state,
@@ -397,6 +482,27 @@ pub fn draw(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
         &mut config.watched_apps[state.ui.config_watched_app_index].osc_out_port,
     );
 
+    components::draw_row(
+        ui,
+        state,
+        "OSC Bind Address",
+        &mut config.watched_apps[state.ui.config_watched_app_index].osc_bind_address,
+    );
+
+    components::draw_row(
+        ui,
+        state,
+        "OSC Client Address",
+        &mut config.watched_apps[state.ui.config_watched_app_index].osc_client_address,
+    );
+
+    components::draw_row(
+        ui,
+        state,
+        "OSC Transport",
+        &mut config.watched_apps[state.ui.config_watched_app_index].osc_transport,
+    );
+
     components::draw_row(
         ui,
         state,
@@ -432,6 +538,96 @@ pub fn draw(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
         &mut config.watched_apps[state.ui.config_watched_app_index].restart_delay,
     );
 
+    components::draw_row(
+        ui,
+        state,
+        "Daily Restart (HH:MM)",
+        &mut config.watched_apps[state.ui.config_watched_app_index].restart_schedule,
+    );
+
+    components::draw_row(
+        ui,
+        state,
+        "Memory Limit (MB)",
+        &mut config.watched_apps[state.ui.config_watched_app_index].memory_limit,
+    );
+
+    components::draw_row(
+        ui,
+        state,
+        "CPU Limit (%)",
+        &mut config.watched_apps[state.ui.config_watched_app_index].cpu_limit,
+    );
+
+    components::draw_row(
+        ui,
+        state,
+        "Limit Duration (sec)",
+        &mut config.watched_apps[state.ui.config_watched_app_index].limit_duration,
+    );
+
+    components::draw_row(
+        ui,
+        state,
+        "Memory Max (MB)",
+        &mut config.watched_apps[state.ui.config_watched_app_index].memory_max,
+    );
+
+    components::draw_row(
+        ui,
+        state,
+        "CPU Max (%)",
+        &mut config.watched_apps[state.ui.config_watched_app_index].cpu_max,
+    );
+
+    components::draw_row(
+        ui,
+        state,
+        "Process Max",
+        &mut config.watched_apps[state.ui.config_watched_app_index].pids_max,
+    );
+
+    components::draw_row(
+        ui,
+        state,
+        "Hang if CPU Idle (sec)",
+        &mut config.watched_apps[state.ui.config_watched_app_index].cpu_idle_timeout,
+    );
+
+    components::draw_row(
+        ui,
+        state,
+        "Hang if CPU Pinned (sec)",
+        &mut config.watched_apps[state.ui.config_watched_app_index].cpu_pinned_timeout,
+    );
+
+    components::draw_row(
+        ui,
+        state,
+        "Hang if D State (sec)",
+        &mut config.watched_apps[state.ui.config_watched_app_index].uninterruptible_timeout,
+    );
+
+    components::draw_row(
+        ui,
+        state,
+        "Log File",
+        &mut config.watched_apps[state.ui.config_watched_app_index].log_file,
+    );
+
+    components::draw_row(
+        ui,
+        state,
+        "Hang if Log Silent (sec)",
+        &mut config.watched_apps[state.ui.config_watched_app_index].log_silence_timeout,
+    );
+
+    draw_health_checks(ui, state, config);
+
+    draw_telemetry_alerts(ui, state, config);
+
+    draw_relay_rules(ui, state, config);
+
     components::draw_separator(ui);
 
     //
@@ -540,4 +736,881 @@ pub fn draw(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
         "Email limit per Day",
         &mut config.email_client.limit_per_day,
     );
+
+    components::draw_separator(ui);
+
+    //
+    // Remote Control
+    //
+
+    ui.horizontal(|ui| {
+        // Remote Control Label group with Enabled button
+        ui.allocate_ui_with_layout(
+            egui::Vec2 {
+                x: ROW_LABEL_WIDTH,
+                y: ROW_HEIGHT,
+            },
+            egui::Layout {
+                main_dir: egui::Direction::LeftToRight,
+                main_wrap: false,
+                main_align: egui::Align::LEFT,
+                main_justify: true,
+                cross_align: egui::Align::Center,
+                cross_justify: true,
+            },
+            |ui| {
+                //
+                ui.label(
+                    egui::RichText::new("Remote Control")
+                        .text_style(egui::TextStyle::Name("Subheading".into()))
+                        .color(COLOR_TEXT_WHITE),
+                );
+
+                ui.add_space(ROW_GUTTER_SPACE);
+
+                let r = ui.toggle_value(&mut config.remote_control.enabled, "Enabled");
+                if r.changed() {
+                    state.actions.config_edited = true;
+                }
+            },
+        );
+    });
+
+    ui.add_space(ROW_MARGIN);
+
+    components::draw_row(
+        ui,
+        state,
+        "OSC Control Port",
+        &mut config.remote_control.port,
+    );
+
+    components::draw_row(
+        ui,
+        state,
+        "OSC Control Address",
+        &mut config.remote_control.bind_address,
+    );
+
+    components::draw_separator(ui);
+
+    //
+    // OSCQuery
+    //
+
+    ui.horizontal(|ui| {
+        // OSCQuery Label group with Enabled button
+        ui.allocate_ui_with_layout(
+            egui::Vec2 {
+                x: ROW_LABEL_WIDTH,
+                y: ROW_HEIGHT,
+            },
+            egui::Layout {
+                main_dir: egui::Direction::LeftToRight,
+                main_wrap: false,
+                main_align: egui::Align::LEFT,
+                main_justify: true,
+                cross_align: egui::Align::Center,
+                cross_justify: true,
+            },
+            |ui| {
+                //
+                ui.label(
+                    egui::RichText::new("OSCQuery")
+                        .text_style(egui::TextStyle::Name("Subheading".into()))
+                        .color(COLOR_TEXT_WHITE),
+                );
+
+                ui.add_space(ROW_GUTTER_SPACE);
+
+                let r = ui.toggle_value(&mut config.oscquery.enabled, "Enabled");
+                if r.changed() {
+                    state.actions.config_edited = true;
+                }
+            },
+        );
+    });
+
+    ui.add_space(ROW_MARGIN);
+
+    components::draw_row(ui, state, "OSCQuery HTTP Port", &mut config.oscquery.port);
+
+    components::draw_row(
+        ui,
+        state,
+        "OSCQuery Address",
+        &mut config.oscquery.bind_address,
+    );
+
+    components::draw_separator(ui);
+
+    //
+    // Announce
+    //
+
+    ui.horizontal(|ui| {
+        // Announce Label group with Enabled button
+        ui.allocate_ui_with_layout(
+            egui::Vec2 {
+                x: ROW_LABEL_WIDTH,
+                y: ROW_HEIGHT,
+            },
+            egui::Layout {
+                main_dir: egui::Direction::LeftToRight,
+                main_wrap: false,
+                main_align: egui::Align::LEFT,
+                main_justify: true,
+                cross_align: egui::Align::Center,
+                cross_justify: true,
+            },
+            |ui| {
+                //
+                ui.label(
+                    egui::RichText::new("Announce")
+                        .text_style(egui::TextStyle::Name("Subheading".into()))
+                        .color(COLOR_TEXT_WHITE),
+                );
+
+                ui.add_space(ROW_GUTTER_SPACE);
+
+                let r = ui.toggle_value(&mut config.announce.enabled, "Enabled");
+                if r.changed() {
+                    state.actions.config_edited = true;
+                }
+            },
+        );
+    });
+
+    ui.add_space(ROW_MARGIN);
+
+    components::draw_row(ui, state, "Multicast Group", &mut config.announce.group);
+
+    components::draw_row(ui, state, "Multicast Port", &mut config.announce.port);
+
+    components::draw_row(
+        ui,
+        state,
+        "Network Interface",
+        &mut config.announce.interface,
+    );
+
+    components::draw_row(
+        ui,
+        state,
+        "Announce Every (sec)",
+        &mut config.announce.interval,
+    );
+
+    draw_backups(ui, state, config);
+}
+
+//
+// Backups of the config file, newest first, each with a diff against the current file
+// and a button to restore it
+//
+fn draw_backups(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
+    components::draw_separator(ui);
+
+    ui.label(
+        egui::RichText::new("Backups")
+            .text_style(egui::TextStyle::Name("Subheading".into()))
+            .color(COLOR_TEXT_WHITE),
+    );
+
+    ui.add_space(ROW_MARGIN);
+
+    let backups = config::list_backups(state);
+    if backups.is_empty() {
+        ui.label(
+            egui::RichText::new(
+                "None yet. The file is backed up before it is saved, at most once a minute.",
+            )
+            .color(COLOR_OFFWHITE),
+        );
+        return;
+    }
+
+    config::refresh_backup_diff(state);
+
+    for b in backups.iter() {
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = 8.0;
+
+            ui.label(
+                egui::RichText::new(b.time.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .monospace()
+                    .color(COLOR_OFFWHITE),
+            );
+
+            let shown = matches!(&state.ui.config_backup_diff, Some(d) if d.backup == b.path);
+            let r = ui.selectable_label(shown, "Diff");
+            if r.clicked() {
+                config::toggle_backup_diff(state, &b.path);
+            }
+            r.on_hover_cursor(egui::CursorIcon::PointingHand);
+
+            let r = ui.button("Restore");
+            if r.clicked() {
+                config::restore_backup(state, config, b);
+            }
+            r.on_hover_cursor(egui::CursorIcon::PointingHand);
+        });
+    }
+
+    let diff = match &state.ui.config_backup_diff {
+        Some(d) => d,
+        None => return,
+    };
+
+    ui.add_space(ROW_MARGIN);
+
+    let changes = match &diff.changes {
+        Ok(c) => c,
+        Err(e) => {
+            ui.label(egui::RichText::new(e).color(COLOR_RED));
+            return;
+        }
+    };
+    if changes.is_empty() {
+        ui.label(egui::RichText::new("Same as the current file").color(COLOR_OFFWHITE));
+        return;
+    }
+
+    //
+    // Lines removed since the backup in red, and added in green
+    egui::ScrollArea::vertical()
+        .id_source("config_backup_diff")
+        .max_height(DIFF_HEIGHT)
+        .auto_shrink([false, true])
+        .show(ui, |ui| {
+            ui.spacing_mut().item_spacing.y = 2.0;
+
+            for c in changes.iter() {
+                let (text, color) = match c {
+                    config::Change::Same(l) => (format!("  {}", l), COLOR_OFFWHITE),
+                    config::Change::Removed(l) => (format!("- {}", l), COLOR_RED),
+                    config::Change::Added(l) => (format!("+ {}", l), COLOR_GREEN),
+                    config::Change::Skipped(n) => {
+                        (format!("  ... {} unchanged lines", n), COLOR_GRAY_TINT)
+                    }
+                };
+                ui.label(egui::RichText::new(text).monospace().color(color));
+            }
+        });
+}
+
+//
+// Ways to recover when the config file could not be read. The file is never saved over
+// until one is chosen, or the config is saved to another file
+//
+fn draw_recovery(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
+    let error = match &state.json.error {
+        Some(e) => e.clone(),
+        None => {
+            if !state.json.parsed && state.json.changed.is_none() {
+                ui.add_space(ROW_MARGIN);
+                ui.label(
+                    egui::RichText::new(
+                        "The defaults are loaded in memory only, and not saved over the file. Save them to a new file to keep them.",
+                    )
+                    .color(COLOR_YELLOW),
+                );
+            }
+            return;
+        }
+    };
+
+    components::draw_separator(ui);
+
+    ui.label(
+        egui::RichText::new("Config file could not be read")
+            .text_style(egui::TextStyle::Name("Subheading".into()))
+            .color(COLOR_RED),
+    );
+
+    ui.add_space(ROW_MARGIN);
+
+    ui.label(egui::RichText::new(format!("The file {}.", error)).color(COLOR_OFFWHITE));
+
+    ui.add_space(ROW_MARGIN);
+
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 8.0;
+
+        let r = ui.button("Open in Editor");
+        if r.clicked() {
+            config::open_in_editor(state);
+        }
+        r.on_hover_cursor(egui::CursorIcon::PointingHand);
+
+        let r = ui.button("Reload");
+        if r.clicked() {
+            state.actions.config_reload = true;
+        }
+        r.on_hover_cursor(egui::CursorIcon::PointingHand);
+
+        let r = ui.button("Load Defaults");
+        if r.clicked() {
+            config::load_defaults(state, config);
+        }
+        r.on_hover_cursor(egui::CursorIcon::PointingHand);
+
+        let has_backup = config::has_good_backup(state);
+        let r = ui.add_enabled(has_backup, egui::Button::new("Restore Last Good Backup"));
+        if r.clicked() {
+            config::restore_good_backup(state, config);
+        }
+        if has_backup {
+            r.on_hover_cursor(egui::CursorIcon::PointingHand);
+        } else {
+            r.on_disabled_hover_text("There is no backup of a file that was read before.");
+        }
+    });
+}
+
+//
+// A change to the config file on disk that was not applied. The file is never saved over
+// until the operator chooses to load it, or keep the current config over it
+//
+fn draw_changed(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
+    let why = match &state.json.changed {
+        Some(w) => w.clone(),
+        None => return,
+    };
+
+    components::draw_separator(ui);
+
+    ui.label(
+        egui::RichText::new("Config file changed on disk")
+            .text_style(egui::TextStyle::Name("Subheading".into()))
+            .color(COLOR_YELLOW),
+    );
+
+    ui.add_space(ROW_MARGIN);
+
+    ui.label(
+        egui::RichText::new(format!(
+            "The change was not applied, as {}. Edits here are not saved until one is kept.",
+            why
+        ))
+        .color(COLOR_OFFWHITE),
+    );
+
+    ui.add_space(ROW_MARGIN);
+
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 8.0;
+
+        let r = ui.button("Open in Editor");
+        if r.clicked() {
+            config::open_in_editor(state);
+        }
+        r.on_hover_cursor(egui::CursorIcon::PointingHand);
+
+        let r = ui.button("Load From Disk");
+        if r.clicked() {
+            state.actions.config_reload = true;
+        }
+        r.on_hover_cursor(egui::CursorIcon::PointingHand)
+            .on_hover_text("Replace the config here with the file, even with invalid values.");
+
+        let r = ui.button("Keep Current");
+        if r.clicked() {
+            config::keep_current(state, config);
+        }
+        r.on_hover_cursor(egui::CursorIcon::PointingHand)
+            .on_hover_text("Save the config here over the file, which is backed up first.");
+    });
+}
+
+//
+// Health checks of the selected watched app, with create and delete buttons
+//
+fn draw_health_checks(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
+    ui.add_space(ROW_MARGIN);
+
+    let num_checks = config.watched_apps[state.ui.config_watched_app_index]
+        .health_checks
+        .len();
+
+    ui.horizontal(|ui| {
+        // Health Checks Label
+        ui.allocate_ui_with_layout(
+            egui::Vec2 {
+                x: ROW_LABEL_WIDTH,
+                y: ROW_HEIGHT,
+            },
+            egui::Layout {
+                main_dir: egui::Direction::LeftToRight,
+                main_wrap: false,
+                main_align: egui::Align::LEFT,
+                main_justify: true,
+                cross_align: egui::Align::Center,
+                cross_justify: true,
+            },
+            |ui| {
+                ui.label(
+                    egui::RichText::new("Health Checks")
+                        .text_style(egui::TextStyle::Name("Subheading".into()))
+                        .color(COLOR_TEXT_WHITE),
+                );
+
+                ui.add_space(ROW_GUTTER_SPACE);
+            },
+        );
+
+        // Without any checks, the app is checked with the OSC heartbeat fields above
+        if num_checks == 0 {
+            ui.label(egui::RichText::new("OSC heartbeat").color(COLOR_OFFWHITE));
+        }
+
+        // Health Check Create Button
+        ui.allocate_ui_with_layout(
+            egui::Vec2 {
+                x: ui.available_width(),
+                y: ROW_HEIGHT,
+            },
+            egui::Layout {
+                main_dir: egui::Direction::RightToLeft,
+                main_wrap: false,
+                main_align: egui::Align::RIGHT,
+                main_justify: false,
+                cross_align: egui::Align::Center,
+                cross_justify: false,
+            },
+            |ui| {
+                components::format_imagebuttons(ui);
+
+                let icon_w2 = egui::Vec2::new(24.0, 24.0);
+                let icon_create = state.ui.textures.get("icon_create").unwrap();
+
+                let able_to_create_more = num_checks < config::MAX_HEALTH_CHECKS;
+                let r_create = ui.add(
+                    egui::ImageButton::new(&icon_create.1, icon_w2)
+                        .tint(if able_to_create_more {
+                            COLOR_WHITE
+                        } else {
+                            COLOR_GRAY_TINT
+                        })
+                        .sense(if able_to_create_more {
+                            egui::Sense::click()
+                        } else {
+                            egui::Sense::hover()
+                        }),
+                );
+
+                if able_to_create_more {
+                    if r_create.clicked() {
+                        config::create_health_check(config, state);
+                    }
+                    r_create.on_hover_cursor(egui::CursorIcon::PointingHand);
+                } else {
+                    r_create.on_hover_cursor(egui::CursorIcon::NotAllowed);
+                }
+            },
+        );
+    });
+
+    ui.add_space(ROW_MARGIN);
+
+    let mut delete = None;
+    for i in 0..num_checks {
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new(format!("Check {}", i + 1))
+                    .text_style(egui::TextStyle::Name("TextButton".into()))
+                    .color(COLOR_OFFWHITE),
+            );
+
+            // Health Check Delete Button
+            ui.allocate_ui_with_layout(
+                egui::Vec2 {
+                    x: ui.available_width(),
+                    y: ROW_HEIGHT,
+                },
+                egui::Layout {
+                    main_dir: egui::Direction::RightToLeft,
+                    main_wrap: false,
+                    main_align: egui::Align::RIGHT,
+                    main_justify: false,
+                    cross_align: egui::Align::Center,
+                    cross_justify: false,
+                },
+                |ui| {
+                    components::format_imagebuttons(ui);
+
+                    let icon_w2 = egui::Vec2::new(24.0, 24.0);
+                    let icon_delete = state.ui.textures.get("icon_delete").unwrap();
+
+                    let r_delete = ui.add(egui::ImageButton::new(&icon_delete.1, icon_w2));
+                    if r_delete.clicked() {
+                        delete = Some(i);
+                    }
+                    r_delete.on_hover_cursor(egui::CursorIcon::PointingHand);
+                },
+            );
+        });
+
+        let check = &mut config.watched_apps[state.ui.config_watched_app_index].health_checks[i];
+
+        components::draw_row(ui, state, "Type", &mut check.kind);
+        components::draw_row(ui, state, "Target", &mut check.target);
+        components::draw_row(ui, state, "Expect (http)", &mut check.expect);
+        components::draw_row(ui, state, "Interval (sec)", &mut check.interval);
+        components::draw_row(ui, state, "Timeout (sec)", &mut check.timeout);
+    }
+
+    // Delete after drawing, so the indices above stay valid for this frame
+    if let Some(i) = delete {
+        config::delete_health_check(config, state, i);
+    }
+}
+
+fn draw_telemetry_alerts(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
+    ui.add_space(ROW_MARGIN);
+
+    let num_alerts = config.watched_apps[state.ui.config_watched_app_index]
+        .telemetry_alerts
+        .len();
+
+    ui.horizontal(|ui| {
+        // Telemetry Alerts Label
+        ui.allocate_ui_with_layout(
+            egui::Vec2 {
+                x: ROW_LABEL_WIDTH,
+                y: ROW_HEIGHT,
+            },
+            egui::Layout {
+                main_dir: egui::Direction::LeftToRight,
+                main_wrap: false,
+                main_align: egui::Align::LEFT,
+                main_justify: true,
+                cross_align: egui::Align::Center,
+                cross_justify: true,
+            },
+            |ui| {
+                ui.label(
+                    egui::RichText::new("Telemetry Alerts")
+                        .text_style(egui::TextStyle::Name("Subheading".into()))
+                        .color(COLOR_TEXT_WHITE),
+                );
+
+                ui.add_space(ROW_GUTTER_SPACE);
+            },
+        );
+
+        if num_alerts == 0 {
+            ui.label(egui::RichText::new("None").color(COLOR_OFFWHITE));
+        }
+
+        // Telemetry Alert Create Button
+        ui.allocate_ui_with_layout(
+            egui::Vec2 {
+                x: ui.available_width(),
+                y: ROW_HEIGHT,
+            },
+            egui::Layout {
+                main_dir: egui::Direction::RightToLeft,
+                main_wrap: false,
+                main_align: egui::Align::RIGHT,
+                main_justify: false,
+                cross_align: egui::Align::Center,
+                cross_justify: false,
+            },
+            |ui| {
+                components::format_imagebuttons(ui);
+
+                let icon_w2 = egui::Vec2::new(24.0, 24.0);
+                let icon_create = state.ui.textures.get("icon_create").unwrap();
+
+                let able_to_create_more = num_alerts < config::MAX_TELEMETRY_ALERTS;
+                let r_create = ui.add(
+                    egui::ImageButton::new(&icon_create.1, icon_w2)
+                        .tint(if able_to_create_more {
+                            COLOR_WHITE
+                        } else {
+                            COLOR_GRAY_TINT
+                        })
+                        .sense(if able_to_create_more {
+                            egui::Sense::click()
+                        } else {
+                            egui::Sense::hover()
+                        }),
+                );
+
+                if able_to_create_more {
+                    if r_create.clicked() {
+                        config::create_telemetry_alert(config, state);
+                    }
+                    r_create.on_hover_cursor(egui::CursorIcon::PointingHand);
+                } else {
+                    r_create.on_hover_cursor(egui::CursorIcon::NotAllowed);
+                }
+            },
+        );
+    });
+
+    ui.add_space(ROW_MARGIN);
+
+    let mut delete = None;
+    for i in 0..num_alerts {
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new(format!("Alert {}", i + 1))
+                    .text_style(egui::TextStyle::Name("TextButton".into()))
+                    .color(COLOR_OFFWHITE),
+            );
+
+            // Telemetry Alert Delete Button
+            ui.allocate_ui_with_layout(
+                egui::Vec2 {
+                    x: ui.available_width(),
+                    y: ROW_HEIGHT,
+                },
+                egui::Layout {
+                    main_dir: egui::Direction::RightToLeft,
+                    main_wrap: false,
+                    main_align: egui::Align::RIGHT,
+                    main_justify: false,
+                    cross_align: egui::Align::Center,
+                    cross_justify: false,
+                },
+                |ui| {
+                    components::format_imagebuttons(ui);
+
+                    let icon_w2 = egui::Vec2::new(24.0, 24.0);
+                    let icon_delete = state.ui.textures.get("icon_delete").unwrap();
+
+                    let r_delete = ui.add(egui::ImageButton::new(&icon_delete.1, icon_w2));
+                    if r_delete.clicked() {
+                        delete = Some(i);
+                    }
+                    r_delete.on_hover_cursor(egui::CursorIcon::PointingHand);
+                },
+            );
+        });
+
+        let alert = &mut config.watched_apps[state.ui.config_watched_app_index].telemetry_alerts[i];
+
+        components::draw_row(ui, state, "Rule", alert);
+    }
+
+    // Delete after drawing, so the indices above stay valid for this frame
+    if let Some(i) = delete {
+        config::delete_telemetry_alert(config, state, i);
+    }
+}
+
+fn draw_relay_rules(ui: &mut egui::Ui, state: &mut State, config: &mut Config) {
+    ui.add_space(ROW_MARGIN);
+
+    let num_rules = config.watched_apps[state.ui.config_watched_app_index]
+        .relay_rules
+        .len();
+
+    ui.horizontal(|ui| {
+        // Relay Rules Label
+        ui.allocate_ui_with_layout(
+            egui::Vec2 {
+                x: ROW_LABEL_WIDTH,
+                y: ROW_HEIGHT,
+            },
+            egui::Layout {
+                main_dir: egui::Direction::LeftToRight,
+                main_wrap: false,
+                main_align: egui::Align::LEFT,
+                main_justify: true,
+                cross_align: egui::Align::Center,
+                cross_justify: true,
+            },
+            |ui| {
+                ui.label(
+                    egui::RichText::new("Relay Rules")
+                        .text_style(egui::TextStyle::Name("Subheading".into()))
+                        .color(COLOR_TEXT_WHITE),
+                );
+
+                ui.add_space(ROW_GUTTER_SPACE);
+            },
+        );
+
+        if num_rules == 0 {
+            ui.label(egui::RichText::new("None").color(COLOR_OFFWHITE));
+        }
+
+        // Relay Rule Create Button
+        ui.allocate_ui_with_layout(
+            egui::Vec2 {
+                x: ui.available_width(),
+                y: ROW_HEIGHT,
+            },
+            egui::Layout {
+                main_dir: egui::Direction::RightToLeft,
+                main_wrap: false,
+                main_align: egui::Align::RIGHT,
+                main_justify: false,
+                cross_align: egui::Align::Center,
+                cross_justify: false,
+            },
+            |ui| {
+                components::format_imagebuttons(ui);
+
+                let icon_w2 = egui::Vec2::new(24.0, 24.0);
+                let icon_create = state.ui.textures.get("icon_create").unwrap();
+
+                let able_to_create_more = num_rules < config::MAX_RELAY_RULES;
+                let r_create = ui.add(
+                    egui::ImageButton::new(&icon_create.1, icon_w2)
+                        .tint(if able_to_create_more {
+                            COLOR_WHITE
+                        } else {
+                            COLOR_GRAY_TINT
+                        })
+                        .sense(if able_to_create_more {
+                            egui::Sense::click()
+                        } else {
+                            egui::Sense::hover()
+                        }),
+                );
+
+                if able_to_create_more {
+                    if r_create.clicked() {
+                        config::create_relay_rule(config, state);
+                    }
+                    r_create.on_hover_cursor(egui::CursorIcon::PointingHand);
+                } else {
+                    r_create.on_hover_cursor(egui::CursorIcon::NotAllowed);
+                }
+            },
+        );
+    });
+
+    ui.add_space(ROW_MARGIN);
+
+    let mut delete = None;
+    for i in 0..num_rules {
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new(format!("Relay {}", i + 1))
+                    .text_style(egui::TextStyle::Name("TextButton".into()))
+                    .color(COLOR_OFFWHITE),
+            );
+
+            // Relay Rule Delete Button
+            ui.allocate_ui_with_layout(
+                egui::Vec2 {
+                    x: ui.available_width(),
+                    y: ROW_HEIGHT,
+                },
+                egui::Layout {
+                    main_dir: egui::Direction::RightToLeft,
+                    main_wrap: false,
+                    main_align: egui::Align::RIGHT,
+                    main_justify: false,
+                    cross_align: egui::Align::Center,
+                    cross_justify: false,
+                },
+                |ui| {
+                    components::format_imagebuttons(ui);
+
+                    let icon_w2 = egui::Vec2::new(24.0, 24.0);
+                    let icon_delete = state.ui.textures.get("icon_delete").unwrap();
+
+                    let r_delete = ui.add(egui::ImageButton::new(&icon_delete.1, icon_w2));
+                    if r_delete.clicked() {
+                        delete = Some(i);
+                    }
+                    r_delete.on_hover_cursor(egui::CursorIcon::PointingHand);
+                },
+            );
+        });
+
+        let rule = &mut config.watched_apps[state.ui.config_watched_app_index].relay_rules[i];
+
+        components::draw_row(ui, state, "Rule", rule);
+    }
+
+    // Delete after drawing, so the indices above stay valid for this frame
+    if let Some(i) = delete {
+        config::delete_relay_rule(config, state, i);
+    }
+}
+
+//
+// Overlay on the locked config, dimming the header and the values, with why it is locked
+// and the ways to edit it
+//
+fn draw_lock(ui: &mut egui::Ui, state: &mut State, header: egui::Rect, rect: egui::Rect) {
+    let scrim = COLOR_DARK_GREY.linear_multiply(0.8);
+    ui.painter().rect_filled(header, 0.0, scrim);
+    ui.painter().rect_filled(rect, 0.0, scrim);
+
+    let card = egui::Rect::from_min_size(
+        rect.min + egui::Vec2::new(0.0, LOCK_OFFSET),
+        egui::Vec2::new(rect.width(), 0.0),
+    );
+    ui.allocate_ui_at_rect(card, |ui| {
+        egui::Frame::none()
+            .fill(COLOR_MED_GREY)
+            .rounding(8.0)
+            .inner_margin(egui::Margin::same(16.0))
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+
+                ui.label(
+                    egui::RichText::new("Config is locked while apps are watched")
+                        .text_style(egui::TextStyle::Name("Subheading".into()))
+                        .color(COLOR_TEXT_WHITE),
+                );
+
+                ui.add_space(ROW_MARGIN);
+
+                ui.label(
+                    egui::RichText::new(
+                        "Edits are applied to the running apps, so a change could restart or stop them in the middle of a show.",
+                    )
+                    .color(COLOR_OFFWHITE),
+                );
+
+                ui.add_space(ROW_MARGIN);
+
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 8.0;
+
+                    let r = ui.button("Stop All & Edit");
+                    if r.clicked() {
+                        state.actions.destroy_listeners = true;
+                    }
+                    r.on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .on_hover_text("Stop watching and kill every app, then edit the config");
+
+                    let r = ui.button("Edit & Hot-Apply");
+                    if r.clicked() {
+                        state.ui.config_unlocked = true;
+                    }
+                    r.on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .on_hover_text("Keep the apps running, and apply each edit to them");
+                });
+            });
+    });
+}
+
+// Reminder that edits are applied to the running apps, with a way to lock the config again
+fn draw_unlocked(ui: &mut egui::Ui, state: &mut State) {
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 8.0;
+
+        ui.label(
+            egui::RichText::new("Apps are watched, edits are applied to them as they are made.")
+                .color(COLOR_YELLOW),
+        );
+
+        let r = ui.button("Lock");
+        if r.clicked() {
+            state.ui.config_unlocked = false;
+        }
+        r.on_hover_cursor(egui::CursorIcon::PointingHand);
+    });
+
+    ui.add_space(SECTION_HEADING_MARGIN);
 }
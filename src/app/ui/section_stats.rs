@@ -1,5 +1,9 @@
 use crate::app::ui::*;
-pub fn draw(ui: &mut egui::Ui, _state: &mut State, _config: &mut Config) {
+
+// How far back to show memory usage in the plot
+const PLOT_WINDOW_SECS: u64 = 60 * 60;
+
+pub fn draw(ui: &mut egui::Ui, _state: &mut State, _config: &mut Config, stats: &Stats) {
     ui.allocate_ui_with_layout(
         egui::Vec2 {
             x: ui.available_width(),
@@ -21,4 +25,74 @@ pub fn draw(ui: &mut egui::Ui, _state: &mut State, _config: &mut Config) {
     );
 
     ui.add_space(SECTION_HEADING_MARGIN);
+
+    if !stats.supported {
+        ui.label(
+            egui::RichText::new("Resource monitoring is only available on Linux.")
+                .color(COLOR_OFFWHITE),
+        );
+        return;
+    }
+
+    if stats.apps.is_empty() {
+        ui.label(
+            egui::RichText::new("No watched apps have been sampled yet.").color(COLOR_OFFWHITE),
+        );
+        return;
+    }
+
+    // Sort by name so the order is stable between frames
+    let mut names: Vec<&String> = stats.apps.keys().collect();
+    names.sort();
+
+    for name in names {
+        let app_stats = &stats.apps[name];
+
+        components::draw_separator(ui);
+
+        ui.label(
+            egui::RichText::new(name)
+                .text_style(egui::TextStyle::Name("Subheading".into()))
+                .color(COLOR_TEXT_WHITE),
+        );
+
+        ui.add_space(ROW_MARGIN);
+
+        components::draw_row_static(ui, "PID", &mut app_stats.pid.to_string());
+
+        if let Some(s) = app_stats.latest() {
+            components::draw_row_static(ui, "CPU", &mut format!("{:.1} %", s.cpu));
+            components::draw_row_static(
+                ui,
+                "Memory",
+                &mut format!("{:.1} MB", s.rss as f64 / 1024.0 / 1024.0),
+            );
+            components::draw_row_static(ui, "Threads", &mut s.threads.to_string());
+            components::draw_row_static(ui, "Open Files", &mut s.fds.to_string());
+
+            //
+            // Memory usage over the last hour, in MB against minutes ago
+            let points: egui::plot::PlotPoints = app_stats
+                .samples
+                .iter()
+                .filter(|p| s.time.saturating_sub(p.time).as_secs() <= PLOT_WINDOW_SECS)
+                .map(|p| {
+                    [
+                        -(s.time.saturating_sub(p.time).as_secs_f64() / 60.0),
+                        p.rss as f64 / 1024.0 / 1024.0,
+                    ]
+                })
+                .collect();
+
+            egui::plot::Plot::new(format!("stats_memory_{}", name))
+                .height(120.0)
+                .allow_drag(false)
+                .allow_zoom(false)
+                .allow_scroll(false)
+                .include_y(0.0)
+                .show(ui, |plot_ui| {
+                    plot_ui.line(egui::plot::Line::new(points).color(COLOR_YELLOW));
+                });
+        }
+    }
 }
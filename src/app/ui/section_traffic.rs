@@ -0,0 +1,224 @@
+use crate::app::osc::{Direction, Packet};
+use crate::app::ui::*;
+
+// Height of the scrolling list of packets
+const LIST_HEIGHT: f32 = 480.0;
+
+//
+// OSC traffic inspector, every packet sent to and received from the selected app,
+// newest at the bottom, to debug heartbeats that do not arrive
+//
+pub fn draw(ui: &mut egui::Ui, state: &mut State, apps: &Apps) {
+    ui.allocate_ui_with_layout(
+        egui::Vec2 {
+            x: ui.available_width(),
+            y: ROW_HEIGHT,
+        },
+        egui::Layout {
+            main_dir: egui::Direction::LeftToRight,
+            main_wrap: false,
+            main_align: egui::Align::LEFT,
+            main_justify: false,
+            cross_align: egui::Align::Center,
+            cross_justify: false,
+        },
+        |ui| {
+            //
+            // Header
+            ui.heading(egui::RichText::new("Traffic").color(COLOR_TEXT_WHITE));
+        },
+    );
+
+    ui.add_space(SECTION_HEADING_MARGIN);
+
+    // Only apps with an OSC heartbeat check have an interface to inspect
+    let names: Vec<&str> = apps
+        .iter()
+        .filter(|a| a.traffic().is_some())
+        .map(|a| a.name())
+        .collect();
+
+    if names.is_empty() {
+        ui.label(
+            egui::RichText::new("No watched apps have an OSC heartbeat check.")
+                .color(COLOR_OFFWHITE),
+        );
+        return;
+    }
+
+    // Keep the selected app, unless it is no longer watched
+    if !names.contains(&state.ui.traffic_app.as_str()) {
+        state.ui.traffic_app = names[0].to_string();
+        state.ui.traffic_paused = None;
+    }
+
+    //
+    // App selection
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 6.0;
+        for name in names.iter() {
+            let selected = state.ui.traffic_app == *name;
+            let r = ui.selectable_label(selected, *name);
+            if r.clicked() && !selected {
+                state.ui.traffic_app = name.to_string();
+                state.ui.traffic_paused = None;
+            }
+            r.on_hover_cursor(egui::CursorIcon::PointingHand);
+        }
+    });
+
+    ui.add_space(ROW_MARGIN);
+
+    let app = match apps.iter().find(|a| a.name() == state.ui.traffic_app) {
+        Some(a) => a,
+        None => return,
+    };
+
+    //
+    // Filter and pause
+    ui.horizontal(|ui| {
+        ui.allocate_ui_with_layout(
+            egui::Vec2 {
+                x: ROW_LABEL_WIDTH,
+                y: ROW_HEIGHT,
+            },
+            egui::Layout {
+                main_dir: egui::Direction::LeftToRight,
+                main_wrap: false,
+                main_align: egui::Align::LEFT,
+                main_justify: true,
+                cross_align: egui::Align::Center,
+                cross_justify: true,
+            },
+            |ui| {
+                ui.label(egui::RichText::new("Filter").color(COLOR_OFFWHITE));
+
+                ui.add_space(ROW_GUTTER_SPACE);
+
+                // Pausing keeps the packets as they are, as old ones keep being dropped
+                let mut paused = state.ui.traffic_paused.is_some();
+                let r = ui.toggle_value(&mut paused, "Pause");
+                if r.changed() {
+                    state.ui.traffic_paused = match app.traffic() {
+                        Some(t) if paused => Some(t.packets().iter().cloned().collect()),
+                        _ => None,
+                    };
+                }
+            },
+        );
+
+        ui.allocate_ui_with_layout(
+            egui::Vec2 {
+                x: ui.available_width(),
+                y: ROW_HEIGHT,
+            },
+            egui::Layout {
+                main_dir: egui::Direction::LeftToRight,
+                main_wrap: false,
+                main_align: egui::Align::LEFT,
+                main_justify: true,
+                cross_align: egui::Align::Center,
+                cross_justify: true,
+            },
+            |ui| {
+                let text_edit = egui::TextEdit::singleline(&mut state.ui.traffic_filter)
+                    .hint_text("address, argument or source")
+                    .margin(egui::Vec2::new(16.0, 0.0))
+                    .text_color(COLOR_TEXT_WHITE)
+                    .vertical_align(egui::Align::Center)
+                    .frame(true);
+                ui.add(text_edit);
+            },
+        );
+    });
+
+    ui.add_space(ROW_MARGIN);
+
+    let traffic = match app.traffic() {
+        Some(t) => t,
+        None => return,
+    };
+    let packets: Vec<&Packet> = match &state.ui.traffic_paused {
+        Some(snapshot) => snapshot.iter().collect(),
+        None => traffic.packets().iter().collect(),
+    };
+
+    //
+    // Each line of each packet is a row, as the packet and its line. Packets are only
+    // formatted to be filtered, and when their rows are scrolled into view
+    let filter = state.ui.traffic_filter.to_lowercase();
+    let mut rows: Vec<(usize, usize)> = Vec::new();
+    for (index, packet) in packets.iter().enumerate() {
+        if !filter.is_empty() {
+            let matched = header(packet).to_lowercase().contains(&filter)
+                || packet
+                    .lines()
+                    .iter()
+                    .any(|l| l.to_lowercase().contains(&filter));
+            if !matched {
+                continue;
+            }
+        }
+        rows.extend((0..packet.line_count()).map(|line| (index, line)));
+    }
+
+    //
+    // Packets, oldest first, sticking to the newest while not scrolled up
+    let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+    egui::ScrollArea::vertical()
+        .id_source("traffic_packets")
+        .max_height(LIST_HEIGHT)
+        .auto_shrink([false, false])
+        .stick_to_bottom(true)
+        .show_rows(ui, row_height, rows.len(), |ui, range| {
+            ui.spacing_mut().item_spacing.y = 2.0;
+
+            let mut formatted: Option<(usize, String, Vec<String>)> = None;
+            for &(index, line) in rows[range].iter() {
+                let packet = packets[index];
+                if formatted.as_ref().map(|(i, _, _)| *i) != Some(index) {
+                    formatted = Some((index, header(packet), packet.lines()));
+                }
+                let (_, header, lines) = formatted.as_ref().unwrap();
+
+                let color = match packet.direction {
+                    Direction::Sent => COLOR_YELLOW,
+                    Direction::Received => COLOR_GREEN,
+                };
+                let text_color = match packet.content {
+                    Ok(_) => COLOR_TEXT_WHITE,
+                    Err(_) => COLOR_RED,
+                };
+
+                ui.horizontal_top(|ui| {
+                    ui.spacing_mut().item_spacing.x = 12.0;
+                    // Later lines of a packet are lined up under its first
+                    let header = if line == 0 {
+                        header.clone()
+                    } else {
+                        " ".repeat(header.chars().count())
+                    };
+                    ui.label(egui::RichText::new(header).monospace().color(color));
+                    ui.label(
+                        egui::RichText::new(lines.get(line).map(|l| l.as_str()).unwrap_or(""))
+                            .monospace()
+                            .color(text_color),
+                    );
+                });
+            }
+        });
+}
+
+// Time, direction and peer of a packet
+fn header(packet: &Packet) -> String {
+    let arrow = match packet.direction {
+        Direction::Sent => "->",
+        Direction::Received => "<-",
+    };
+    format!(
+        "{} {} {}",
+        packet.time.format("%H:%M:%S%.3f"),
+        arrow,
+        packet.peer
+    )
+}
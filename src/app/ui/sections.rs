@@ -25,7 +25,13 @@ pub fn style(ui: &mut egui::Ui) {
 // Nested containers for proper layout of all sections, as well as
 // state dependent draw calls to each section
 //
-pub fn draw(context: &egui::Context, state: &mut State, config: &mut Config) {
+pub fn draw(
+    context: &egui::Context,
+    state: &mut State,
+    config: &mut Config,
+    apps: &Apps,
+    stats: &Stats,
+) {
     // Draw main content area
     egui::CentralPanel::default()
         // Create a medium gray background color for whole frame,
@@ -80,15 +86,19 @@ pub fn draw(context: &egui::Context, state: &mut State, config: &mut Config) {
                                     match state.ui.active_tab {
                                         TabState::Config => {
                                             // Draw the Config section
-                                            section_config::draw(ui, state, config)
+                                            section_config::draw(ui, state, config, apps)
                                         }
                                         TabState::Apps => {
                                             // Draw the Apps section
-                                            section_apps::draw(ui, state, config)
+                                            section_apps::draw(ui, state, config, apps, stats)
                                         }
                                         TabState::Stats => {
                                             // Draw the Stats section
-                                            section_stats::draw(ui, state, config)
+                                            section_stats::draw(ui, state, config, stats)
+                                        }
+                                        TabState::Traffic => {
+                                            // Draw the OSC Traffic inspector
+                                            section_traffic::draw(ui, state, apps)
                                         }
                                     }
 